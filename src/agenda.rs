@@ -0,0 +1,169 @@
+//! What is coming up, day by day
+//!
+//! Open actions with a do-date are sorted into overdue, today, tomorrow and later, where later runs
+//! to the end of the window asked for. A do-date at midnight is taken as the whole day, since that
+//! is where a date without a time lands.
+use chrono::{DateTime, Days, Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::entities::{Action, ActionPath, ActionState, ancestors, walk};
+use crate::ids::shortest_prefixes;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgendaEntry {
+    pub path: ActionPath,
+    pub name: String,
+    pub state: ActionState,
+    /// The name of the parent action, if there is one
+    pub parent: Option<String>,
+    pub short_id: Option<String>,
+    pub do_date_time: DateTime<Local>,
+    /// Whether the do-date says a time of day rather than just a day
+    pub timed: bool,
+}
+
+/// Every bucket is always there, empty or not, so whatever reads the JSON can count on the keys
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Agenda {
+    pub overdue: Vec<AgendaEntry>,
+    pub today: Vec<AgendaEntry>,
+    pub tomorrow: Vec<AgendaEntry>,
+    pub later: Vec<AgendaEntry>,
+}
+
+impl Agenda {
+    /// The buckets in the order they are shown, under their headings
+    pub fn buckets(&self) -> [(&'static str, &[AgendaEntry]); 4] {
+        [
+            ("Overdue", &self.overdue),
+            ("Today", &self.today),
+            ("Tomorrow", &self.tomorrow),
+            ("Later", &self.later),
+        ]
+    }
+}
+
+/// The open actions due before the end of the `days` starting today, including the ones that are
+/// already overdue, each bucket in do-date order
+///
+/// One day only covers today, two adds tomorrow and anything longer fills later.
+pub fn build_agenda(action_list: &[Action], days: u64, now: DateTime<Local>) -> Agenda {
+    let today = now.date_naive();
+    let prefixes = shortest_prefixes(action_list);
+
+    let mut entries: Vec<AgendaEntry> = walk(action_list)
+        .into_iter()
+        .filter(|(path, action)| {
+            action.common.state.is_open()
+                && ancestors(action_list, path).all(|ancestor| ancestor.common.state.is_open())
+        })
+        .filter_map(|(path, action)| {
+            let do_date_time = action.common.do_date_time?;
+            Some(AgendaEntry {
+                parent: ancestors(action_list, &path)
+                    .next()
+                    .map(|parent| parent.common.name.clone()),
+                name: action.common.name.clone(),
+                state: action.common.state,
+                short_id: action.common.id.and_then(|id| prefixes.get(&id).cloned()),
+                timed: do_date_time.time() != NaiveTime::MIN,
+                do_date_time,
+                path,
+            })
+        })
+        .collect();
+    // sorting is stable, so actions due at the same time stay in file order
+    entries.sort_by_key(|entry| entry.do_date_time);
+
+    let mut agenda = Agenda::default();
+    for entry in entries {
+        let offset = (entry.do_date_time.date_naive() - today).num_days();
+        let bucket = match offset {
+            ..0 => &mut agenda.overdue,
+            0 => &mut agenda.today,
+            1 if days > 1 => &mut agenda.tomorrow,
+            _ if offset < days as i64 => &mut agenda.later,
+            _ => continue,
+        };
+        bucket.push(entry);
+    }
+    agenda
+}
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+
+fn heading_color(heading: &str) -> &'static str {
+    match heading {
+        "Overdue" => "\x1b[1;31m",
+        "Today" => "\x1b[1;33m",
+        "Tomorrow" => "\x1b[1;36m",
+        _ => "\x1b[1;34m",
+    }
+}
+
+/// The agenda for the terminal, a heading per bucket with anything in it and a line per action
+///
+/// Today and tomorrow give the date in their heading, so their actions only show the time of day,
+/// the others show the date of each action. With `color` the headings are colored and the parents
+/// dimmed using ANSI escapes.
+pub fn render_agenda(agenda: &Agenda, now: DateTime<Local>, color: bool) -> String {
+    let paint = |code: &str, text: &str| match color {
+        true => format!("{}{}{}", code, text, RESET),
+        false => text.to_string(),
+    };
+
+    let mut sections = Vec::new();
+    for (heading, entries) in agenda.buckets() {
+        if entries.is_empty() {
+            continue;
+        }
+        let day = match heading {
+            "Today" => Some(now),
+            "Tomorrow" => now.checked_add_days(Days::new(1)),
+            _ => None,
+        };
+        let title = match day {
+            Some(day) => format!("{} {}", heading, day.format("%a %b %-d")),
+            None => heading.to_string(),
+        };
+
+        let mut lines = vec![paint(heading_color(heading), &title)];
+        for entry in entries {
+            let time = entry.do_date_time.format("%H:%M").to_string();
+            // overdue and later can be in any year, today and tomorrow go by the heading
+            let when = match (day.is_some(), entry.timed) {
+                (true, true) => time,
+                (true, false) => " ".repeat(5),
+                (false, true) => format!("{} {}", entry.do_date_time.format("%a %b %d %Y"), time),
+                (false, false) => {
+                    format!(
+                        "{}{}",
+                        entry.do_date_time.format("%a %b %d %Y"),
+                        " ".repeat(6)
+                    )
+                }
+            };
+            let id = entry
+                .short_id
+                .as_ref()
+                .map(|id| format!(" #{}", id))
+                .unwrap_or_default();
+            let parent = entry
+                .parent
+                .as_ref()
+                .map(|parent| format!(" {}", paint(DIM, &format!("in {}", parent))))
+                .unwrap_or_default();
+            lines.push(format!(
+                "  {}  ({}) {}{}{}",
+                when, entry.state, entry.name, id, parent
+            ));
+        }
+        sections.push(lines.join("\n"));
+    }
+
+    match sections.is_empty() {
+        true => "nothing due\n".to_string(),
+        false => format!("{}\n", sections.join("\n\n")),
+    }
+}
@@ -0,0 +1,119 @@
+//! Moving finished work out of the action file
+//!
+//! A root action whose whole subtree is completed or cancelled is cut out of the file exactly as
+//! it was written, ids and completion dates included, and filed under the month it was last
+//! finished in. The archive files are ordinary action files, so every command can still read them.
+//! Actions left behind stop listing archived ones as what they are blocked by, see
+//! [`crate::dependencies::drop_blockers`].
+use chrono::{DateTime, Duration, Local};
+use uuid::Uuid;
+
+use crate::edits::{TextEdit, apply_edits, line_after, line_start, map_source};
+use crate::entities::{Action, depth_first};
+
+/// A root action taken out of the file, along with its subtree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedAction {
+    /// The month it belongs to as `YYYY-MM`, which names its archive file
+    pub month: String,
+    pub name: String,
+    /// Its lines exactly as they were in the file
+    pub text: String,
+    /// The ids in its subtree, which other actions may still list as what they are blocked by
+    pub ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Archive {
+    /// The file with every archived action gone
+    pub remaining: String,
+    pub archived: Vec<ArchivedAction>,
+}
+
+/// When the last action of the subtree was finished, if any of them say
+fn last_finished(action: &Action) -> Option<DateTime<Local>> {
+    depth_first(std::slice::from_ref(action))
+        .filter_map(|action| action.common.completed_date_time)
+        .max()
+}
+
+fn is_closed(action: &Action) -> bool {
+    depth_first(std::slice::from_ref(action)).all(|action| !action.common.state.is_open())
+}
+
+/// Split the fully closed root actions off the source
+///
+/// With `older_than`, only subtrees last finished at least that long before `now` go, and those
+/// without any completion date stay since there is no telling how old they are. Without it, those
+/// are filed under the current month.
+pub fn archive_actions(
+    source: &str,
+    older_than: Option<Duration>,
+    now: DateTime<Local>,
+) -> Result<Archive, String> {
+    let source_map = map_source(source)?;
+    let mut edits = Vec::new();
+    let mut archived = Vec::new();
+    for (index, action) in source_map.actions.iter().enumerate() {
+        if !is_closed(action) {
+            continue;
+        }
+        let finished = last_finished(action);
+        let old_enough = match (older_than, finished) {
+            (None, _) => true,
+            (Some(age), Some(finished)) => {
+                finished.checked_add_signed(age).ok_or_else(|| {
+                    format!(
+                        "{} days is longer ago than any date there can be",
+                        age.num_days()
+                    )
+                })? <= now
+            }
+            (Some(_), None) => false,
+        };
+        if !old_enough {
+            continue;
+        }
+        let location = source_map
+            .location(&[index])
+            .ok_or_else(|| format!("{} has no location", action.common.name))?;
+        let range = line_start(source, location.range.start)..line_after(source, &location.range);
+
+        let mut text = source[range.clone()].to_string();
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        archived.push(ArchivedAction {
+            month: finished.unwrap_or(now).format("%Y-%m").to_string(),
+            name: action.common.name.clone(),
+            text,
+            ids: depth_first(std::slice::from_ref(action))
+                .filter_map(|action| action.common.id)
+                .collect(),
+        });
+        edits.push(TextEdit {
+            range,
+            text: String::new(),
+        });
+    }
+
+    let remaining = apply_edits(source, &edits);
+    map_source(&remaining)
+        .map_err(|e| format!("archiving would leave the file unreadable: {}", e))?;
+    Ok(Archive {
+        remaining,
+        archived,
+    })
+}
+
+/// The archive file contents once `archived` is added to the end of `existing`
+pub fn append_to_archive(existing: &str, archived: &[&ArchivedAction]) -> String {
+    let mut contents = existing.to_string();
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for action in archived {
+        contents.push_str(&action.text);
+    }
+    contents
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args as ClapArgs, CommandFactory, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
@@ -15,6 +15,11 @@ pub fn get_cli_map() -> Result<Args, String> {
         .map_err(|e| format!("Failed to deserialize CLI args to map: {}", e))
 }
 
+/// The same help `cliche --help` prints
+pub fn get_help() -> String {
+    Cli::command().render_help().to_string()
+}
+
 #[derive(Parser, Serialize, Deserialize)]
 #[command(version, about, long_about = None)]
 struct Cli {
@@ -26,14 +31,350 @@ struct Cli {
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub debug: u8,
 
+    /// Without a subcommand the action file opens in a full screen view
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Commands>,
 }
 
+// the subcommand name ends up under `command.name` in the options map
 #[derive(Subcommand, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "lowercase")]
 enum Commands {
+    /// Print the actions in the action file
     Read {
+        /// Include completed and cancelled actions
         #[arg(short, long)]
         all: bool,
+
+        /// How to print the actions, defaults to the `format` setting, or table in a terminal and
+        /// native otherwise
+        #[arg(short, long, value_enum)]
+        format: Option<OutputFormat>,
+
+        /// Only show actions matching a query such as `priority <= 2 and context in (@work)`
+        filter: Vec<String>,
+
+        /// Comma separated fields to sort by, `-` in front of one sorts it descending
+        #[arg(short, long)]
+        sort: Option<String>,
+
+        /// Show the actions in groups
+        #[arg(short, long, value_enum)]
+        group_by: Option<GroupBy>,
+
+        /// Show at most this many actions
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+
+        /// Number the actions so the next command can pick one with `[3]` as `3`, defaults to the
+        /// `numbering` setting
+        #[arg(long)]
+        number: bool,
+    },
+    /// Add a new action to the action file
+    Create {
+        /// Name of the new action
+        #[serde(rename = "action_name")]
+        name: String,
+
+        /// Longer description of the action
+        #[arg(long)]
+        description: Option<String>,
+
+        #[arg(short, long)]
+        priority: Option<usize>,
+
+        /// Context the action can be done in, can be given more than once (e.g. @home)
+        #[arg(short = 'x', long = "context", value_name = "CONTEXT")]
+        contexts: Vec<String>,
+
+        /// When the action should be done (e.g. 2026-10-20T09:00)
+        #[arg(long = "do", value_name = "DATE")]
+        do_date: Option<String>,
+
+        /// Repeat the action from its do-date (e.g. "every mon,thu at 09:00", "every 2w",
+        /// "every month", FREQ=WEEKLY;BYDAY=MO or RW Mon), intervals are kept as an every-2w
+        /// context and take their time of day from the do-date
+        #[arg(long, value_name = "RULE", requires = "do_date")]
+        every: Option<String>,
+
+        /// Id of the action to nest the new action under
+        #[arg(long, value_name = "ID")]
+        parent: Option<String>,
+
+        /// Id of an action that has to be finished first, can be given more than once
+        #[arg(long = "blocked-by", value_name = "ID")]
+        blocked_by: Vec<String>,
+
+        /// Workspace file to add the action to, defaults to the parent's file or the first one
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Change fields of an existing action in place
+    Update {
+        /// Which action to change, by #id, position, name, Parent/Child path or key:value
+        selector: String,
+
+        /// New name for the action
+        #[arg(long = "name", value_name = "NAME")]
+        #[serde(rename = "action_name")]
+        new_name: Option<String>,
+
+        #[arg(long)]
+        description: Option<String>,
+
+        #[arg(short, long)]
+        priority: Option<usize>,
+
+        /// not-started, in-progress, completed, blocked or cancelled, changed the same way as
+        /// start, complete, block, cancel and reopen change it
+        #[arg(short, long)]
+        state: Option<String>,
+
+        /// Context to add, can be given more than once
+        #[arg(long = "add-context", value_name = "CONTEXT")]
+        add_contexts: Vec<String>,
+
+        /// Context to remove, can be given more than once
+        #[arg(long = "remove-context", value_name = "CONTEXT")]
+        remove_contexts: Vec<String>,
+
+        /// Id of an action that has to be finished first, can be given more than once
+        #[arg(long = "add-blocked-by", value_name = "ID")]
+        add_blocked_by: Vec<String>,
+
+        /// Id of an action to stop waiting on, can be given more than once
+        #[arg(long = "remove-blocked-by", value_name = "ID")]
+        remove_blocked_by: Vec<String>,
+
+        /// Repeat the action from its do-date, in any form create takes
+        #[arg(long, value_name = "RULE")]
+        every: Option<String>,
+
+        /// Stop the action repeating, it keeps its do-date
+        #[arg(long, conflicts_with = "every")]
+        no_every: bool,
+    },
+    /// Remove an action from the action file
+    Delete {
+        /// Which action to remove, by #id, position, name, Parent/Child path or key:value
+        selector: String,
+
+        /// Delete the children along with the action
+        #[arg(long, conflicts_with = "promote")]
+        subtree: bool,
+
+        /// Keep the children, moving them up one level into the action's place
+        #[arg(long)]
+        promote: bool,
+
+        /// Print the change as a diff instead of writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Open an action and everything under it in $VISUAL or $EDITOR, saving it back once it
+    /// parses
+    Edit {
+        /// Which action to edit, by #id, position, name, Parent/Child path or key:value
+        selector: String,
+    },
+    /// Mark an action as in progress
+    Start(TransitionArgs),
+    /// Mark an action as completed, stamping the completed date and scheduling the next occurrence
+    /// of a repeating action
+    Complete(CompleteArgs),
+    /// Mark an action as blocked or awaiting something
+    Block(TransitionArgs),
+    /// Mark an action as cancelled
+    Cancel(TransitionArgs),
+    /// Mark an action as not started, clearing the completed date
+    Reopen(TransitionArgs),
+    /// Rewrite the action file in its canonical layout
+    Fmt {
+        /// The file to format instead of every workspace file, `-` reads stdin and writes stdout
+        file: Option<PathBuf>,
+
+        /// Change nothing, fail with a diff when the file is not formatted
+        #[arg(long)]
+        check: bool,
+    },
+    /// Check the action file for likely mistakes, failing when any error level rule is broken
+    Lint {
+        /// The file to check instead of every workspace file
+        file: Option<PathBuf>,
+
+        #[arg(short, long, value_enum, default_value = "text")]
+        format: LintFormat,
+    },
+    /// Move finished root actions and their subtrees to archive/YYYY-MM.actions in the data dir
+    Archive {
+        /// Only archive what was finished at least this long ago (e.g. 14d, 2w)
+        #[arg(long, value_name = "AGE")]
+        older_than: Option<String>,
+
+        /// Print the changes as diffs instead of making them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Suggest what to work on now, best first, with the reasons for each score
+    Next {
+        /// How many suggestions to show
+        #[arg(short = 'n', long, default_value = "5")]
+        limit: usize,
+
+        /// Context you are in right now, can be given more than once, defaults to the `context`
+        /// setting
+        #[arg(short = 'x', long = "context", value_name = "CONTEXT")]
+        contexts: Vec<String>,
+
+        #[arg(short, long, value_enum, default_value = "text")]
+        format: ReportFormat,
+    },
+    /// Show open actions by do-date: overdue, today, tomorrow and later
+    Agenda {
+        /// Cover the seven days starting today, which is also what it does without --days
+        #[arg(long, conflicts_with = "days")]
+        week: bool,
+
+        /// Cover this many days starting today, defaults to the seven days of --week
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        days: Option<u64>,
+
+        #[arg(short, long, value_enum, default_value = "text")]
+        format: ReportFormat,
+    },
+    /// Write every action out for other tools, e.g. as todos for calendar apps to subscribe to
+    Export {
+        #[arg(short, long, value_enum, default_value = "ics")]
+        format: ExportFormat,
+
+        /// Write to this file instead of printing
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Draw which actions are blocked by which
+    Graph {
+        #[arg(short, long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+    },
+    /// Manage action ids
+    Ids {
+        #[command(subcommand)]
+        command: IdsCommands,
+    },
+}
+
+#[derive(Subcommand, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "lowercase")]
+enum IdsCommands {
+    /// Give a new time ordered id to every action that does not have one
+    Assign {
+        /// Print the change as a diff instead of writing it
+        #[arg(long)]
+        dry_run: bool,
     },
 }
+
+#[derive(ClapArgs, Serialize, Deserialize)]
+struct TransitionArgs {
+    /// Which action to change, by #id, position, name, Parent/Child path or key:value
+    selector: String,
+
+    /// How descendants are treated, defaults to the `transition_policy` setting or none
+    #[arg(long, value_enum)]
+    policy: Option<TransitionPolicy>,
+
+    /// Print the change as a diff instead of writing it
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(ClapArgs, Serialize, Deserialize)]
+struct CompleteArgs {
+    #[command(flatten)]
+    #[serde(flatten)]
+    transition: TransitionArgs,
+
+    /// What the next occurrence of a repeating action counts from, defaults to the `recur_from`
+    /// setting or do-date
+    #[arg(long, value_enum)]
+    from: Option<RecurFrom>,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RecurFrom {
+    /// The do-date of the completed occurrence
+    DoDate,
+    /// When it was completed
+    Completion,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TransitionPolicy {
+    /// Only the selected action changes
+    None,
+    /// Completing or cancelling also completes or cancels open descendants
+    Cascade,
+    /// Refuse to complete an action while any descendant is open
+    Refuse,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GroupBy {
+    /// One group per context, actions with several show up in each
+    Context,
+    State,
+    /// The name of the parent action
+    Parent,
+    /// The workspace file the action is in
+    File,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GraphFormat {
+    /// Graphviz, e.g. `cliche graph | dot -Tsvg`
+    Dot,
+    Mermaid,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    /// iCalendar, one VTODO per action
+    Ics,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReportFormat {
+    /// For reading in the terminal
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LintFormat {
+    /// One finding per line
+    Text,
+    Json,
+    /// SARIF 2.1.0, for code review tools
+    Sarif,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    /// The action file syntax
+    Native,
+    /// A single pretty printed JSON document
+    Json,
+    /// One JSON object per action, per line
+    Ndjson,
+    /// Aligned columns
+    Table,
+}
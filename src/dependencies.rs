@@ -0,0 +1,375 @@
+//! Actions that wait on other actions
+//!
+//! An action lists the ids of what has to be finished before it can start in its context list:
+//!
+//! ```text
+//! ( ) Gather numbers #01951111cfa6718db303d7107f4005b3
+//! (=) Write report +work,blocked-by-01951111cfa6718db303d7107f4005b3
+//! ```
+//!
+//! Those references make a graph across the whole list, which should have no cycles. Once every
+//! action an action is blocked by is completed or cancelled it is free to start, and one that was
+//! marked blocked only because of them goes back to not started. An id that no action has keeps
+//! holding up what waits on it, since there is no telling whether it was finished, and the
+//! `unknown-blocker` lint rule points those out.
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::edits::{TextEdit, apply_edits, common_edits, map_source, set_property};
+use crate::entities::{Action, ActionPath, ActionState, ancestors, walk};
+use crate::ids::shortest_prefixes;
+
+/// Contexts starting with this are references to the action the rest of the context is the id of
+pub const BLOCKED_BY_PREFIX: &str = "blocked-by-";
+
+pub const GRAPH_FORMATS: [&str; 2] = ["dot", "mermaid"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphNode {
+    pub path: ActionPath,
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub state: ActionState,
+}
+
+impl GraphNode {
+    fn key(&self) -> String {
+        self.id
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| self.name.clone())
+    }
+}
+
+/// The actions that take part in a dependency and which waits on which
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DependencyGraph {
+    pub nodes: Vec<GraphNode>,
+    /// Pairs of node indices, the first has to be finished before the second can start
+    pub edges: Vec<(usize, usize)>,
+    /// Actions blocked by an id that no action has, along with that id
+    pub missing: Vec<(usize, Uuid)>,
+}
+
+impl DependencyGraph {
+    pub fn new(action_list: &[Action]) -> Self {
+        let walked = walk(action_list);
+        let by_id: HashMap<Uuid, &ActionPath> = walked
+            .iter()
+            .filter_map(|(path, action)| action.common.id.map(|id| (id, path)))
+            .collect();
+
+        // every action blocked by something or blocking something is a node, in file order
+        let involved: Vec<&ActionPath> = walked
+            .iter()
+            .filter(|(_, action)| !action.common.blocked_by.is_empty())
+            .flat_map(|(path, action)| {
+                std::iter::once(path).chain(
+                    action
+                        .common
+                        .blocked_by
+                        .iter()
+                        .filter_map(|blocker| by_id.get(blocker).copied()),
+                )
+            })
+            .collect();
+        let mut graph = DependencyGraph::default();
+        let mut indices: HashMap<&ActionPath, usize> = HashMap::new();
+        for (path, action) in walked.iter().filter(|(path, _)| involved.contains(&path)) {
+            indices.insert(path, graph.nodes.len());
+            graph.nodes.push(GraphNode {
+                path: path.clone(),
+                id: action.common.id,
+                name: action.common.name.clone(),
+                state: action.common.state,
+            });
+        }
+
+        for (path, action) in &walked {
+            for blocker in &action.common.blocked_by {
+                let dependent = indices[path];
+                match by_id.get(blocker) {
+                    Some(blocker_path) => graph.edges.push((indices[blocker_path], dependent)),
+                    None => graph.missing.push((dependent, *blocker)),
+                }
+            }
+        }
+        graph
+    }
+
+    /// Every distinct cycle as the node indices around it, starting and ending on the same node
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            New,
+            OnStack,
+            Done,
+        }
+
+        fn visit(
+            graph: &DependencyGraph,
+            node: usize,
+            marks: &mut [Mark],
+            stack: &mut Vec<usize>,
+            cycles: &mut Vec<Vec<usize>>,
+        ) {
+            marks[node] = Mark::OnStack;
+            stack.push(node);
+            for &(_, next) in graph.edges.iter().filter(|(from, _)| *from == node) {
+                match marks[next] {
+                    Mark::New => visit(graph, next, marks, stack, cycles),
+                    Mark::OnStack => {
+                        let start = stack.iter().position(|on| *on == next).unwrap_or(0);
+                        let mut cycle = stack[start..].to_vec();
+                        cycle.push(next);
+                        cycles.push(cycle);
+                    }
+                    Mark::Done => {}
+                }
+            }
+            stack.pop();
+            marks[node] = Mark::Done;
+        }
+
+        let mut marks = vec![Mark::New; self.nodes.len()];
+        let mut cycles = Vec::new();
+        for node in 0..self.nodes.len() {
+            if marks[node] == Mark::New {
+                visit(self, node, &mut marks, &mut Vec::new(), &mut cycles);
+            }
+        }
+        cycles
+    }
+
+    /// Fail on the first id that points nowhere or the first cycle, naming the actions involved
+    pub fn check(&self) -> Result<(), String> {
+        self.check_where(|_, _| true)
+    }
+
+    /// Like `check`, but only for what waits on something it did not wait on in `before`, so a
+    /// change is not held up by problems it did not bring in
+    pub fn check_changes(&self, before: &DependencyGraph) -> Result<(), String> {
+        let existing: HashSet<(String, Uuid)> = before.references().collect();
+        self.check_where(|node, blocker| !existing.contains(&(self.nodes[node].key(), blocker)))
+    }
+
+    // what waits on what by id, the waiting action by name when it has no id of its own
+    fn references(&self) -> impl Iterator<Item = (String, Uuid)> + '_ {
+        self.edges
+            .iter()
+            .filter_map(|(blocker, node)| Some((*node, self.nodes[*blocker].id?)))
+            .chain(self.missing.iter().copied())
+            .map(|(node, blocker)| (self.nodes[node].key(), blocker))
+    }
+
+    fn check_where(&self, included: impl Fn(usize, Uuid) -> bool) -> Result<(), String> {
+        if let Some((node, id)) = self.missing.iter().find(|(node, id)| included(*node, *id)) {
+            return Err(format!(
+                "{} is blocked by {}, which no action has as its id",
+                self.nodes[*node].name, id
+            ));
+        }
+        let cycles = self.cycles();
+        let cycle = cycles.iter().find(|cycle| {
+            cycle.windows(2).any(|pair| {
+                self.nodes[pair[0]]
+                    .id
+                    .is_some_and(|blocker| included(pair[1], blocker))
+            })
+        });
+        if let Some(cycle) = cycle {
+            let names: Vec<&str> = cycle
+                .iter()
+                .map(|node| self.nodes[*node].name.as_str())
+                .collect();
+            return Err(format!(
+                "actions cannot wait on each other in a circle: {}",
+                names.join(" -> ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+// whether the action with each id is completed or cancelled
+fn finished_ids(action_list: &[Action]) -> HashMap<Uuid, bool> {
+    walk(action_list)
+        .into_iter()
+        .filter_map(|(_, action)| {
+            action
+                .common
+                .id
+                .map(|id| (id, !action.common.state.is_open()))
+        })
+        .collect()
+}
+
+// an action no longer holds up the ones waiting on it once it is finished, but a missing one
+// never stops holding them up, lint is where those get found
+fn blockers_finished(finished: &HashMap<Uuid, bool>, action: &Action) -> bool {
+    action
+        .common
+        .blocked_by
+        .iter()
+        .all(|id| finished.get(id).copied().unwrap_or(false))
+}
+
+/// The open actions that can be worked on right now: not marked blocked, and with everything
+/// they or any of their ancestors are blocked by finished
+pub fn actionable(action_list: &[Action]) -> Vec<ActionPath> {
+    let finished = finished_ids(action_list);
+    walk(action_list)
+        .into_iter()
+        .filter(|(path, action)| {
+            action.common.state.is_open()
+                && action.common.state != ActionState::BlockedorAwaiting
+                && blockers_finished(&finished, action)
+                && ancestors(action_list, path)
+                    .all(|ancestor| blockers_finished(&finished, ancestor))
+        })
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// The blocked actions whose blockers are now all finished, which should go back to not started
+///
+/// An action marked blocked without naming anything it is blocked by is waiting on something
+/// outside the file, so it is left for whoever marked it.
+pub fn to_unblock(action_list: &[Action]) -> Vec<ActionPath> {
+    let finished = finished_ids(action_list);
+    walk(action_list)
+        .into_iter()
+        .filter(|(_, action)| {
+            action.common.state == ActionState::BlockedorAwaiting
+                && !action.common.blocked_by.is_empty()
+                && blockers_finished(&finished, action)
+        })
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// The source with every reference to `ids` taken out of what actions are blocked by, for when
+/// those actions leave the workspace finished
+///
+/// A blocked action left waiting on nothing goes back to not started, since what it waited on was
+/// done.
+pub fn drop_blockers(source: &str, ids: &[Uuid]) -> Result<String, String> {
+    let source_map = map_source(source)?;
+    let mut edits = Vec::new();
+    for (path, action) in walk(&source_map.actions) {
+        if !action.common.blocked_by.iter().any(|id| ids.contains(id)) {
+            continue;
+        }
+        let mut updated = action.common.clone();
+        updated.blocked_by.retain(|id| !ids.contains(id));
+        if updated.blocked_by.is_empty() && updated.state == ActionState::BlockedorAwaiting {
+            updated.state = ActionState::NotStarted;
+        }
+        if let Some(location) = source_map.location(&path) {
+            edits.extend(common_edits(source, location, &action.common, &updated));
+        }
+    }
+    Ok(apply_edits(source, &edits))
+}
+
+/// The source with the actions at `paths` moved from blocked to not started
+pub fn unblock_actions(source: &str, paths: &[ActionPath]) -> Result<String, String> {
+    let source_map = map_source(source)?;
+    let edits: Vec<TextEdit> = paths
+        .iter()
+        .filter_map(|path| source_map.location(path))
+        .filter_map(|location| {
+            set_property(
+                source,
+                location,
+                "state",
+                Some(&format!("({})", ActionState::NotStarted)),
+            )
+        })
+        .collect();
+    Ok(apply_edits(source, &edits))
+}
+
+fn escape_label(text: &str, format: &str) -> String {
+    match format {
+        "mermaid" => text.replace('"', "#quot;"),
+        _ => text.replace('\\', "\\\\").replace('"', "\\\""),
+    }
+}
+
+/// The graph as Graphviz DOT or a Mermaid flowchart, arrows running from each action to the ones
+/// waiting on it and finished actions drawn dashed
+pub fn render_graph(action_list: &[Action], format: &str) -> Result<String, String> {
+    if !GRAPH_FORMATS.contains(&format) {
+        return Err(format!(
+            "unknown graph format {}, expected one of {}",
+            format,
+            GRAPH_FORMATS.join(", ")
+        ));
+    }
+    let graph = DependencyGraph::new(action_list);
+    let prefixes = shortest_prefixes(action_list);
+    let label = |node: &GraphNode| {
+        let id = node
+            .id
+            .and_then(|id| prefixes.get(&id))
+            .map(|prefix| format!(" #{}", prefix))
+            .unwrap_or_default();
+        escape_label(&format!("({}) {}{}", node.state, node.name, id), format)
+    };
+    let missing_label = |id: &Uuid| escape_label(&format!("unknown #{}", id.simple()), format);
+
+    let mut lines = Vec::new();
+    match format {
+        "dot" => {
+            lines.push("digraph actions {".to_string());
+            lines.push("  rankdir=LR;".to_string());
+            lines.push("  node [shape=box];".to_string());
+            for (index, node) in graph.nodes.iter().enumerate() {
+                let style = if node.state.is_open() {
+                    ""
+                } else {
+                    ", style=dashed"
+                };
+                lines.push(format!(
+                    "  n{} [label=\"{}\"{}];",
+                    index,
+                    label(node),
+                    style
+                ));
+            }
+            for (index, (_, id)) in graph.missing.iter().enumerate() {
+                lines.push(format!(
+                    "  m{} [label=\"{}\", color=red];",
+                    index,
+                    missing_label(id)
+                ));
+            }
+            for (from, to) in &graph.edges {
+                lines.push(format!("  n{} -> n{};", from, to));
+            }
+            for (index, (node, _)) in graph.missing.iter().enumerate() {
+                lines.push(format!("  m{} -> n{};", index, node));
+            }
+            lines.push("}".to_string());
+        }
+        _ => {
+            lines.push("flowchart LR".to_string());
+            for (index, node) in graph.nodes.iter().enumerate() {
+                let class = if node.state.is_open() { "" } else { ":::done" };
+                lines.push(format!("  n{}[\"{}\"]{}", index, label(node), class));
+            }
+            for (index, (_, id)) in graph.missing.iter().enumerate() {
+                lines.push(format!("  m{}[\"{}\"]:::missing", index, missing_label(id)));
+            }
+            for (from, to) in &graph.edges {
+                lines.push(format!("  n{} --> n{}", from, to));
+            }
+            for (index, (node, _)) in graph.missing.iter().enumerate() {
+                lines.push(format!("  m{} --> n{}", index, node));
+            }
+            lines.push("  classDef done stroke-dasharray: 5 5,color:#888".to_string());
+            lines.push("  classDef missing stroke:#c00,color:#c00".to_string());
+        }
+    }
+    Ok(format!("{}\n", lines.join("\n")))
+}
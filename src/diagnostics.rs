@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tree_sitter::Node;
+
+use crate::treesitter::get_node_text;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Something worth telling the user about a specific spot in an action file
+///
+/// Lines and columns start at 1 so they can be shown as is, columns are counted in bytes the same
+/// way tree-sitter counts them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub node_kind: String,
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Diagnostic {
+    pub fn from_node(node: &Node, severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            message: message.into(),
+            node_kind: node.kind().to_string(),
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+            line: node.start_position().row + 1,
+            column: node.start_position().column + 1,
+        }
+    }
+
+    pub fn error(node: &Node, message: impl Into<String>) -> Self {
+        Diagnostic::from_node(node, Severity::Error, message)
+    }
+
+    /// For problems that belong to the file as a whole rather than any one node
+    pub fn for_source(message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            node_kind: "action_list".to_string(),
+            start_byte: 0,
+            end_byte: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {} ({})",
+            self.line, self.column, self.severity, self.message, self.node_kind
+        )
+    }
+}
+
+/// The error for a strict parse, holding every problem found rather than just the first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self.diagnostics.iter().map(|d| d.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for String {
+    fn from(error: ParseError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<Diagnostic> for ParseError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        ParseError {
+            diagnostics: vec![diagnostic],
+        }
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+/// Turn every ERROR and MISSING node under `node` into a diagnostic
+///
+/// ERROR nodes are reported against the kind of node they interrupted since "ERROR" on its own
+/// does not tell anyone much
+pub fn syntax_diagnostics(node: &Node, source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    collect_syntax_diagnostics(node, source, &mut diagnostics);
+    diagnostics
+}
+
+fn collect_syntax_diagnostics(node: &Node, source: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if node.is_missing() {
+        diagnostics.push(Diagnostic::error(node, format!("missing {}", node.kind())));
+        return;
+    }
+    if node.is_error() {
+        let mut diagnostic = Diagnostic::error(
+            node,
+            format!("unexpected `{}`", get_node_text(node, source).trim()),
+        );
+        if let Some(parent) = node.parent() {
+            diagnostic.node_kind = parent.kind().to_string();
+        }
+        diagnostics.push(diagnostic);
+        return;
+    }
+    if !node.has_error() {
+        return;
+    }
+
+    let mut binding = node.walk();
+    for child in node.children(&mut binding) {
+        collect_syntax_diagnostics(&child, source, diagnostics);
+    }
+}
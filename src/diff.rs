@@ -0,0 +1,178 @@
+//! Line based unified diffs, so that changes can be shown before they are written, and structural
+//! diffs of JSON values for when the lines are not what matters
+use serde_json::Value;
+use std::fmt::Write;
+
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineChange<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The difference between two versions of a file in unified diff format, empty when they match
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+
+    let changes = line_changes(old, new);
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for hunk in hunks(&changes) {
+        write_hunk(&mut output, &changes, hunk);
+    }
+    output
+}
+
+// a longest common subsequence over lines, action files are small enough that the quadratic
+// table is not a concern
+fn line_changes<'a>(old: &'a str, new: &'a str) -> Vec<LineChange<'a>> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let (rows, columns) = (old_lines.len(), new_lines.len());
+
+    let mut lengths = vec![vec![0u32; columns + 1]; rows + 1];
+    for i in (0..rows).rev() {
+        for j in (0..columns).rev() {
+            lengths[i][j] = if old_lines[i] == new_lines[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::new();
+    while i < rows || j < columns {
+        if i < rows && j < columns && old_lines[i] == new_lines[j] {
+            changes.push(LineChange::Same(old_lines[i]));
+            i += 1;
+            j += 1;
+        } else if i < rows && (j == columns || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            changes.push(LineChange::Removed(old_lines[i]));
+            i += 1;
+        } else {
+            changes.push(LineChange::Added(new_lines[j]));
+            j += 1;
+        }
+    }
+    changes
+}
+
+// ranges of changes, each padded with unchanged context and merged when they touch
+fn hunks(changes: &[LineChange]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+    for (index, change) in changes.iter().enumerate() {
+        if matches!(change, LineChange::Same(_)) {
+            continue;
+        }
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + 1 + CONTEXT_LINES).min(changes.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+    hunks
+}
+
+fn write_hunk(output: &mut String, changes: &[LineChange], hunk: std::ops::Range<usize>) {
+    let old_start = changes[..hunk.start]
+        .iter()
+        .filter(|change| !matches!(change, LineChange::Added(_)))
+        .count();
+    let new_start = changes[..hunk.start]
+        .iter()
+        .filter(|change| !matches!(change, LineChange::Removed(_)))
+        .count();
+    let lines = &changes[hunk];
+    let old_count = lines
+        .iter()
+        .filter(|change| !matches!(change, LineChange::Added(_)))
+        .count();
+    let new_count = lines
+        .iter()
+        .filter(|change| !matches!(change, LineChange::Removed(_)))
+        .count();
+
+    // an empty side of a hunk is reported on the line before it, per the format
+    let position = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+    let _ = writeln!(
+        output,
+        "@@ -{},{} +{},{} @@",
+        position(old_start, old_count),
+        old_count,
+        position(new_start, new_count),
+        new_count
+    );
+    for change in lines {
+        let _ = match change {
+            LineChange::Same(line) => writeln!(output, " {}", line),
+            LineChange::Removed(line) => writeln!(output, "-{}", line),
+            LineChange::Added(line) => writeln!(output, "+{}", line),
+        };
+    }
+}
+
+/// Every place two JSON values differ, one line each naming the path and both sides, empty when
+/// they match
+pub fn json_diff(expected: &Value, actual: &Value) -> Vec<String> {
+    let mut differences = Vec::new();
+    collect_json_differences("$", expected, actual, &mut differences);
+    differences
+}
+
+fn collect_json_differences(
+    path: &str,
+    expected: &Value,
+    actual: &Value,
+    differences: &mut Vec<String>,
+) {
+    match (expected, actual) {
+        (Value::Object(expected_fields), Value::Object(actual_fields)) => {
+            let mut keys: Vec<&String> =
+                expected_fields.keys().chain(actual_fields.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let field_path = format!("{}.{}", path, key);
+                match (expected_fields.get(key), actual_fields.get(key)) {
+                    (Some(expected), Some(actual)) => {
+                        collect_json_differences(&field_path, expected, actual, differences)
+                    }
+                    (Some(expected), None) => {
+                        differences.push(format!("{}: missing, expected {}", field_path, expected))
+                    }
+                    (None, Some(actual)) => {
+                        differences.push(format!("{}: unexpected {}", field_path, actual))
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(expected_items), Value::Array(actual_items)) => {
+            for index in 0..expected_items.len().max(actual_items.len()) {
+                let item_path = format!("{}[{}]", path, index);
+                match (expected_items.get(index), actual_items.get(index)) {
+                    (Some(expected), Some(actual)) => {
+                        collect_json_differences(&item_path, expected, actual, differences)
+                    }
+                    (Some(expected), None) => {
+                        differences.push(format!("{}: missing, expected {}", item_path, expected))
+                    }
+                    (None, Some(actual)) => {
+                        differences.push(format!("{}: unexpected {}", item_path, actual))
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if expected != actual => {
+            differences.push(format!("{}: expected {}, found {}", path, expected, actual))
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,569 @@
+//! Text edits against the source of an action file
+//!
+//! Every function here takes the current source and hands back the new source, leaving it up to
+//! the caller to decide when (and if) that gets written anywhere. Edits only ever touch the lines
+//! they need to so that hand-written files keep their shape.
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use tree_sitter::Node;
+use uuid::Uuid;
+
+use crate::entities::{
+    Action, ActionPath, ActionState, CommonActionProperties, MAX_ACTION_DEPTH, action_line,
+    get_action, walk,
+};
+use crate::recurrence::Recurrence;
+use crate::selector::select_one;
+use crate::treesitter::{PROPERTY_NODE_KINDS, action_nodes};
+use crate::{ActionList, get_tree_wrapper};
+
+/// The parsed actions of a source along with where each of them lives in the text
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMap {
+    pub actions: ActionList,
+    /// One entry per action in file order, lining up with `entities::walk`
+    pub locations: Vec<ActionLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionLocation {
+    pub path: ActionPath,
+    /// The bytes of the whole subtree, from the state of this action to the end of its last
+    /// descendant
+    pub range: Range<usize>,
+    /// The bytes of each property node of this action keyed by node kind, in file order, followed
+    /// by its story when it has one
+    pub properties: Vec<(String, Range<usize>)>,
+}
+
+impl ActionLocation {
+    pub fn property(&self, kind: &str) -> Option<Range<usize>> {
+        self.properties
+            .iter()
+            .find(|(property_kind, _)| property_kind == kind)
+            .map(|(_, range)| range.clone())
+    }
+}
+
+impl SourceMap {
+    pub fn action(&self, path: &[usize]) -> Option<&Action> {
+        get_action(&self.actions, path)
+    }
+
+    pub fn location(&self, path: &[usize]) -> Option<&ActionLocation> {
+        self.locations.iter().find(|location| location.path == path)
+    }
+}
+
+/// Parse the source and pair every action with its location, refusing files that do not parse
+/// cleanly since an edit against a half-understood file could land anywhere
+pub fn map_source(source: &str) -> Result<SourceMap, String> {
+    let tree_wrapper = get_tree_wrapper(source)?;
+    let tree = tree_wrapper.tree.clone();
+    let masked = tree_wrapper.source.clone();
+    let actions: ActionList = tree_wrapper.try_into()?;
+
+    // the whitespace the grammar soaks up can hold blanked out comments, which belong to whatever
+    // comes after them, so every range stops at the last thing that is actually written
+    let nodes = action_nodes(&tree.root_node());
+    let locations = walk(&actions)
+        .into_iter()
+        .zip(nodes)
+        .map(|((path, _), node)| ActionLocation {
+            path,
+            range: node.start_byte()..visible(&masked, &(node.start_byte()..node.end_byte())).end,
+            properties: property_ranges(&node)
+                .into_iter()
+                .map(|(kind, range)| {
+                    let end = visible(&masked, &range).end.max(range.start);
+                    (kind, range.start..end)
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(SourceMap { actions, locations })
+}
+
+fn property_ranges(action_node: &Node) -> Vec<(String, Range<usize>)> {
+    let mut binding = action_node.walk();
+    let core = action_node
+        .children(&mut binding)
+        .find(|child| child.kind() == "core_action");
+    let Some(core) = core else {
+        return Vec::new();
+    };
+
+    let mut core_binding = core.walk();
+    let mut story_binding = action_node.walk();
+    core.children(&mut core_binding)
+        .filter(|property| PROPERTY_NODE_KINDS.contains(&property.kind()))
+        .chain(
+            action_node
+                .children(&mut story_binding)
+                .filter(|child| child.kind() == "story"),
+        )
+        .map(|property| {
+            (
+                property.kind().to_string(),
+                property.start_byte()..property.end_byte(),
+            )
+        })
+        .collect()
+}
+
+/// A single replacement of a range of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// Apply edits that do not overlap, last first so that earlier ranges stay valid
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> String {
+    let mut sorted = edits.to_vec();
+    sorted.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+
+    let mut updated = source.to_string();
+    for edit in sorted {
+        updated.replace_range(edit.range, &edit.text);
+    }
+    updated
+}
+
+// the grammar lets names and descriptions soak up the whitespace around them, so what we want to
+// replace is only the part that is actually visible
+pub(crate) fn visible(source: &str, range: &Range<usize>) -> Range<usize> {
+    let text = &source[range.clone()];
+    let start = range.start + (text.len() - text.trim_start().len());
+    let end = range.start + text.trim_end().len();
+    start..end.max(start)
+}
+
+/// The edit that sets one property of a located action to `text`, or removes it when `text` is
+/// `None`
+///
+/// Existing properties are replaced where they stand, new ones are added right after the property
+/// that comes before them in the grammar, which keeps whatever layout the action was written in
+pub fn set_property(
+    source: &str,
+    location: &ActionLocation,
+    kind: &str,
+    text: Option<&str>,
+) -> Option<TextEdit> {
+    let order = PROPERTY_NODE_KINDS.iter().position(|k| *k == kind)?;
+    let preceding_end = PROPERTY_NODE_KINDS[..order]
+        .iter()
+        .rev()
+        .find_map(|preceding| location.property(preceding))
+        .map(|range| visible(source, &range).end);
+
+    match (location.property(kind), text) {
+        (Some(range), Some(text)) => Some(TextEdit {
+            range: visible(source, &range),
+            text: text.to_string(),
+        }),
+        (Some(range), None) => Some(TextEdit {
+            range: preceding_end.unwrap_or(range.start)..visible(source, &range).end,
+            text: String::new(),
+        }),
+        (None, Some(text)) => {
+            let position = preceding_end?;
+            Some(TextEdit {
+                range: position..position,
+                text: format!(" {}", text),
+            })
+        }
+        (None, None) => None,
+    }
+}
+
+/// The changes `update_action` can make, anything left empty stays as it was
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionUpdate {
+    pub name: Option<String>,
+    pub state: Option<ActionState>,
+    pub description: Option<String>,
+    pub priority: Option<usize>,
+    pub add_contexts: Vec<String>,
+    pub remove_contexts: Vec<String>,
+    pub add_blocked_by: Vec<Uuid>,
+    pub remove_blocked_by: Vec<Uuid>,
+    pub recurrence: Option<Recurrence>,
+    /// Stop the action repeating, it keeps its do-date
+    pub remove_recurrence: bool,
+}
+
+impl ActionUpdate {
+    pub fn apply(&self, common: &CommonActionProperties) -> CommonActionProperties {
+        let mut updated = common.clone();
+        if let Some(name) = &self.name {
+            updated.name = name.clone();
+        }
+        if let Some(state) = self.state {
+            updated.state = state;
+        }
+        if let Some(description) = &self.description {
+            updated.description = Some(description.clone());
+        }
+        if let Some(priority) = self.priority {
+            updated.priority = Some(priority);
+        }
+        if let Some(recurrence) = &self.recurrence {
+            updated.recurrence = Some(recurrence.clone());
+        }
+        if self.remove_recurrence {
+            updated.recurrence = None;
+        }
+
+        let mut contexts = updated.context_list.take().unwrap_or_default();
+        for context in &self.add_contexts {
+            let context = normalize_context(context);
+            if !contexts.contains(&context) {
+                contexts.push(context);
+            }
+        }
+        contexts.retain(|context| {
+            !self
+                .remove_contexts
+                .iter()
+                .any(|removed| normalize_context(removed) == *context)
+        });
+        updated.context_list = (!contexts.is_empty()).then_some(contexts);
+
+        for id in &self.add_blocked_by {
+            if !updated.blocked_by.contains(id) {
+                updated.blocked_by.push(*id);
+            }
+        }
+        updated
+            .blocked_by
+            .retain(|id| !self.remove_blocked_by.contains(id));
+
+        updated
+    }
+}
+
+/// Contexts are kept with their leading `@`, but are just as often typed without it
+pub fn normalize_context(context: &str) -> String {
+    format!("@{}", context.trim().trim_start_matches('@'))
+}
+
+/// Change fields of the action matching `selector`, rewriting only the properties that changed
+pub fn update_action(
+    source: &str,
+    selector: &str,
+    update: &ActionUpdate,
+) -> Result<String, String> {
+    let source_map = map_source(source)?;
+    let path = select_one(&source_map.actions, selector)?;
+    let (action, location) = source_map
+        .action(&path)
+        .zip(source_map.location(&path))
+        .ok_or_else(|| format!("no action matches {}", selector))?;
+
+    let updated = update.apply(&action.common);
+    validate_common(&updated)?;
+
+    Ok(apply_edits(
+        source,
+        &common_edits(source, location, &action.common, &updated),
+    ))
+}
+
+// only the properties that render differently get touched, dates and ids are left to the commands
+// that own them
+pub(crate) fn common_edits(
+    source: &str,
+    location: &ActionLocation,
+    current: &CommonActionProperties,
+    updated: &CommonActionProperties,
+) -> Vec<TextEdit> {
+    let render = |common: &CommonActionProperties| -> [(&str, Option<String>); 5] {
+        [
+            ("state", Some(format!("({})", common.state))),
+            ("name", Some(common.name.clone())),
+            (
+                "description",
+                common.description.as_ref().map(|d| format!("${}", d)),
+            ),
+            ("priority", common.priority.map(|p| format!("!{}", p))),
+            ("context_list", common.context_property()),
+        ]
+    };
+
+    render(current)
+        .into_iter()
+        .zip(render(updated))
+        .filter(|((_, before), (_, after))| before != after)
+        .filter_map(|(_, (kind, after))| set_property(source, location, kind, after.as_deref()))
+        .chain(
+            // intervals go in the context list, so only the grammar's repeats touch the do-date
+            {
+                let text = |common: &CommonActionProperties| {
+                    common
+                        .recurrence
+                        .as_ref()
+                        .and_then(Recurrence::do_date_text)
+                };
+                (text(current) != text(updated))
+                    .then(|| set_recurrence(source, location, text(updated).as_deref()))
+                    .flatten()
+            },
+        )
+        .collect()
+}
+
+/// The edit that sets the repeat at the end of the do-date to `text`, or removes it when `text`
+/// is `None`, leaving the date and any duration in front of it as they were written
+pub fn set_recurrence(
+    source: &str,
+    location: &ActionLocation,
+    text: Option<&str>,
+) -> Option<TextEdit> {
+    let range = visible(source, &location.property("do_date_or_time")?);
+    let written = &source[range.clone()];
+    // no part of a date, time or duration starts with an R, every repeat does
+    let repeat = written
+        .char_indices()
+        .find(|(index, c)| *c == 'R' && written[..*index].ends_with(char::is_whitespace))
+        .map(|(index, _)| range.start + index);
+
+    match (repeat, text) {
+        (Some(start), Some(text)) => Some(TextEdit {
+            range: start..range.end,
+            text: text.to_string(),
+        }),
+        (Some(start), None) => Some(TextEdit {
+            range: range.start + source[range.start..start].trim_end().len()..range.end,
+            text: String::new(),
+        }),
+        (None, Some(text)) => Some(TextEdit {
+            range: range.end..range.end,
+            text: format!(" {}", text),
+        }),
+        (None, None) => None,
+    }
+}
+
+/// The grammar reserves a handful of characters as property markers, so those cannot show up
+/// inside the free text of an action
+pub fn validate_common(common: &CommonActionProperties) -> Result<(), String> {
+    if common.name.trim().is_empty() {
+        return Err("an action needs a name".to_string());
+    }
+    // a line break would start what reads as the next action
+    if common.name.contains(['\n', '\r']) {
+        return Err(format!(
+            "action names have to fit on one line: {:?}",
+            common.name
+        ));
+    }
+    if let Some(description) = &common.description
+        && description.contains(['\n', '\r'])
+    {
+        return Err(format!(
+            "descriptions have to fit on one line: {:?}",
+            description
+        ));
+    }
+    if let Some(marker) = common.name.chars().find(|c| "$!*+@%>#(".contains(*c)) {
+        return Err(format!(
+            "action names cannot contain `{}`: {}",
+            marker, common.name
+        ));
+    }
+    if let Some(description) = &common.description
+        && let Some(marker) = description.chars().find(|c| "!*+@%>#(".contains(*c))
+    {
+        return Err(format!(
+            "descriptions cannot contain `{}`: {}",
+            marker, description
+        ));
+    }
+    for context in common.context_list.iter().flatten() {
+        let bare = context.trim_start_matches('@');
+        if bare.is_empty()
+            || !bare
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "contexts can only use letters, numbers, `-` and `_`: {}",
+                context
+            ));
+        }
+    }
+    // every repeat counts on from the do-date
+    if common.recurrence.is_some() && common.do_date_time.is_none() {
+        return Err(format!("{} needs a do-date to repeat from", common.name));
+    }
+    Ok(())
+}
+
+/// Add a new action to the source, either as the last root action or as the last child of the
+/// action with the `parent` id
+pub fn create_action(
+    source: &str,
+    common: &CommonActionProperties,
+    parent: Option<Uuid>,
+) -> Result<String, String> {
+    validate_common(common)?;
+
+    let source_map = map_source(source)?;
+    let Some(parent_id) = parent else {
+        return Ok(insert_at(source, source.len(), &action_line(common, 0)));
+    };
+
+    let (path, parent) = walk(&source_map.actions)
+        .into_iter()
+        .find(|(_, action)| action.common.id == Some(parent_id))
+        .ok_or_else(|| format!("no action has the id {}", parent_id))?;
+    if parent.depth >= MAX_ACTION_DEPTH {
+        return Err(format!(
+            "{} is already as deep as actions go, it cannot have children",
+            parent.common.name
+        ));
+    }
+    let location = source_map
+        .location(&path)
+        .ok_or_else(|| format!("no action has the id {}", parent_id))?;
+
+    let line = action_line(common, parent.depth + 1);
+    Ok(insert_at(
+        source,
+        line_after(source, &location.range),
+        &line,
+    ))
+}
+
+// the grammar lets names soak up the whitespace after them, so the end of a node can land partway
+// into the indentation of the next line, instead we want the start of the line after the last
+// visible character
+pub(crate) fn line_after(source: &str, range: &Range<usize>) -> usize {
+    let visible_end = range.start + source[range.clone()].trim_end().len();
+    source[visible_end..]
+        .find('\n')
+        .map(|offset| visible_end + offset + 1)
+        .unwrap_or(source.len())
+}
+
+// inserts whole lines, making sure whatever came before ends in a newline first
+pub(crate) fn insert_at(source: &str, position: usize, lines: &str) -> String {
+    let before = &source[..position];
+    let separator = if before.is_empty() || before.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    format!("{}{}{}{}", before, separator, lines, &source[position..])
+}
+
+/// What to do with the children of an action that is being deleted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChildHandling {
+    /// Leave the file alone and report the children instead
+    #[default]
+    Refuse,
+    /// Delete the children along with their parent
+    Subtree,
+    /// Keep the children, moving each of them up to where their parent was
+    Promote,
+}
+
+/// Remove the action matching `selector` from the source
+pub fn delete_action(
+    source: &str,
+    selector: &str,
+    children: ChildHandling,
+) -> Result<String, String> {
+    let source_map = map_source(source)?;
+    let path = select_one(&source_map.actions, selector)?;
+    let (action, location) = source_map
+        .action(&path)
+        .zip(source_map.location(&path))
+        .ok_or_else(|| format!("no action matches {}", selector))?;
+
+    let edits = match (children, action.children.len()) {
+        (_, 0) | (ChildHandling::Subtree, _) => vec![TextEdit {
+            range: line_start(source, location.range.start)..line_after(source, &location.range),
+            text: String::new(),
+        }],
+        (ChildHandling::Refuse, count) => {
+            return Err(format!(
+                "{} has {} {}, delete the whole subtree or promote them instead",
+                action.common.name,
+                count,
+                if count == 1 { "child" } else { "children" }
+            ));
+        }
+        (ChildHandling::Promote, _) => promote_edits(source, &source_map, &path)?,
+    };
+
+    let updated = apply_edits(source, &edits);
+    map_source(&updated).map_err(|e| {
+        format!(
+            "deleting {} would leave the file unreadable: {}",
+            action.common.name, e
+        )
+    })?;
+    Ok(updated)
+}
+
+// the parent goes from the start of its line up to the line its first child starts on, then every
+// descendant loses one `>` and one level of indentation
+fn promote_edits(
+    source: &str,
+    source_map: &SourceMap,
+    path: &[usize],
+) -> Result<Vec<TextEdit>, String> {
+    let location = source_map
+        .location(path)
+        .ok_or("the action to delete has no location")?;
+    let mut first_child_path = path.to_vec();
+    first_child_path.push(0);
+    let first_child = source_map
+        .location(&first_child_path)
+        .ok_or("the first child to promote has no location")?;
+
+    let mut edits = vec![TextEdit {
+        range: line_start(source, location.range.start)
+            ..line_start(source, first_child.range.start),
+        text: String::new(),
+    }];
+
+    for descendant in source_map.locations.iter().filter(|descendant| {
+        descendant.path.len() > path.len() && descendant.path.starts_with(path)
+    }) {
+        let depth = descendant.path.len() - 1;
+        let start = line_start(source, descendant.range.start);
+        let indentation = &source[start..descendant.range.start];
+        let kept = indentation.len() * (depth - 1) / depth;
+
+        // past the icon, a promoted root action loses the space after its icon as well
+        let icon_end = descendant.range.start + depth;
+        let replaced_end = if depth == 1 {
+            icon_end + (source[icon_end..].len() - source[icon_end..].trim_start().len())
+        } else {
+            icon_end
+        };
+        edits.push(TextEdit {
+            range: start..replaced_end,
+            text: format!("{}{}", &indentation[..kept], ">".repeat(depth - 1)),
+        });
+    }
+
+    Ok(edits)
+}
+
+// where the line holding `position` begins, as long as only indentation sits in front of it
+pub(crate) fn line_start(source: &str, position: usize) -> usize {
+    let start = source[..position].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    if source[start..position].trim().is_empty() {
+        start
+    } else {
+        position
+    }
+}
@@ -1,95 +1,131 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde::de::Deserializer;
+use serde::ser::{SerializeStruct, Serializer};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fmt;
 
-use crate::treesitter::{NodeWrapper, TreeWrapper, create_node_wrapper, get_node_text};
+use crate::dependencies::BLOCKED_BY_PREFIX;
+use crate::diagnostics::{Diagnostic, ParseError, has_errors, syntax_diagnostics};
+use crate::ids::shortest_prefixes;
+use crate::recurrence::Recurrence;
+use crate::treesitter::{
+    ACTION_LIST_NODE_KINDS, NodeWrapper, TreeWrapper, action_node_depth, create_node_wrapper,
+    get_node_text,
+};
+use tree_sitter::Node;
 use uuid::Uuid;
 
-pub type ActionList = Vec<RootAction>;
+pub type ActionList = Vec<Action>;
+
+/// Indices from the top of an `ActionList` down to a single action, so `[1, 0]` is the first
+/// child of the second root action
+pub type ActionPath = Vec<usize>;
+
+/// The grammar supports a root action plus five levels of children, so a leaf sits at depth 5
+pub const MAX_ACTION_DEPTH: usize = 5;
+
+// the names the children were serialized under back when every level had its own struct, we keep
+// them so that the json shape stays the same for anyone consuming it
+const CHILDREN_FIELD_NAMES: [&str; MAX_ACTION_DEPTH] = [
+    "children",
+    "grandchildren",
+    "great_grandchildren",
+    "great_great_grandchildren",
+    "leaf_children",
+];
+
+/// Everything that could be read out of an action file along with everything that could not
+///
+/// This is what the lenient parse hands back, actions that fail to convert are left out and
+/// reported in the diagnostics instead of failing the whole file
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParsedActions {
+    pub actions: ActionList,
+    pub diagnostics: Vec<Diagnostic>,
+}
 
-impl TryFrom<TreeWrapper> for ActionList {
-    type Error = &'static str;
-    fn try_from(value: TreeWrapper) -> Result<Self, Self::Error> {
+impl From<TreeWrapper> for ParsedActions {
+    fn from(value: TreeWrapper) -> Self {
         let root = value.tree.root_node();
-        let mut action_list: Vec<RootAction> = Vec::new();
+        let mut diagnostics = syntax_diagnostics(&root, &value.source);
+        let mut actions: Vec<Action> = Vec::new();
         let mut binding = root.walk();
 
-        let root_action_iterator = root.children(&mut binding);
-
-        root_action_iterator.for_each(|action_node| {
-            action_list.push(
-                create_node_wrapper(action_node, value.source.clone())
-                    .try_into()
-                    .expect("failed to properly build action list"),
-            )
-        });
+        // anything at the top that is not a root action is an ERROR node, which the syntax
+        // diagnostics already cover
+        for action_node in root.children(&mut binding) {
+            if action_node_depth(action_node.kind()) != Some(0) {
+                continue;
+            }
+            match read_action(&action_node, &value.source, &mut diagnostics) {
+                Ok(action) => actions.push(action),
+                Err(diagnostic) => diagnostics.push(diagnostic),
+            }
+        }
 
-        return Ok(action_list);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.start_byte);
+        ParsedActions {
+            actions,
+            diagnostics,
+        }
     }
 }
 
-macro_rules! impl_action_list_try_from {
-    ($list_type:ty, $child_kind:literal, $expect_msg:literal) => {
-        impl<'a> TryFrom<NodeWrapper<'a>> for $list_type {
-            type Error = &'static str;
-            fn try_from(value: NodeWrapper<'a>) -> Result<Self, Self::Error> {
-                let mut binding = value.node.walk();
-                let child_iterator = value.node.children(&mut binding);
-                let mut list: $list_type = Vec::new();
-                for child in child_iterator {
-                    if child.kind() == $child_kind {
-                        let wrapper = create_node_wrapper(child, value.source.clone());
-                        list.push(wrapper.try_into().expect($expect_msg));
-                    }
-                }
-                Ok(list)
-            }
+impl TryFrom<TreeWrapper> for ActionList {
+    type Error = ParseError;
+    fn try_from(value: TreeWrapper) -> Result<Self, Self::Error> {
+        let parsed = ParsedActions::from(value);
+
+        if has_errors(&parsed.diagnostics) {
+            return Err(ParseError {
+                diagnostics: parsed.diagnostics,
+            });
         }
-    };
+
+        Ok(parsed.actions)
+    }
 }
 
-macro_rules! impl_action_node_try_from {
-    ($struct_name:ty, $children_field:ident, $children_kind:literal) => {
-        impl<'a> TryFrom<NodeWrapper<'a>> for $struct_name {
-            type Error = &'static str;
-            fn try_from(value: NodeWrapper<'a>) -> Result<Self, Self::Error> {
-                let mut binding = value.node.walk();
-                let child_iterator = value.node.children(&mut binding);
+/// A single action and everything nested underneath it
+///
+/// Every level of the grammar maps onto this one type, the `depth` tells you where in the
+/// hierarchy it sits with root actions at 0, and only root actions can carry a story
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Action {
+    pub common: CommonActionProperties,
+    pub story: Option<Story>,
+    pub children: Vec<Action>,
+    pub depth: usize,
+}
 
-                let mut common: CommonActionProperties = CommonActionProperties::default();
-                let mut $children_field = None;
+impl Action {
+    pub fn new(common: CommonActionProperties, depth: usize) -> Self {
+        Action {
+            common,
+            depth,
+            ..Default::default()
+        }
+    }
 
-                for child in child_iterator {
-                    match child.kind() {
-                        "core_action" => {
-                            let core_wrapper = create_node_wrapper(child, value.source.clone());
-                            common = core_wrapper.try_into()?;
-                        }
-                        $children_kind => {
-                            $children_field =
-                                Some(create_node_wrapper(child, value.source.clone()).try_into()?);
-                        }
-                        _ => {}
-                    }
-                }
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
 
-                Ok(Self {
-                    common,
-                    $children_field,
-                })
-            }
-        }
-    };
-}
+    /// This action followed by all of its descendants, parents before their children
+    pub fn depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst { stack: vec![self] }
+    }
 
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
-pub struct RootAction {
-    pub common: CommonActionProperties,
-    pub story: Option<Story>,
-    pub children: Option<ChildActionList>,
+    /// This action followed by all of its descendants, one level at a time
+    pub fn breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst {
+            queue: VecDeque::from([self]),
+        }
+    }
 }
 
-impl fmt::Display for RootAction {
+impl fmt::Display for Action {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.common)?;
 
@@ -97,216 +133,349 @@ impl fmt::Display for RootAction {
             write!(f, " *{}", story)?;
         }
 
-        if let Some(children) = &self.children {
-            for child in children {
-                write!(f, " >{}", child)?;
-            }
+        for child in &self.children {
+            write!(f, " {}{}", ">".repeat(child.depth), child)?;
         }
 
         Ok(())
     }
 }
 
-impl<'a> TryFrom<NodeWrapper<'a>> for RootAction {
-    type Error = &'static str;
-    fn try_from(value: NodeWrapper<'a>) -> Result<Self, Self::Error> {
-        let mut binding = value.node.walk();
-        let child_iterator = value.node.children(&mut binding);
-
-        let mut common = CommonActionProperties::default();
-        let mut story: Option<Story> = None;
-        let mut children: Option<ChildActionList> = None;
+/// A single action written out on its own line, indented and marked for its depth
+pub fn action_line(common: &CommonActionProperties, depth: usize) -> String {
+    if depth == 0 {
+        format!("{}\n", common)
+    } else {
+        format!("{}{} {}\n", "\t".repeat(depth), ">".repeat(depth), common)
+    }
+}
 
-        for child in child_iterator {
-            match child.kind() {
-                "core_action" => {
-                    let core_wrapper = create_node_wrapper(child, value.source.clone());
-                    common = core_wrapper.try_into()?;
-                }
-                "story" => {
-                    story = Some(get_node_text(&child, &value.source));
-                }
-                "child_actions" => {
-                    children = Some(ChildActionList::try_from(create_node_wrapper(
-                        child,
-                        value.source.clone(),
-                    ))?);
-                }
-                _ => {}
+/// The list written out as an action file, one action per line and indented for its depth
+///
+/// `Display` keeps a whole tree on one line, this is the layout to use for anything meant to be
+/// parsed again. The depth comes from where each action sits in the list, so a tree put together
+/// by hand does not need its `depth` fields filled in.
+pub fn to_action_file(action_list: &[Action]) -> String {
+    walk(action_list)
+        .into_iter()
+        .map(|(path, action)| {
+            let mut line = action_line(&action.common, path.len() - 1);
+            if let Some(story) = &action.story {
+                line.insert_str(line.len() - 1, &format!(" *{}", story));
             }
-        }
-
-        Ok(RootAction {
-            common,
-            story,
-            children,
+            line
         })
-    }
+        .collect()
 }
-type ChildActionList = Vec<ChildAction>;
 
-impl_action_list_try_from!(
-    ChildActionList,
-    "child_action",
-    "failed to convert child action"
-);
+impl<'a> TryFrom<NodeWrapper<'a>> for Action {
+    type Error = ParseError;
+    fn try_from(value: NodeWrapper<'a>) -> Result<Self, Self::Error> {
+        let mut diagnostics = Vec::new();
+        let action = read_action(&value.node, &value.source, &mut diagnostics)?;
 
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
-pub struct ChildAction {
-    common: CommonActionProperties,
-    grandchildren: Option<GrandChildActionList>,
-}
+        if has_errors(&diagnostics) {
+            return Err(ParseError { diagnostics });
+        }
 
-impl fmt::Display for ChildAction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.common)?;
+        Ok(action)
+    }
+}
 
-        if let Some(grandchildren) = &self.grandchildren {
-            for grandchild in grandchildren {
-                write!(f, " >>{}", grandchild)?;
+// an action without its own core properties is unusable, but a broken child only costs us that
+// child, so those are reported alongside and skipped
+fn read_action(
+    node: &Node,
+    source: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Action, Diagnostic> {
+    let depth = action_node_depth(node.kind()).ok_or_else(|| {
+        Diagnostic::error(
+            node,
+            format!("expected an action but found {}", node.kind()),
+        )
+    })?;
+    let mut binding = node.walk();
+    let child_iterator = node.children(&mut binding);
+
+    let mut action = Action::new(CommonActionProperties::default(), depth);
+
+    for child in child_iterator {
+        match child.kind() {
+            "core_action" => {
+                let core_wrapper = create_node_wrapper(child, source.to_string());
+                action.common = core_wrapper.try_into()?;
+            }
+            "story" => {
+                action.story = find_descendant(&child, "story_name")
+                    .map(|story_name| get_node_text(&story_name, source))
+                    .map(|story| story.trim().to_string());
             }
+            kind if Some(kind) == ACTION_LIST_NODE_KINDS.get(depth).copied() => {
+                let mut list_binding = child.walk();
+                for child_node in child.children(&mut list_binding) {
+                    if action_node_depth(child_node.kind()) != Some(depth + 1) {
+                        continue;
+                    }
+                    match read_action(&child_node, source, diagnostics) {
+                        Ok(child_action) => action.children.push(child_action),
+                        Err(diagnostic) => diagnostics.push(diagnostic),
+                    }
+                }
+            }
+            _ => {}
         }
-
-        Ok(())
     }
-}
 
-impl_action_node_try_from!(ChildAction, grandchildren, "grandchild_action_list");
+    Ok(action)
+}
 
-type GrandChildActionList = Vec<GrandChildAction>;
+impl Serialize for Action {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let children_field = CHILDREN_FIELD_NAMES.get(self.depth).copied();
+        let field_count = 1 + usize::from(self.depth == 0) + usize::from(children_field.is_some());
 
-impl_action_list_try_from!(
-    GrandChildActionList,
-    "grand_child_action",
-    "failed to convert grand child action"
-);
+        let mut state = serializer.serialize_struct("Action", field_count)?;
+        state.serialize_field("common", &self.common)?;
+        if self.depth == 0 {
+            state.serialize_field("story", &self.story)?;
+        }
+        if let Some(children_field) = children_field {
+            let children = (!self.children.is_empty()).then_some(&self.children);
+            state.serialize_field(children_field, &children)?;
+        }
+        state.end()
+    }
+}
 
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
-struct GrandChildAction {
+// accepts any of the per-level children names so that a single action can be read back no
+// matter which level it was written from
+#[derive(Deserialize)]
+struct SerializedAction {
     common: CommonActionProperties,
-    great_grandchildren: Option<GreatGrandChildActionList>,
+    #[serde(default)]
+    story: Option<Story>,
+    #[serde(default)]
+    children: Option<Vec<SerializedAction>>,
+    #[serde(default)]
+    grandchildren: Option<Vec<SerializedAction>>,
+    #[serde(default)]
+    great_grandchildren: Option<Vec<SerializedAction>>,
+    #[serde(default)]
+    great_great_grandchildren: Option<Vec<SerializedAction>>,
+    #[serde(default)]
+    leaf_children: Option<Vec<SerializedAction>>,
 }
 
-impl fmt::Display for GrandChildAction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.common)?;
-
-        if let Some(great_grandchildren) = &self.great_grandchildren {
-            for great_grandchild in great_grandchildren {
-                write!(f, " >>>{}", great_grandchild)?;
-            }
+impl SerializedAction {
+    fn into_action(self, depth: usize) -> Action {
+        let children = self
+            .children
+            .or(self.grandchildren)
+            .or(self.great_grandchildren)
+            .or(self.great_great_grandchildren)
+            .or(self.leaf_children)
+            .unwrap_or_default();
+
+        Action {
+            common: self.common,
+            story: self.story,
+            children: children
+                .into_iter()
+                .map(|child| child.into_action(depth + 1))
+                .collect(),
+            depth,
         }
-
-        Ok(())
     }
 }
 
-impl_action_node_try_from!(
-    GrandChildAction,
-    great_grandchildren,
-    "great_grand_child_actions"
-);
-
-type GreatGrandChildActionList = Vec<GreatGrandChildAction>;
+impl<'de> Deserialize<'de> for Action {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(SerializedAction::deserialize(deserializer)?.into_action(0))
+    }
+}
 
-impl_action_list_try_from!(
-    GreatGrandChildActionList,
-    "great_grand_child_action",
-    "failed to convert great grand child action"
-);
+/// Pre-order traversal, see `Action::depth_first` and `depth_first`
+pub struct DepthFirst<'a> {
+    stack: Vec<&'a Action>,
+}
 
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
-struct GreatGrandChildAction {
-    common: CommonActionProperties,
-    great_great_grandchildren: Option<GreatGreatGrandChildActionList>,
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = &'a Action;
+    fn next(&mut self) -> Option<Self::Item> {
+        let action = self.stack.pop()?;
+        self.stack.extend(action.children.iter().rev());
+        Some(action)
+    }
 }
 
-impl fmt::Display for GreatGrandChildAction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.common)?;
+/// Level-order traversal, see `Action::breadth_first` and `breadth_first`
+pub struct BreadthFirst<'a> {
+    queue: VecDeque<&'a Action>,
+}
 
-        if let Some(great_great_grandchildren) = &self.great_great_grandchildren {
-            for great_great_grandchild in great_great_grandchildren {
-                write!(f, " >>>>{}", great_great_grandchild)?;
-            }
-        }
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = &'a Action;
+    fn next(&mut self) -> Option<Self::Item> {
+        let action = self.queue.pop_front()?;
+        self.queue.extend(action.children.iter());
+        Some(action)
+    }
+}
 
-        Ok(())
+/// Every action in the list in file order
+pub fn depth_first(action_list: &[Action]) -> DepthFirst<'_> {
+    DepthFirst {
+        stack: action_list.iter().rev().collect(),
     }
 }
 
-impl_action_node_try_from!(
-    GreatGrandChildAction,
-    great_great_grandchildren,
-    "great_great_grand_child_actions"
-);
+/// Every action in the list, all of the roots first, then all of their children and so on
+pub fn breadth_first(action_list: &[Action]) -> BreadthFirst<'_> {
+    BreadthFirst {
+        queue: action_list.iter().collect(),
+    }
+}
 
-type GreatGreatGrandChildActionList = Vec<GreatGreatGrandChildAction>;
+/// Every action in file order along with the path that leads to it
+pub fn walk(action_list: &[Action]) -> Vec<(ActionPath, &Action)> {
+    fn visit<'a>(
+        actions: &'a [Action],
+        path: &ActionPath,
+        out: &mut Vec<(ActionPath, &'a Action)>,
+    ) {
+        for (index, action) in actions.iter().enumerate() {
+            let mut action_path = path.clone();
+            action_path.push(index);
+            out.push((action_path.clone(), action));
+            visit(&action.children, &action_path, out);
+        }
+    }
 
-impl_action_list_try_from!(
-    GreatGreatGrandChildActionList,
-    "great_great_grand_child_action",
-    "failed to convert great great grand child action"
-);
+    let mut out = Vec::new();
+    visit(action_list, &Vec::new(), &mut out);
+    out
+}
 
+/// A single action pulled out of the tree without its children, along with where it came from
+///
+/// This is the shape used wherever actions are handled one at a time, such as NDJSON output and
+/// queries
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
-struct GreatGreatGrandChildAction {
-    common: CommonActionProperties,
-    leaf_children: Option<LeafActionList>,
+pub struct FlatAction {
+    pub path: ActionPath,
+    pub depth: usize,
+    /// The name of the parent action, if there is one
+    pub parent: Option<String>,
+    /// The shortest start of the id that is unique within the list
+    #[serde(default)]
+    pub short_id: Option<String>,
+    /// The file the action was read from, when it came out of a workspace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub common: CommonActionProperties,
 }
 
-impl fmt::Display for GreatGreatGrandChildAction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.common)?;
+/// Every action in file order as a `FlatAction`
+pub fn flatten(action_list: &[Action]) -> Vec<FlatAction> {
+    let prefixes = shortest_prefixes(action_list);
+    walk(action_list)
+        .into_iter()
+        .map(|(path, action)| FlatAction {
+            parent: ancestors(action_list, &path)
+                .next()
+                .map(|parent| parent.common.name.clone()),
+            short_id: action.common.id.and_then(|id| prefixes.get(&id).cloned()),
+            file: None,
+            path,
+            depth: action.depth,
+            common: action.common.clone(),
+        })
+        .collect()
+}
 
-        if let Some(leaf_children) = &self.leaf_children {
-            for leaf_child in leaf_children {
-                write!(f, " >>>>>{}", leaf_child)?;
-            }
-        }
+/// A copy of the list with every closed action dropped, taking its subtree along with it
+pub fn open_actions(action_list: &[Action]) -> ActionList {
+    action_list
+        .iter()
+        .filter(|action| action.common.state.is_open())
+        .map(|action| Action {
+            children: open_actions(&action.children),
+            ..action.clone()
+        })
+        .collect()
+}
 
-        Ok(())
-    }
+pub fn get_action<'a>(action_list: &'a [Action], path: &[usize]) -> Option<&'a Action> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(action_list.get(*first)?, |action, index| {
+            action.children.get(*index)
+        })
 }
 
-impl_action_node_try_from!(GreatGreatGrandChildAction, leaf_children, "leaf_actions");
+pub fn get_action_mut<'a>(action_list: &'a mut [Action], path: &[usize]) -> Option<&'a mut Action> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(action_list.get_mut(*first)?, |action, index| {
+            action.children.get_mut(*index)
+        })
+}
 
-type LeafActionList = Vec<LeafAction>;
+/// The parents of the action at `path`, starting with the closest one and ending at the root
+pub fn ancestors<'a>(action_list: &'a [Action], path: &[usize]) -> Ancestors<'a> {
+    let mut chain: Vec<&'a Action> = (1..path.len())
+        .filter_map(|length| get_action(action_list, &path[..length]))
+        .collect();
+    chain.reverse();
+    Ancestors {
+        chain: chain.into_iter(),
+    }
+}
 
-impl_action_list_try_from!(
-    LeafActionList,
-    "leaf_action",
-    "failed to convert leaf action"
-);
+pub struct Ancestors<'a> {
+    chain: std::vec::IntoIter<&'a Action>,
+}
 
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
-struct LeafAction {
-    common: CommonActionProperties,
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = &'a Action;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chain.next()
+    }
 }
 
-impl fmt::Display for LeafAction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.common)
+/// The other actions that share a parent with the action at `path`, in file order
+pub fn siblings<'a>(action_list: &'a [Action], path: &[usize]) -> Siblings<'a> {
+    let own_index = path.last().copied();
+    let level: &'a [Action] = match path.split_last() {
+        Some((_, [])) => action_list,
+        Some((_, parent_path)) => get_action(action_list, parent_path)
+            .map(|parent| parent.children.as_slice())
+            .unwrap_or(&[]),
+        None => &[],
+    };
+    Siblings {
+        level: level.iter().enumerate(),
+        own_index,
     }
 }
 
-impl<'a> TryFrom<NodeWrapper<'a>> for LeafAction {
-    type Error = &'static str;
-    fn try_from(value: NodeWrapper<'a>) -> Result<Self, Self::Error> {
-        let mut binding = value.node.walk();
-        let child_iterator = value.node.children(&mut binding);
-        let mut common = None;
-        for child in child_iterator {
-            if child.kind() == "core_action" {
-                let core_wrapper = create_node_wrapper(child, value.source.clone());
-                common = Some(core_wrapper.try_into()?);
-            }
-        }
-        Ok(LeafAction {
-            common: common.ok_or("Missing core action properties")?,
-        })
+pub struct Siblings<'a> {
+    level: std::iter::Enumerate<std::slice::Iter<'a, Action>>,
+    own_index: Option<usize>,
+}
+
+impl<'a> Iterator for Siblings<'a> {
+    type Item = &'a Action;
+    fn next(&mut self) -> Option<Self::Item> {
+        let own_index = self.own_index;
+        self.level
+            .by_ref()
+            .find(|(index, _)| Some(*index) != own_index)
+            .map(|(_, action)| action)
     }
 }
+
 #[derive(PartialEq, Default, Debug, Clone, Serialize, Deserialize)]
 pub struct CommonActionProperties {
     pub state: ActionState,
@@ -317,6 +486,32 @@ pub struct CommonActionProperties {
     pub id: Option<ActionId>,
     pub do_date_time: Option<ActionDoDateTime>,
     pub completed_date_time: Option<ActionCompletedDateTime>,
+    /// How the action repeats, kept with the do-date in the file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    /// The ids of the actions that have to be finished first, kept in the context list
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<Uuid>,
+}
+
+impl CommonActionProperties {
+    /// The context list as written in the file, `+` and all, along with what the action is
+    /// blocked by and any interval it repeats on
+    pub fn context_property(&self) -> Option<String> {
+        let tokens: Vec<String> = self
+            .context_list
+            .iter()
+            .flatten()
+            .map(|context| context.trim_start_matches('@').to_string())
+            .chain(
+                self.blocked_by
+                    .iter()
+                    .map(|id| format!("{}{}", BLOCKED_BY_PREFIX, id.simple())),
+            )
+            .chain(self.recurrence.as_ref().and_then(Recurrence::context))
+            .collect();
+        (!tokens.is_empty()).then(|| format!("+{}", tokens.join(",")))
+    }
 }
 
 impl fmt::Display for CommonActionProperties {
@@ -335,20 +530,25 @@ impl fmt::Display for CommonActionProperties {
         }
 
         // Context list (optional)
-        if let Some(context_list) = &self.context_list {
-            for context in context_list {
-                write!(f, " +{}", context.trim_start_matches('@'))?;
-            }
+        if let Some(contexts) = self.context_property() {
+            write!(f, " {}", contexts)?;
         }
 
         // Do date time (optional)
         if let Some(do_date_time) = &self.do_date_time {
-            write!(f, " @{}", do_date_time.format("%Y-%m-%dT%H:%M"))?;
+            write!(f, " @{}", do_date_time.format(ACTION_DATE_TIME_FORMAT))?;
+            if let Some(recurrence) = self.recurrence.as_ref().and_then(Recurrence::do_date_text) {
+                write!(f, " {}", recurrence)?;
+            }
         }
 
         // Completed date time (optional)
         if let Some(completed_date_time) = &self.completed_date_time {
-            write!(f, " %{}", completed_date_time.format("%Y-%m-%dT%H:%M"))?;
+            write!(
+                f,
+                " %{}",
+                completed_date_time.format(ACTION_DATE_TIME_FORMAT)
+            )?;
         }
 
         // ID (optional)
@@ -360,7 +560,7 @@ impl fmt::Display for CommonActionProperties {
     }
 }
 
-type Story = String;
+pub type Story = String;
 type ActionName = String;
 type ActionPriority = usize;
 type ActionDescription = String;
@@ -370,7 +570,7 @@ type ActionDoDateTime = DateTime<Local>;
 type ActionCompletedDateTime = DateTime<Local>;
 
 impl<'a> TryFrom<NodeWrapper<'a>> for CommonActionProperties {
-    type Error = &'static str;
+    type Error = Diagnostic;
     fn try_from(value: NodeWrapper<'a>) -> Result<Self, Self::Error> {
         let mut binding = value.node.walk();
         let child_iterator = value.node.children(&mut binding);
@@ -381,46 +581,106 @@ impl<'a> TryFrom<NodeWrapper<'a>> for CommonActionProperties {
         let mut priority = None;
         let mut context_list = None;
         let mut id = None;
-        let do_date_time = None;
-        let completed_date_time = None;
+        let mut do_date_time = None;
+        let mut completed_date_time = None;
+        let mut recurrence = None;
+        let mut blocked_by = Vec::new();
 
         for child in child_iterator {
             match child.kind() {
-                "state" => match child.child(1).unwrap().kind() {
-                    "not_started" => state = ActionState::NotStarted,
-                    "completed" => state = ActionState::Completed,
-                    "in_progress" => state = ActionState::InProgress,
-                    "blocked" => state = ActionState::BlockedorAwaiting,
-                    "cancelled" => state = ActionState::Cancelled,
-                    _ => return Err("Unknown or malformed action state"),
-                },
+                "state" => {
+                    let state_node = child
+                        .child(1)
+                        .ok_or_else(|| Diagnostic::error(&child, "action state is empty"))?;
+                    state = match state_node.kind() {
+                        "not_started" => ActionState::NotStarted,
+                        "completed" => ActionState::Completed,
+                        "in_progress" => ActionState::InProgress,
+                        "blocked" => ActionState::BlockedorAwaiting,
+                        "cancelled" => ActionState::Cancelled,
+                        _ => {
+                            return Err(Diagnostic::error(
+                                &child,
+                                format!(
+                                    "unknown action state `{}`",
+                                    get_node_text(&child, &value.source)
+                                ),
+                            ));
+                        }
+                    };
+                }
                 "name" => {
                     name = get_node_text(&child, &value.source).trim().to_string();
                 }
                 "description" => {
-                    description = Some(get_node_text(&child, &value.source).trim().to_string());
+                    description = find_descendant(&child, "description_text")
+                        .map(|text| get_node_text(&text, &value.source).trim().to_string());
                 }
                 "priority" => {
-                    if let Ok(prio) = get_node_text(&child, &value.source).trim().parse::<usize>() {
-                        priority = Some(prio);
-                    }
+                    let number = find_descendant(&child, "priority_number")
+                        .map(|number| get_node_text(&number, &value.source))
+                        .unwrap_or_default();
+                    priority = Some(number.parse::<usize>().map_err(|_| {
+                        Diagnostic::error(&child, format!("unable to read priority `{}`", number))
+                    })?);
                 }
                 "context_list" => {
-                    let context_text = get_node_text(&child, &value.source);
-                    let contexts: Vec<String> = context_text
-                        .split_whitespace()
-                        .filter(|s| s.starts_with('@'))
-                        .map(|s| s.to_string())
-                        .collect();
+                    // contexts are written without their `@` in the file, but we hold onto it so
+                    // they read the same way everywhere else
+                    let mut contexts = Vec::new();
+                    for context in find_descendants(&child, "context_text") {
+                        let text = get_node_text(&context, &value.source);
+                        if let Some(interval) = Recurrence::from_context(&text) {
+                            recurrence = Some(interval);
+                            continue;
+                        }
+                        match text
+                            .strip_prefix(BLOCKED_BY_PREFIX)
+                            .and_then(|id| Uuid::parse_str(id).ok())
+                        {
+                            Some(id) => blocked_by.push(id),
+                            None => contexts.push(format!("@{}", text)),
+                        }
+                    }
                     if !contexts.is_empty() {
                         context_list = Some(contexts);
                     }
                 }
-                "action" => {
-                    if let Ok(uuid) = Uuid::parse_str(get_node_text(&child, &value.source).trim()) {
-                        id = Some(uuid);
+                "id" => {
+                    let uuid_text = find_descendant(&child, "uuid")
+                        .map(|uuid| get_node_text(&uuid, &value.source))
+                        .unwrap_or_default();
+                    id = Some(Uuid::parse_str(&uuid_text).map_err(|_| {
+                        Diagnostic::error(&child, format!("unable to read id `{}`", uuid_text))
+                    })?);
+                }
+                "do_date_or_time" => {
+                    do_date_time =
+                        Some(parse_date_node(&child, &value.source).map_err(|message| {
+                            Diagnostic::error(
+                                &child,
+                                format!("unable to read do-date: {}", message),
+                            )
+                        })?);
+                    if let Some(repeat) = find_descendant(&child, "recurrance") {
+                        let text = get_node_text(&repeat, &value.source);
+                        recurrence = Some(text.parse::<Recurrence>().map_err(|message| {
+                            Diagnostic::error(
+                                &repeat,
+                                format!("unable to read repeat: {}", message),
+                            )
+                        })?);
                     }
                 }
+                "completed_date" => {
+                    completed_date_time =
+                        Some(parse_date_node(&child, &value.source).map_err(|message| {
+                            Diagnostic::error(
+                                &child,
+                                format!("unable to read completed date: {}", message),
+                            )
+                        })?);
+                }
                 _ => {} // Ignore other node types for now
             }
         }
@@ -434,8 +694,104 @@ impl<'a> TryFrom<NodeWrapper<'a>> for CommonActionProperties {
             id,
             do_date_time,
             completed_date_time,
+            recurrence,
+            blocked_by,
+        })
+    }
+}
+
+// the grammar only accepts a space between the date and the time, so this is the form we write
+const ACTION_DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// A date and time the way it is written into an action file
+pub fn format_action_date_time(date_time: &DateTime<Local>) -> String {
+    date_time.format(ACTION_DATE_TIME_FORMAT).to_string()
+}
+
+// both the do-date and completed date wrap a `date_and_time` node, the do-date also carries a
+// duration which we are not reading yet and the recurrence, which is read on its own
+fn parse_date_node(node: &Node, source: &str) -> Result<DateTime<Local>, String> {
+    let date_node = find_descendant(node, "date_and_time")
+        .ok_or_else(|| format!("no date found in '{}'", get_node_text(node, source)))?;
+
+    parse_action_date_time(&get_node_text(&date_node, source))
+}
+
+fn find_descendants<'a>(node: &Node<'a>, kind: &str) -> Vec<Node<'a>> {
+    let mut binding = node.walk();
+    let children: Vec<Node<'a>> = node.children(&mut binding).collect();
+    children
+        .into_iter()
+        .flat_map(|child| {
+            if child.kind() == kind {
+                vec![child]
+            } else {
+                find_descendants(&child, kind)
+            }
         })
+        .collect()
+}
+
+fn find_descendant<'a>(node: &Node<'a>, kind: &str) -> Option<Node<'a>> {
+    let mut binding = node.walk();
+    let children: Vec<Node<'a>> = node.children(&mut binding).collect();
+    children.into_iter().find_map(|child| {
+        if child.kind() == kind {
+            Some(child)
+        } else {
+            find_descendant(&child, kind)
+        }
+    })
+}
+
+/// Parse the date formats we accept for do-dates and completed dates:
+/// - date only (`2019-01-01`), which lands on local midnight
+/// - date and time (`2019-01-01 12:01`, `2019-01-01T12:01`, `2019-01-01 12:01 AM`)
+/// - date and time with an offset (`2019-01-01T12:01+02:00`, `2019-01-01T12:01:00Z`), which is
+///   converted into local time
+pub fn parse_action_date_time(text: &str) -> Result<DateTime<Local>, String> {
+    let text = text.trim();
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(date_time.with_timezone(&Local));
     }
+    for format in [
+        "%Y-%m-%d %H:%M%:z",
+        "%Y-%m-%dT%H:%M%:z",
+        "%Y-%m-%d %H:%M %:z",
+    ] {
+        if let Ok(date_time) = DateTime::parse_from_str(text, format) {
+            return Ok(date_time.with_timezone(&Local));
+        }
+    }
+
+    // `12:01 AM` and `12:01AM` are both valid in the grammar
+    let compact = text
+        .replace(" AM", "AM")
+        .replace(" PM", "PM")
+        .replace(" am", "am")
+        .replace(" pm", "pm");
+    let naive = [
+        "%Y-%m-%d %I:%M%p",
+        "%Y-%m-%dT%I:%M%p",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(&compact, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+    .ok_or_else(|| format!("unable to read '{}' as a date or date and time", text))?;
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("'{}' does not exist in the local timezone", text))
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -448,6 +804,40 @@ pub enum ActionState {
     Cancelled,
 }
 
+impl ActionState {
+    /// Completed and cancelled actions are closed, everything else still needs doing
+    pub fn is_open(&self) -> bool {
+        !matches!(self, ActionState::Completed | ActionState::Cancelled)
+    }
+}
+
+// accepts the markers used in the file as well as spelled out names, so `x`, `completed` and
+// `done` all mean the same thing
+impl std::str::FromStr for ActionState {
+    type Err = String;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let normalized: String = value
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .collect();
+        match (value, normalized.as_str()) {
+            (" ", _) | (_, "notstarted" | "todo") => Ok(ActionState::NotStarted),
+            ("x", _) | (_, "completed" | "complete" | "done") => Ok(ActionState::Completed),
+            ("-", _) | (_, "inprogress" | "started" | "doing") => Ok(ActionState::InProgress),
+            ("=", _) | (_, "blocked" | "awaiting" | "blockedorawaiting") => {
+                Ok(ActionState::BlockedorAwaiting)
+            }
+            ("_", _) | (_, "cancelled" | "canceled") => Ok(ActionState::Cancelled),
+            _ => Err(format!(
+                "unknown action state {}, expected one of not-started, in-progress, completed, blocked or cancelled",
+                value
+            )),
+        }
+    }
+}
+
 impl fmt::Display for ActionState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let state_char = match self {
@@ -31,14 +31,14 @@ pub fn get_config_map(custom_config_loc: Option<PathBuf>) -> Config {
             panic!("Failed to build configuration: {}", e);
         });
 
-    return settings.try_deserialize::<Map<String, Value>>().unwrap();
+    settings.try_deserialize::<Map<String, Value>>().unwrap()
 }
 pub fn ensure_path_exists(path: &PathBuf) {
     if !path.exists() {
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent).expect("Failed to create parent directory");
-            }
+        if let Some(parent) = path.parent()
+            && !parent.exists()
+        {
+            std::fs::create_dir_all(parent).expect("Failed to create parent directory");
         }
         std::fs::File::create(path).expect("Failed to create file");
     }
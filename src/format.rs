@@ -0,0 +1,147 @@
+//! Rewriting an action file into its canonical layout without losing anything it says
+//!
+//! Every action ends up on a single line, indented with one tab per level, with single spaces
+//! between its properties and the markers in the order `Display` writes them: `$ ! + @ % #`, and
+//! then the `*` story of a root action. The text of names, descriptions and stories is kept as
+//! written, apart from the whitespace around it, and dates keep their own form (`12:01AM`,
+//! durations and recurrences) with their spacing evened out.
+//!
+//! Comment lines, the ones starting with `//`, are kept as they are, and so are the blank lines
+//! separating groups of actions, although a run of them is collapsed to one.
+use std::ops::Range;
+
+use crate::edits::{map_source, visible};
+use crate::{COMMENT_PREFIX, mask_comments};
+
+struct Line {
+    range: Range<usize>,
+    kind: LineKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    Blank,
+    Comment,
+    Content,
+}
+
+fn split_lines(source: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for text in source.split_inclusive('\n') {
+        let trimmed = text.trim();
+        lines.push(Line {
+            range: start..start + text.len(),
+            kind: if trimmed.is_empty() {
+                LineKind::Blank
+            } else if trimmed.starts_with(COMMENT_PREFIX) {
+                LineKind::Comment
+            } else {
+                LineKind::Content
+            },
+        });
+        start += text.len();
+    }
+    lines
+}
+
+// a property with the space the grammar lets in after its marker removed, names and states have
+// no marker to speak of
+fn property_text(source: &str, kind: &str, range: &Range<usize>) -> String {
+    let text = &source[visible(source, range)];
+    let mut chars = text.chars();
+    let marker = chars.next().map(String::from).unwrap_or_default();
+    let rest = chars.as_str().trim();
+    match kind {
+        "state" | "name" => text.to_string(),
+        "description" | "story" => format!("{}{}", marker, rest),
+        "context_list" => format!("{}{}", marker, rest.split_whitespace().collect::<String>()),
+        _ => format!(
+            "{}{}",
+            marker,
+            rest.split_whitespace().collect::<Vec<_>>().join(" ")
+        ),
+    }
+}
+
+/// The source in canonical layout, or the reasons it could not be parsed
+///
+/// Formatting an already formatted file changes nothing.
+pub fn format_source(source: &str) -> Result<String, String> {
+    let lines = split_lines(source);
+
+    let masked = mask_comments(source);
+    let source_map = map_source(source)?;
+    let line_of = |byte: usize| {
+        lines
+            .iter()
+            .position(|line| line.range.contains(&byte))
+            .unwrap_or(lines.len())
+    };
+
+    // the lines each action is written over, and the single line it will be written as
+    let mut starts: Vec<(usize, usize, String)> = Vec::new();
+    for location in &source_map.locations {
+        let depth = location.path.len() - 1;
+        let mut text = if depth == 0 {
+            String::new()
+        } else {
+            format!("{}{} ", "\t".repeat(depth), ">".repeat(depth))
+        };
+        let parts: Vec<String> = location
+            .properties
+            .iter()
+            .map(|(kind, range)| property_text(&masked, kind, range))
+            .collect();
+        text.push_str(&parts.join(" "));
+        text.push('\n');
+
+        let end = location
+            .properties
+            .iter()
+            .map(|(_, range)| visible(&masked, range).end)
+            .max()
+            .unwrap_or(location.range.start);
+        starts.push((
+            line_of(location.range.start),
+            line_of(end.saturating_sub(1)),
+            text,
+        ));
+    }
+
+    let mut formatted = String::new();
+    let mut pending_blank = false;
+    let mut covered_until = None;
+    for (index, line) in lines.iter().enumerate() {
+        if let Some((_, last, text)) = starts.iter().find(|(first, _, _)| *first == index) {
+            if pending_blank && !formatted.is_empty() {
+                formatted.push('\n');
+            }
+            pending_blank = false;
+            formatted.push_str(text);
+            covered_until = Some(*last);
+            continue;
+        }
+        // a comment in the middle of an action written over several lines ends up after it
+        let covered = covered_until.is_some_and(|last| index <= last);
+        match line.kind {
+            LineKind::Blank if covered => {}
+            LineKind::Blank => pending_blank = true,
+            LineKind::Content if covered => {}
+            LineKind::Comment | LineKind::Content => {
+                if pending_blank && !formatted.is_empty() {
+                    formatted.push('\n');
+                }
+                pending_blank = false;
+                formatted.push_str(source[line.range.clone()].trim_end());
+                formatted.push('\n');
+            }
+        }
+    }
+    Ok(formatted)
+}
+
+/// Whether the source is already in canonical layout
+pub fn is_formatted(source: &str) -> Result<bool, String> {
+    Ok(format_source(source)? == source)
+}
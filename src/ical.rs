@@ -0,0 +1,218 @@
+//! Exporting actions for other tools to pick up
+//!
+//! The only format so far is iCalendar, where every action becomes a VTODO that calendar apps can
+//! subscribe to. Times are written in UTC so the file needs no timezone definitions.
+use chrono::{DateTime, Days, Duration, Local, NaiveTime, TimeZone, Utc};
+
+use crate::entities::{Action, ActionState, ancestors, walk};
+
+pub const EXPORT_FORMATS: [&str; 1] = ["ics"];
+
+pub fn export(
+    action_list: &[Action],
+    format: &str,
+    now: DateTime<Local>,
+) -> Result<String, String> {
+    match format {
+        "ics" => Ok(to_ics(action_list, now)),
+        _ => Err(format!(
+            "unknown export format {}, expected one of {}",
+            format,
+            EXPORT_FORMATS.join(", ")
+        )),
+    }
+}
+
+/// The UID of an action in the export, which is its id when it has one
+///
+/// An action without an id gets one made from where it is and what it is called, so it changes
+/// whenever the action is moved or renamed. `cliche ids assign` gives every action a lasting one.
+pub fn uid(action: &Action, path: &[usize]) -> String {
+    match action.common.id {
+        Some(id) => id.to_string(),
+        None => {
+            let position: Vec<String> = path.iter().map(|index| index.to_string()).collect();
+            format!(
+                "{}-{:016x}@cliche",
+                position.join("."),
+                fnv1a(action.common.name.as_bytes())
+            )
+        }
+    }
+}
+
+// a hash that stays the same from one build to the next, unlike the standard library's
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// TEXT values keep their meaning by escaping the characters iCalendar uses as separators
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn utc(date_time: &DateTime<Local>) -> String {
+    date_time
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+// lines longer than 75 bytes carry on in lines starting with a space, never splitting a character
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        let limit = if folded.is_empty() { 75 } else { 74 };
+        if length + c.len_utf8() > limit {
+            folded.push_str("\r\n ");
+            length = 0;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn status(state: ActionState) -> &'static str {
+    match state {
+        ActionState::NotStarted | ActionState::BlockedorAwaiting => "NEEDS-ACTION",
+        ActionState::InProgress => "IN-PROCESS",
+        ActionState::Completed => "COMPLETED",
+        ActionState::Cancelled => "CANCELLED",
+    }
+}
+
+/// The properties of the VTODO for one action, each as its `NAME:value` line before folding
+///
+/// A do-date with a time starts the todo then and has it due by the end of that day. One without a
+/// time only starts it on that day, since a due date on its own day would come before the start.
+/// A repeat carries over as an RRULE on open actions, taking its time from DTSTART even where
+/// `moves_repeat_time` says the repeat has a time of its own. Parent and
+/// child actions are linked both ways through RELATED-TO, as are the actions it is blocked by.
+pub fn vtodo_properties(
+    action_list: &[Action],
+    path: &[usize],
+    action: &Action,
+    now: DateTime<Local>,
+) -> Vec<String> {
+    let common = &action.common;
+    let mut lines = vec![
+        format!("UID:{}", uid(action, path)),
+        format!("DTSTAMP:{}", utc(&now)),
+    ];
+    // ids are handed out in time order, so they say when the action was first given one
+    if let Some(created) = common
+        .id
+        .and_then(|id| id.get_timestamp())
+        .map(|timestamp| timestamp.to_unix())
+        .and_then(|(seconds, nanos)| Local.timestamp_opt(seconds as i64, nanos).single())
+    {
+        lines.push(format!("CREATED:{}", utc(&created)));
+    }
+    lines.push(format!("SUMMARY:{}", escape(&common.name)));
+    if let Some(description) = &common.description {
+        lines.push(format!("DESCRIPTION:{}", escape(description)));
+    }
+    lines.push(format!("STATUS:{}", status(common.state)));
+    // both run from 1 as the most important, iCalendar stops at 9 and takes 0 as no priority
+    if let Some(priority) = common.priority {
+        lines.push(format!("PRIORITY:{}", priority.clamp(1, 9)));
+    }
+    if let Some(contexts) = &common.context_list {
+        let categories: Vec<String> = contexts
+            .iter()
+            .map(|context| escape(context.trim_start_matches('@')))
+            .collect();
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+    if let Some(do_date) = &common.do_date_time {
+        if do_date.time() == NaiveTime::MIN {
+            lines.push(format!(
+                "DTSTART;VALUE=DATE:{}",
+                do_date.date_naive().format("%Y%m%d")
+            ));
+        } else {
+            let next_day = do_date.date_naive().checked_add_days(Days::new(1));
+            let end_of_day = next_day
+                .and_then(|day| {
+                    Local
+                        .from_local_datetime(&day.and_time(NaiveTime::MIN))
+                        .earliest()
+                })
+                .unwrap_or(*do_date + Duration::days(1));
+            lines.push(format!("DTSTART:{}", utc(do_date)));
+            lines.push(format!("DUE:{}", utc(&end_of_day)));
+        }
+        // the rule's own time of day would be local against a DTSTART in UTC, or clash with a
+        // date, so DTSTART alone sets it; a closed action is done with, completing it adds the
+        // next occurrence as an action of its own
+        if let Some(recurrence) = common
+            .recurrence
+            .as_ref()
+            .filter(|_| common.state.is_open())
+        {
+            lines.push(format!("RRULE:{}", recurrence.without_time().to_rrule()));
+        }
+    }
+    if let Some(completed) = &common.completed_date_time {
+        lines.push(format!("COMPLETED:{}", utc(completed)));
+    }
+
+    if let Some(parent) = ancestors(action_list, path).next() {
+        lines.push(format!(
+            "RELATED-TO;RELTYPE=PARENT:{}",
+            uid(parent, &path[..path.len() - 1])
+        ));
+    }
+    for (index, child) in action.children.iter().enumerate() {
+        let mut child_path = path.to_vec();
+        child_path.push(index);
+        lines.push(format!(
+            "RELATED-TO;RELTYPE=CHILD:{}",
+            uid(child, &child_path)
+        ));
+    }
+    for blocker in &common.blocked_by {
+        lines.push(format!("RELATED-TO;RELTYPE=DEPENDS-ON:{}", blocker));
+    }
+    lines
+}
+
+/// Whether the action repeats at a time of day other than its do-date's, which the export cannot
+/// keep since its RRULE takes the time from DTSTART
+pub fn moves_repeat_time(action: &Action) -> bool {
+    let common = &action.common;
+    let repeat_time = common
+        .recurrence
+        .as_ref()
+        .filter(|_| common.state.is_open())
+        .and_then(|recurrence| recurrence.time());
+    common
+        .do_date_time
+        .zip(repeat_time)
+        .is_some_and(|(do_date, time)| do_date.time() != time)
+}
+
+/// Every action in the list, open or not, as a VTODO in one VCALENDAR
+pub fn to_ics(action_list: &[Action], now: DateTime<Local>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//cliche//cliche {}//EN", env!("CARGO_PKG_VERSION")),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for (path, action) in walk(action_list) {
+        lines.push("BEGIN:VTODO".to_string());
+        lines.extend(vtodo_properties(action_list, &path, action, now));
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect()
+}
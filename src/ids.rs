@@ -0,0 +1,126 @@
+//! Giving actions stable ids
+//!
+//! Ids are UUIDv7, so they sort in the order they were handed out and say roughly when that was.
+//! Since nobody wants to type one out, they can be shortened to the shortest unique prefix, and
+//! `read` can number what it shows so the next command can refer to `3` instead.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use uuid::Uuid;
+
+use crate::edits::{TextEdit, apply_edits, map_source, set_property};
+use crate::entities::{Action, ActionPath, depth_first, walk};
+
+/// The source with an id written into every action that lacks one, along with how many were added
+///
+/// `new_id` is called once per action in file order, `Uuid::now_v7` outside of tests. Actions that
+/// already have an id, and the rest of the text, are left exactly as they were.
+pub fn assign_ids(
+    source: &str,
+    mut new_id: impl FnMut() -> Uuid,
+) -> Result<(String, usize), String> {
+    let source_map = map_source(source)?;
+    let edits: Vec<TextEdit> = source_map
+        .locations
+        .iter()
+        .filter(|location| {
+            source_map
+                .action(&location.path)
+                .is_some_and(|action| action.common.id.is_none())
+        })
+        .filter_map(|location| {
+            set_property(source, location, "id", Some(&format!("#{}", new_id())))
+        })
+        .collect();
+
+    let count = edits.len();
+    Ok((apply_edits(source, &edits), count))
+}
+
+/// Ids are never shortened below this many hex digits, so that a prefix stays recognisable as one
+pub const MIN_PREFIX_LENGTH: usize = 4;
+
+/// The shortest start of each id, in hex without dashes, that no other id in the list shares
+pub fn shortest_prefixes(action_list: &[Action]) -> HashMap<Uuid, String> {
+    let mut ids: Vec<String> = depth_first(action_list)
+        .filter_map(|action| action.common.id)
+        .map(|id| id.simple().to_string())
+        .collect();
+    ids.sort();
+
+    let shared = |left: &str, right: &str| {
+        left.chars()
+            .zip(right.chars())
+            .take_while(|(l, r)| l == r)
+            .count()
+    };
+
+    // in sorted order the id sharing the longest start with any given id is one of its neighbours
+    let mut prefixes = HashMap::new();
+    for (index, id) in ids.iter().enumerate() {
+        let before = index
+            .checked_sub(1)
+            .map(|previous| shared(id, &ids[previous]))
+            .unwrap_or(0);
+        let after = ids.get(index + 1).map(|next| shared(id, next)).unwrap_or(0);
+        let length = (before.max(after) + 1).clamp(MIN_PREFIX_LENGTH, id.len());
+        if let Ok(uuid) = Uuid::parse_str(id) {
+            prefixes.insert(uuid, id[..length].to_string());
+        }
+    }
+    prefixes
+}
+
+/// The numbers shown by the last `read` of a file, which stay good for as long as the file is
+/// exactly as it was then
+///
+/// Each number is stored as the action's position in the file, which is what selectors already
+/// understand, so handing a number to a command only needs a lookup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub source_hash: u64,
+    pub positions: Vec<usize>,
+}
+
+/// A hash of the text for noticing that it changed, not for telling it apart from anything else
+pub fn text_hash(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Session {
+    /// Numbers 1 and up for the actions at `paths`, in the order they were shown
+    pub fn new(source: &str, action_list: &[Action], paths: &[ActionPath]) -> Self {
+        let walked: Vec<ActionPath> = walk(action_list)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        Session {
+            source_hash: text_hash(source),
+            positions: paths
+                .iter()
+                .filter_map(|path| walked.iter().position(|walked| walked == path))
+                .map(|position| position + 1)
+                .collect(),
+        }
+    }
+
+    /// The selector a shown number stands for, anything that is not a number is passed through
+    pub fn translate(&self, source: &str, selector: &str) -> Result<String, String> {
+        let Ok(number) = selector.trim().parse::<usize>() else {
+            return Ok(selector.to_string());
+        };
+        if text_hash(source) != self.source_hash {
+            return Err(format!(
+                "the action file changed since [{}] was shown, read it again to get new numbers",
+                number
+            ));
+        }
+        number
+            .checked_sub(1)
+            .and_then(|index| self.positions.get(index))
+            .map(|position| position.to_string())
+            .ok_or_else(|| format!("no action was shown as [{}]", number))
+    }
+}
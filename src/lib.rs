@@ -3,8 +3,47 @@ use tree_sitter::Tree;
 
 pub mod treesitter;
 
+pub mod diagnostics;
+use diagnostics::{Diagnostic, ParseError};
+
 pub mod entities;
-use entities::ActionList;
+use entities::{ActionList, ParsedActions};
+
+pub mod diff;
+
+pub mod edits;
+
+pub mod format;
+
+pub mod ids;
+
+pub mod lint;
+
+pub mod output;
+
+pub mod query;
+
+pub mod selector;
+
+pub mod transitions;
+
+pub mod workspace;
+
+pub mod archive;
+
+pub mod recurrence;
+
+pub mod dependencies;
+
+pub mod next;
+
+pub mod agenda;
+
+pub mod tui;
+
+pub mod subtree;
+
+pub mod ical;
 
 // merging json hashmaps as our universal structure
 pub fn merge_hashmaps(
@@ -18,33 +57,63 @@ pub fn merge_hashmaps(
     Ok(Value::Object(merged))
 }
 
-pub fn get_action_list_struct(_opts: &Value, actions: &str) -> Result<ActionList, String> {
-    let tree = get_action_list_tree(actions)?;
+/// Lines starting with this, after any indentation, are comments
+///
+/// The grammar has no comments of its own, so they are blanked out before anything is parsed and
+/// every command leaves them where they are.
+pub const COMMENT_PREFIX: &str = "//";
+
+/// The source with every comment line turned into spaces, keeping every byte where it was so that
+/// positions in the parse still point into the original
+pub fn mask_comments(source: &str) -> String {
+    source
+        .split_inclusive('\n')
+        .map(|line| match line.trim_start().starts_with(COMMENT_PREFIX) {
+            true => line
+                .chars()
+                .map(|c| if c == '\n' { '\n' } else { ' ' })
+                .collect(),
+            false => line.to_string(),
+        })
+        .collect()
+}
 
-    let tree_wrapper = treesitter::TreeWrapper {
-        tree,
-        source: actions.to_string(),
-    };
+pub fn get_action_list_struct(_opts: &Value, actions: &str) -> Result<ActionList, ParseError> {
+    let tree_wrapper = get_tree_wrapper(actions)
+        .map_err(|message| ParseError::from(Diagnostic::for_source(message)))?;
     let action_list: ActionList = tree_wrapper.try_into()?;
 
-    return Ok(action_list);
+    Ok(action_list)
+}
+
+// the lenient version of the above, handing back whatever actions could be read along with a
+// diagnostic for everything that could not
+pub fn get_action_list_lenient(_opts: &Value, actions: &str) -> Result<ParsedActions, ParseError> {
+    let tree_wrapper = get_tree_wrapper(actions)
+        .map_err(|message| ParseError::from(Diagnostic::for_source(message)))?;
+
+    Ok(tree_wrapper.into())
 }
 // this is the function where we actually use treesitter to parse the actions into the tree, and
 // translate that into a proper vector of hashmaps so that we are passing back plain data
 pub fn get_action_list(_opts: &Value, actions: String) -> Result<Value, String> {
-    let tree = get_action_list_tree(&actions)?;
-
-    let tree_wrapper = treesitter::TreeWrapper {
-        tree,
-        source: actions.clone(),
-    };
+    let tree_wrapper = get_tree_wrapper(&actions)?;
 
     let action_list: ActionList = tree_wrapper.try_into()?;
 
-    return Ok(serde_json::to_value(&action_list).unwrap());
+    Ok(serde_json::to_value(&action_list).unwrap())
+}
+
+// the tree along with the source it was parsed from, which has its comments blanked out so that
+// none of them can end up in what is read from it
+pub(crate) fn get_tree_wrapper(actions: &str) -> Result<treesitter::TreeWrapper, String> {
+    Ok(treesitter::TreeWrapper {
+        tree: get_action_list_tree(actions)?,
+        source: mask_comments(actions),
+    })
 }
 
-fn get_action_list_tree(actions: &str) -> Result<Tree, String> {
+pub(crate) fn get_action_list_tree(actions: &str) -> Result<Tree, String> {
     let mut action_parser = tree_sitter::Parser::new();
 
     action_parser
@@ -52,6 +121,6 @@ fn get_action_list_tree(actions: &str) -> Result<Tree, String> {
         .expect("Failed to set language for tree-sitter parser");
 
     action_parser
-        .parse(actions, None)
+        .parse(mask_comments(actions), None)
         .ok_or("Failed to parse tree".to_string())
 }
@@ -0,0 +1,724 @@
+//! Checking an action file for things that parse but are probably mistakes
+//!
+//! Each rule has an id, a level it runs at unless the config says otherwise, and a check that
+//! reports the nodes it objects to. Rules work on the syntax tree rather than the action list, so
+//! a file with a few broken lines still gets checked everywhere else.
+//!
+//! Rules are set in the `[lint]` table of the config file:
+//!
+//! ```toml
+//! [lint]
+//! priority_min = 1
+//! priority_max = 3
+//! contexts = ["home", "work"]
+//!
+//! [lint.rules]
+//! past-do-date = "off"
+//! unknown-context = "error"
+//! ```
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use tree_sitter::Node;
+use uuid::Uuid;
+
+use crate::diagnostics::{Diagnostic, Severity, syntax_diagnostics};
+use crate::entities::{ActionState, CommonActionProperties, MAX_ACTION_DEPTH};
+use crate::treesitter::{action_node_depth, action_nodes, create_node_wrapper, get_node_text};
+use crate::{get_action_list_tree, mask_comments};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Warning,
+    Error,
+}
+
+impl RuleLevel {
+    fn severity(&self) -> Option<Severity> {
+        match self {
+            RuleLevel::Off => None,
+            RuleLevel::Warning => Some(Severity::Warning),
+            RuleLevel::Error => Some(Severity::Error),
+        }
+    }
+}
+
+fn default_priority_min() -> usize {
+    1
+}
+
+fn default_priority_max() -> usize {
+    5
+}
+
+fn default_max_depth() -> usize {
+    MAX_ACTION_DEPTH
+}
+
+/// The `[lint]` table of the config, every part of it can be left out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintConfig {
+    /// Levels for rules by id, overriding their defaults
+    #[serde(default)]
+    pub rules: HashMap<String, RuleLevel>,
+    #[serde(default = "default_priority_min")]
+    pub priority_min: usize,
+    #[serde(default = "default_priority_max")]
+    pub priority_max: usize,
+    /// The contexts actions may use, with or without their `@`, any context is fine when unset
+    #[serde(default)]
+    pub contexts: Option<Vec<String>>,
+    /// How deep actions may be nested, the grammar itself stops at 5
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            rules: HashMap::new(),
+            priority_min: default_priority_min(),
+            priority_max: default_priority_max(),
+            contexts: None,
+            max_depth: default_max_depth(),
+        }
+    }
+}
+
+impl LintConfig {
+    /// Read the `[lint]` table out of the options, refusing rule ids that do not exist so a typo
+    /// does not silently leave a rule at its default
+    pub fn from_opts(opts: &Value) -> Result<Self, String> {
+        let config: LintConfig = match opts.get("lint") {
+            Some(lint) => serde_json::from_value(lint.clone())
+                .map_err(|e| format!("invalid [lint] settings: {}", e))?,
+            None => LintConfig::default(),
+        };
+        if let Some(unknown) = config
+            .rules
+            .keys()
+            .find(|id| !RULES.iter().any(|rule| rule.id == id.as_str()))
+        {
+            return Err(format!(
+                "unknown lint rule {}, expected one of {}",
+                unknown,
+                RULES.map(|rule| rule.id).join(", ")
+            ));
+        }
+        Ok(config)
+    }
+
+    pub fn level(&self, rule: &Rule) -> RuleLevel {
+        self.rules
+            .get(rule.id)
+            .copied()
+            .unwrap_or(rule.default_level)
+    }
+}
+
+/// One action as the rules see it, read straight from its node
+pub struct LintAction<'tree> {
+    pub node: Node<'tree>,
+    pub common: CommonActionProperties,
+    /// The depth the action was written at, which can be more than the grammar allows
+    pub depth: usize,
+    /// Position of the parent in `LintContext::actions`
+    pub parent: Option<usize>,
+}
+
+impl<'tree> LintAction<'tree> {
+    /// The node of one of the action's properties, or the action itself when it does not have it
+    pub fn property(&self, kind: &str) -> Node<'tree> {
+        let mut binding = self.node.walk();
+        let core = self
+            .node
+            .children(&mut binding)
+            .find(|child| child.kind() == "core_action");
+        core.and_then(|core| {
+            let mut core_binding = core.walk();
+            core.children(&mut core_binding)
+                .find(|child| child.kind() == kind)
+        })
+        .unwrap_or(self.node)
+    }
+}
+
+pub struct LintContext<'tree> {
+    pub source: &'tree str,
+    pub root: Node<'tree>,
+    pub actions: Vec<LintAction<'tree>>,
+    pub config: &'tree LintConfig,
+    pub now: DateTime<Local>,
+    /// The ids of every action in the file and the rest of the workspace
+    pub ids: HashSet<Uuid>,
+}
+
+pub struct Rule {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub default_level: RuleLevel,
+    check: fn(&LintContext) -> Vec<Diagnostic>,
+}
+
+pub const RULES: [Rule; 11] = [
+    Rule {
+        id: "syntax",
+        description: "The file has to parse",
+        default_level: RuleLevel::Error,
+        check: check_syntax,
+    },
+    Rule {
+        id: "duplicate-id",
+        description: "No two actions share an id",
+        default_level: RuleLevel::Error,
+        check: check_duplicate_ids,
+    },
+    Rule {
+        id: "empty-name",
+        description: "Every action has a name",
+        default_level: RuleLevel::Error,
+        check: check_empty_names,
+    },
+    Rule {
+        id: "completed-without-date",
+        description: "Completed actions say when they were completed",
+        default_level: RuleLevel::Warning,
+        check: check_completed_without_date,
+    },
+    Rule {
+        id: "completed-date-on-open",
+        description: "Open actions have no completed date",
+        default_level: RuleLevel::Warning,
+        check: check_completed_date_on_open,
+    },
+    Rule {
+        id: "open-under-closed",
+        description: "Actions under a completed or cancelled parent are closed too",
+        default_level: RuleLevel::Warning,
+        check: check_open_under_closed,
+    },
+    Rule {
+        id: "priority-range",
+        description: "Priorities fall between priority_min and priority_max",
+        default_level: RuleLevel::Warning,
+        check: check_priority_range,
+    },
+    Rule {
+        id: "unknown-context",
+        description: "Contexts are ones listed in the contexts setting",
+        default_level: RuleLevel::Warning,
+        check: check_unknown_contexts,
+    },
+    Rule {
+        id: "past-do-date",
+        description: "Open actions are not due to be done in the past",
+        default_level: RuleLevel::Warning,
+        check: check_past_do_dates,
+    },
+    Rule {
+        id: "too-deep",
+        description: "Actions are nested no deeper than max_depth or the grammar allows",
+        default_level: RuleLevel::Error,
+        check: check_depth,
+    },
+    Rule {
+        id: "unknown-blocker",
+        description: "Actions are only blocked by ids some action has",
+        default_level: RuleLevel::Warning,
+        check: check_unknown_blockers,
+    },
+];
+
+/// A diagnostic along with the rule that raised it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    pub rule: String,
+    #[serde(flatten)]
+    pub diagnostic: Diagnostic,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let diagnostic = &self.diagnostic;
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            diagnostic.line, diagnostic.column, diagnostic.severity, self.rule, diagnostic.message
+        )
+    }
+}
+
+// the extra `>`s of an action nested past the grammar's limit end up in an ERROR node, so they are
+// counted as written rather than taken from the node kind
+fn written_depth(node: &Node, source: &str) -> usize {
+    get_node_text(node, source)
+        .chars()
+        .take_while(|c| *c == '>' || c.is_whitespace())
+        .filter(|c| *c == '>')
+        .count()
+}
+
+fn lint_actions<'tree>(root: &Node<'tree>, source: &str) -> Vec<LintAction<'tree>> {
+    let nodes = action_nodes(root);
+    let mut actions: Vec<LintAction<'tree>> = Vec::new();
+    for node in nodes {
+        let mut binding = node.walk();
+        let Some(core) = node
+            .children(&mut binding)
+            .find(|child| child.kind() == "core_action")
+        else {
+            continue;
+        };
+        let Ok(common) = create_node_wrapper(core, source.to_string()).try_into() else {
+            continue;
+        };
+
+        // the parent of an action node is its list, whose parent is the parent action
+        let parent_node = node.parent().and_then(|list| list.parent());
+        let parent = parent_node.and_then(|parent_node| {
+            actions
+                .iter()
+                .rposition(|action| action.node.id() == parent_node.id())
+        });
+        actions.push(LintAction {
+            depth: written_depth(&node, source).max(action_node_depth(node.kind()).unwrap_or(0)),
+            node,
+            common,
+            parent,
+        });
+    }
+    actions
+}
+
+/// Every finding of every rule that is not turned off, in file order
+pub fn lint(
+    source: &str,
+    config: &LintConfig,
+    now: DateTime<Local>,
+) -> Result<Vec<Finding>, String> {
+    lint_in_workspace(source, config, now, &HashSet::new())
+}
+
+/// Like `lint`, for a file whose actions can be blocked by the `other_ids` of other files
+pub fn lint_in_workspace(
+    source: &str,
+    config: &LintConfig,
+    now: DateTime<Local>,
+    other_ids: &HashSet<Uuid>,
+) -> Result<Vec<Finding>, String> {
+    let tree = get_action_list_tree(source)?;
+    // comments are not actions, so no rule gets to see them
+    let source = &mask_comments(source);
+    let root = tree.root_node();
+    let actions = lint_actions(&root, source);
+    let ids = actions
+        .iter()
+        .filter_map(|action| action.common.id)
+        .chain(other_ids.iter().copied())
+        .collect();
+    let context = LintContext {
+        source,
+        root,
+        actions,
+        config,
+        now,
+        ids,
+    };
+
+    let mut findings: Vec<Finding> = RULES
+        .iter()
+        .filter_map(|rule| {
+            config
+                .level(rule)
+                .severity()
+                .map(|severity| (rule, severity))
+        })
+        .flat_map(|(rule, severity)| {
+            (rule.check)(&context)
+                .into_iter()
+                .map(move |diagnostic| Finding {
+                    rule: rule.id.to_string(),
+                    diagnostic: Diagnostic {
+                        severity,
+                        ..diagnostic
+                    },
+                })
+        })
+        .collect();
+    findings.sort_by_key(|finding| finding.diagnostic.start_byte);
+    Ok(findings)
+}
+
+/// The ids of every action in the file that reads, for linting the other files of a workspace
+pub fn action_ids(source: &str) -> Result<HashSet<Uuid>, String> {
+    let tree = get_action_list_tree(source)?;
+    let source = &mask_comments(source);
+    Ok(lint_actions(&tree.root_node(), source)
+        .iter()
+        .filter_map(|action| action.common.id)
+        .collect())
+}
+
+fn check_syntax(context: &LintContext) -> Vec<Diagnostic> {
+    syntax_diagnostics(&context.root, context.source)
+        .into_iter()
+        .filter(|diagnostic| {
+            let text = context.source[diagnostic.start_byte..diagnostic.end_byte].trim();
+            // stray `>`s are left to too-deep, which can say what is actually wrong
+            text.is_empty() || !text.chars().all(|c| c == '>')
+        })
+        .collect()
+}
+
+fn check_duplicate_ids(context: &LintContext) -> Vec<Diagnostic> {
+    let mut seen = HashMap::new();
+    let mut diagnostics = Vec::new();
+    for action in &context.actions {
+        let Some(id) = action.common.id else {
+            continue;
+        };
+        match seen.get(&id) {
+            Some(first_line) => diagnostics.push(Diagnostic::from_node(
+                &action.property("id"),
+                Severity::Error,
+                format!("#{} is already used on line {}", id.simple(), first_line),
+            )),
+            None => {
+                seen.insert(id, action.node.start_position().row + 1);
+            }
+        }
+    }
+    diagnostics
+}
+
+fn check_empty_names(context: &LintContext) -> Vec<Diagnostic> {
+    context
+        .actions
+        .iter()
+        .filter(|action| action.common.name.trim().is_empty())
+        .map(|action| {
+            Diagnostic::from_node(
+                &action.property("name"),
+                Severity::Error,
+                "the name is empty",
+            )
+        })
+        .collect()
+}
+
+fn check_completed_without_date(context: &LintContext) -> Vec<Diagnostic> {
+    context
+        .actions
+        .iter()
+        .filter(|action| {
+            action.common.state == ActionState::Completed
+                && action.common.completed_date_time.is_none()
+        })
+        .map(|action| {
+            Diagnostic::from_node(
+                &action.property("state"),
+                Severity::Warning,
+                format!("{} is completed but has no %date", action.common.name),
+            )
+        })
+        .collect()
+}
+
+fn check_completed_date_on_open(context: &LintContext) -> Vec<Diagnostic> {
+    context
+        .actions
+        .iter()
+        .filter(|action| {
+            action.common.state.is_open() && action.common.completed_date_time.is_some()
+        })
+        .map(|action| {
+            Diagnostic::from_node(
+                &action.property("completed_date"),
+                Severity::Warning,
+                format!(
+                    "{} is still open but has a completed date",
+                    action.common.name
+                ),
+            )
+        })
+        .collect()
+}
+
+fn check_open_under_closed(context: &LintContext) -> Vec<Diagnostic> {
+    context
+        .actions
+        .iter()
+        .filter(|action| action.common.state.is_open())
+        .filter_map(|action| {
+            let parent = &context.actions[action.parent?];
+            (!parent.common.state.is_open()).then(|| {
+                Diagnostic::from_node(
+                    &action.property("state"),
+                    Severity::Warning,
+                    format!(
+                        "{} is open but its parent {} is closed",
+                        action.common.name, parent.common.name
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+fn check_priority_range(context: &LintContext) -> Vec<Diagnostic> {
+    let range = context.config.priority_min..=context.config.priority_max;
+    context
+        .actions
+        .iter()
+        .filter_map(|action| {
+            let priority = action.common.priority?;
+            (!range.contains(&priority)).then(|| {
+                Diagnostic::from_node(
+                    &action.property("priority"),
+                    Severity::Warning,
+                    format!(
+                        "priority {} is outside of {} to {}",
+                        priority,
+                        range.start(),
+                        range.end()
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+fn check_unknown_contexts(context: &LintContext) -> Vec<Diagnostic> {
+    let Some(known) = &context.config.contexts else {
+        return Vec::new();
+    };
+    let known: Vec<String> = known
+        .iter()
+        .map(|context| format!("@{}", context.trim_start_matches('@')))
+        .collect();
+    context
+        .actions
+        .iter()
+        .flat_map(|action| {
+            action
+                .common
+                .context_list
+                .iter()
+                .flatten()
+                .filter(|context| !known.contains(context))
+                .map(|unknown| {
+                    Diagnostic::from_node(
+                        &action.property("context_list"),
+                        Severity::Warning,
+                        format!("{} is not one of the configured contexts", unknown),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn check_past_do_dates(context: &LintContext) -> Vec<Diagnostic> {
+    context
+        .actions
+        .iter()
+        .filter(|action| action.common.state.is_open())
+        .filter_map(|action| {
+            let do_date = action.common.do_date_time?;
+            (do_date < context.now).then(|| {
+                Diagnostic::from_node(
+                    &action.property("do_date_or_time"),
+                    Severity::Warning,
+                    format!(
+                        "{} is still open but was to be done {}",
+                        action.common.name,
+                        do_date.format("%Y-%m-%d %H:%M")
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+fn check_depth(context: &LintContext) -> Vec<Diagnostic> {
+    let max_depth = context.config.max_depth.min(MAX_ACTION_DEPTH);
+    context
+        .actions
+        .iter()
+        .filter(|action| action.depth > max_depth)
+        .map(|action| {
+            Diagnostic::from_node(
+                &action.node,
+                Severity::Error,
+                format!(
+                    "{} is nested {} deep, at most {} is allowed",
+                    action.common.name, action.depth, max_depth
+                ),
+            )
+        })
+        .collect()
+}
+
+// what waits on an id no action has waits forever, which is usually a typo or a blocker that was
+// deleted rather than finished
+fn check_unknown_blockers(context: &LintContext) -> Vec<Diagnostic> {
+    context
+        .actions
+        .iter()
+        .flat_map(|action| {
+            action
+                .common
+                .blocked_by
+                .iter()
+                .filter(|id| !context.ids.contains(id))
+                .map(|id| {
+                    Diagnostic::from_node(
+                        &action.property("context_list"),
+                        Severity::Warning,
+                        format!(
+                            "{} is blocked by {}, which no action has as its id",
+                            action.common.name, id
+                        ),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+pub const LINT_FORMATS: [&str; 3] = ["text", "json", "sarif"];
+
+/// The findings of one file, which SARIF needs the source of to count columns the way it does
+pub fn render_findings(
+    findings: &[Finding],
+    path: &str,
+    source: &str,
+    format: &str,
+) -> Result<String, String> {
+    match format {
+        "text" => Ok(findings
+            .iter()
+            .map(|finding| format!("{}:{}\n", path, finding))
+            .collect()),
+        "json" => serde_json::to_string_pretty(findings)
+            .map(|json| format!("{}\n", json))
+            .map_err(|e| format!("unable to serialize findings: {}", e)),
+        "sarif" => serde_json::to_string_pretty(&sarif(findings, path, source))
+            .map(|json| format!("{}\n", json))
+            .map_err(|e| format!("unable to serialize findings: {}", e)),
+        _ => Err(format!(
+            "unknown lint format {}, expected one of {}",
+            format,
+            LINT_FORMATS.join(", ")
+        )),
+    }
+}
+
+/// The findings of several files at once, each with its path and source, JSON as one
+/// `{file, findings}` entry per file and SARIF as a single run covering every file
+pub fn render_file_findings(
+    files: &[(String, String, Vec<Finding>)],
+    format: &str,
+) -> Result<String, String> {
+    match format {
+        "text" => files
+            .iter()
+            .map(|(path, source, findings)| render_findings(findings, path, source, format))
+            .collect(),
+        "json" => {
+            let entries: Vec<Value> = files
+                .iter()
+                .map(|(path, _, findings)| json!({ "file": path, "findings": findings }))
+                .collect();
+            serde_json::to_string_pretty(&entries)
+                .map(|json| format!("{}\n", json))
+                .map_err(|e| format!("unable to serialize findings: {}", e))
+        }
+        "sarif" => serde_json::to_string_pretty(&sarif_files(files))
+            .map(|json| format!("{}\n", json))
+            .map_err(|e| format!("unable to serialize findings: {}", e)),
+        _ => render_findings(&[], "", "", format),
+    }
+}
+
+/// The findings as a SARIF 2.1.0 log, which code review tools can show inline
+pub fn sarif(findings: &[Finding], path: &str, source: &str) -> Value {
+    sarif_files(&[(path.to_string(), source.to_string(), findings.to_vec())])
+}
+
+// SARIF counts columns in UTF-16 code units unless told otherwise, where tree-sitter counts bytes
+fn utf16_column(source: &str, byte: usize) -> usize {
+    let byte = byte.min(source.len());
+    let line_start = source[..byte].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    source
+        .get(line_start..byte)
+        .map(|text| text.encode_utf16().count())
+        .unwrap_or(0)
+        + 1
+}
+
+fn sarif_files(files: &[(String, String, Vec<Finding>)]) -> Value {
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id,
+                "shortDescription": { "text": rule.description },
+                "defaultConfiguration": { "level": sarif_level(rule.default_level.severity()) },
+            })
+        })
+        .collect();
+    let results: Vec<Value> = files
+        .iter()
+        .flat_map(|(path, source, findings)| {
+            findings.iter().map(move |finding| (path, source, finding))
+        })
+        .map(|(path, source, finding)| {
+            let diagnostic = &finding.diagnostic;
+            json!({
+                "ruleId": finding.rule,
+                "ruleIndex": RULES.iter().position(|rule| rule.id == finding.rule),
+                "level": sarif_level(Some(diagnostic.severity)),
+                "message": { "text": diagnostic.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": path },
+                        "region": {
+                            "startLine": diagnostic.line,
+                            "startColumn": utf16_column(source, diagnostic.start_byte),
+                            "byteOffset": diagnostic.start_byte,
+                            "byteLength": diagnostic.end_byte - diagnostic.start_byte,
+                        },
+                    },
+                }],
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+fn sarif_level(severity: Option<Severity>) -> &'static str {
+    match severity {
+        Some(Severity::Error) => "error",
+        Some(Severity::Warning) => "warning",
+        None => "none",
+    }
+}
@@ -1,9 +1,45 @@
+use chrono::Local;
+use cliche::agenda::{build_agenda, render_agenda};
+use cliche::archive::{ArchivedAction, append_to_archive, archive_actions};
+use cliche::dependencies::{
+    DependencyGraph, drop_blockers, render_graph, to_unblock, unblock_actions,
+};
+use cliche::diagnostics::Severity;
+use cliche::diff::unified_diff;
+use cliche::edits::{
+    ActionUpdate, ChildHandling, create_action, delete_action, map_source, normalize_context,
+    update_action,
+};
+use cliche::entities::{
+    ActionPath, ActionState, CommonActionProperties, ancestors, get_action, open_actions,
+    parse_action_date_time, walk,
+};
+use cliche::format::format_source;
+use cliche::ical::{export as export_actions, moves_repeat_time};
+use cliche::ids::{Session, assign_ids, text_hash};
+use cliche::lint::{
+    LintConfig, action_ids, lint_in_workspace, render_file_findings, render_findings,
+};
 use cliche::merge_hashmaps;
-use std::path::PathBuf;
+use cliche::next::{RankOptions, rank_actions, render_ranking};
+use cliche::output::{render, render_groups};
+use cliche::query::{QueryOptions, number_actions, parse_duration, query_actions};
+use cliche::recurrence::{RecurFrom, Recurrence, complete_action};
+use cliche::selector::select_one;
+use cliche::subtree::{extract_subtree, splice_subtree, strip_comments, with_problems};
+use cliche::transitions::{TransitionPolicy, transition_action};
+use cliche::tui::{App, Store};
+use cliche::workspace::{Workspace, is_within, resolve_entries};
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
 
 use serde_json::Value;
 mod argparser;
-use argparser::get_cli_map;
+use argparser::{get_cli_map, get_help};
 
 pub mod environment_reader;
 use environment_reader::get_config_map;
@@ -18,29 +54,994 @@ fn main() {
 
     let opts = merge_hashmaps(&config_map, &cli).unwrap();
 
-    if let Some(debug) = opts.get("debug") {
-        if debug.as_u64().unwrap_or(0) > 0 {
-            println!("Full opts Map: {:#?}", opts);
+    if let Some(debug) = opts.get("debug")
+        && debug.as_u64().unwrap_or(0) > 0
+    {
+        println!("Full opts Map: {:#?}", opts);
+    }
+
+    if let Err(message) = process_subcommand(&opts) {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    }
+}
+
+fn process_subcommand(opts: &Value) -> Result<(), String> {
+    if let Some(command) = opts.get("command")
+        && let Some(name) = command.get("name").and_then(Value::as_str)
+    {
+        match name {
+            "read" => read(command, opts)?,
+            "create" => create(command, opts)?,
+            "update" => update(command, opts)?,
+            "delete" => delete(command, opts)?,
+            "edit" => edit(command, opts)?,
+            "start" => transition(command, opts, ActionState::InProgress)?,
+            "complete" => complete(command, opts)?,
+            "block" => transition(command, opts, ActionState::BlockedorAwaiting)?,
+            "cancel" => transition(command, opts, ActionState::Cancelled)?,
+            "reopen" => transition(command, opts, ActionState::NotStarted)?,
+            "fmt" => fmt(command, opts)?,
+            "lint" => lint(command, opts)?,
+            "ids" => ids(command, opts)?,
+            "archive" => archive(command, opts)?,
+            "next" => next(command, opts)?,
+            "agenda" => agenda(command, opts)?,
+            "export" => export(command, opts)?,
+            "graph" => graph(command, opts)?,
+            _ => println!("Unknown command"),
+        }
+        return Ok(());
+    }
+    // the full screen view needs a terminal to read keys from and draw on
+    if !(std::io::stdin().is_terminal() && std::io::stdout().is_terminal()) {
+        return Err(format!(
+            "{}\nwithout a subcommand cliche opens a full screen view, which needs a terminal",
+            get_help()
+        ));
+    }
+    tui(opts)
+}
+
+fn read(command: &Value, opts: &Value) -> Result<(), String> {
+    let paths = workspace_paths(opts)?;
+    // one bad line should not keep the rest of the files from being read
+    let workspace = Workspace::load(&paths)?;
+    for file in &workspace.files {
+        for diagnostic in &file.diagnostics {
+            eprintln!("{}:{}", file.path.display(), diagnostic);
         }
     }
 
-    process_subcommand(&opts);
+    let filter = get_strings(command, "filter").join(" ");
+    let query = QueryOptions {
+        filter: (!filter.trim().is_empty()).then_some(filter),
+        sort: get_string(command, "sort"),
+        group_by: get_string(command, "group_by"),
+        limit: command
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map(|limit| limit as usize),
+    };
+
+    // a filter says for itself which states it wants to see
+    let show_closed = get_bool(command, "all") || query.filter.is_some();
+    let numbered = get_bool(command, "number") || get_bool(opts, "numbering");
+
+    let format = command
+        .get("format")
+        .and_then(Value::as_str)
+        .or_else(|| opts.get("format").and_then(Value::as_str))
+        .unwrap_or(match std::io::stdout().is_terminal() {
+            // the table is the one that shows the short ids, the file syntax only has full ones
+            true => "table",
+            false => "native",
+        });
+    // several files are shown one after the other under their names, except as JSON which
+    // carries the file of every action instead
+    let several = workspace.files.len() > 1;
+    if query == QueryOptions::default()
+        && !numbered
+        && (!several || matches!(format, "native" | "table"))
+    {
+        forget_session(&paths);
+        let mut output = String::new();
+        for (index, file) in workspace.files.iter().enumerate() {
+            let actions = if show_closed {
+                file.actions.clone()
+            } else {
+                open_actions(&file.actions)
+            };
+            if several {
+                let gap = if index == 0 { "" } else { "\n" };
+                output.push_str(&format!("{}// {}\n", gap, file.path.display()));
+            }
+            output.push_str(&render(&actions, format)?);
+        }
+        print!("{}", output);
+        return Ok(());
+    }
+
+    let actions = workspace.actions();
+    let entries: Vec<Value> = workspace
+        .flatten()
+        .into_iter()
+        .filter(|action| {
+            show_closed
+                || (action.common.state.is_open()
+                    && ancestors(&actions, &action.path)
+                        .all(|ancestor| ancestor.common.state.is_open()))
+        })
+        .map(serde_json::to_value)
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("unable to serialize actions: {}", e))?;
+    let mut groups = query_actions(&entries, &query, Local::now())?;
+    if numbered {
+        let shown = number_actions(&mut groups);
+        let session = Session::new(&workspace.fingerprint(), &actions, &shown);
+        save_session(&paths, &session)?;
+    } else {
+        forget_session(&paths);
+    }
+    print!("{}", render_groups(&groups, format)?);
+    Ok(())
+}
+
+// the numbers from the last numbered `read` of each workspace are kept in the temp dir, so they
+// last about as long as the terminal session that saw them
+fn session_path(paths: &[PathBuf]) -> PathBuf {
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    std::env::temp_dir().join(format!(
+        "cliche-{:016x}.session.json",
+        text_hash(&paths.join("\n"))
+    ))
+}
+
+fn save_session(paths: &[PathBuf], session: &Session) -> Result<(), String> {
+    let json = serde_json::to_string(session)
+        .map_err(|e| format!("unable to serialize the session: {}", e))?;
+    fs::write(session_path(paths), json)
+        .map_err(|e| format!("unable to save the shown numbers: {}", e))
+}
+
+// once a read shows no numbers, a bare number goes back to meaning a position in the file
+fn forget_session(paths: &[PathBuf]) {
+    let _ = fs::remove_file(session_path(paths));
 }
 
-fn process_subcommand(opts: &Value) {
-    if let Some(command) = opts.get("command") {
-        if let Some(name) = command.get("name").and_then(Value::as_str) {
-            match name {
-                "read" => {
-                    let all = command.get("all").and_then(Value::as_bool).unwrap_or(false);
-                    if all {
-                        println!("Reading all actions");
-                    } else {
-                        println!("Reading specific actions");
-                    }
-                }
-                _ => println!("Unknown command"),
+fn translate_selector(
+    selector: &str,
+    workspace: &Workspace,
+    paths: &[PathBuf],
+) -> Result<String, String> {
+    match fs::read_to_string(session_path(paths)) {
+        Ok(json) => serde_json::from_str::<Session>(&json)
+            .map_err(|e| format!("unable to read the shown numbers: {}", e))?
+            .translate(&workspace.fingerprint(), selector),
+        Err(_) => Ok(selector.to_string()),
+    }
+}
+
+// the file holding the selected action, its contents and a selector for the action within it
+// when `prefer_open` is set a selector matching several actions picks the one still open
+fn select_target(
+    selector: &str,
+    opts: &Value,
+    prefer_open: bool,
+) -> Result<(PathBuf, String, String), String> {
+    let paths = workspace_paths(opts)?;
+    let mut workspace = Workspace::load(&paths)?;
+    let selector = translate_selector(selector, &workspace, &paths)?;
+    // a single file keeps its selectors exactly as they were
+    let (index, selector) = if workspace.files.len() == 1 {
+        (0, selector)
+    } else if prefer_open {
+        workspace.select_open(&selector)?
+    } else {
+        workspace.select_one(&selector)?
+    };
+    let file = workspace.files.swap_remove(index);
+    Ok((file.path, file.source, selector))
+}
+
+fn create(command: &Value, opts: &Value) -> Result<(), String> {
+    let mut common = CommonActionProperties {
+        name: command
+            .get("action_name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        description: get_string(command, "description"),
+        priority: command
+            .get("priority")
+            .and_then(Value::as_u64)
+            .map(|priority| priority as usize),
+        context_list: get_contexts(command),
+        do_date_time: get_string(command, "do_date")
+            .map(|date| parse_action_date_time(&date))
+            .transpose()?,
+        recurrence: get_string(command, "every")
+            .map(|rule| rule.parse::<Recurrence>())
+            .transpose()?,
+        ..Default::default()
+    };
+    let parent = get_string(command, "parent")
+        .map(|id| parse_id(&id))
+        .transpose()?;
+
+    let mut workspace = Workspace::load(&workspace_paths(opts)?)?;
+    common.blocked_by = resolve_ids(&workspace, &get_strings(command, "blocked_by"))?;
+    let index = match (get_string(command, "file"), parent) {
+        (Some(file), _) => workspace
+            .file(Path::new(&file))
+            .ok_or_else(|| format!("{} is not part of the workspace", file))?,
+        (None, Some(parent)) => workspace.file_with_id(parent).unwrap_or(0),
+        (None, None) => 0,
+    };
+    let file = workspace.files.swap_remove(index);
+    let updated = create_action(&file.source, &common, parent)?;
+    if !common.blocked_by.is_empty() {
+        check_dependencies(opts, &file.path, &updated)?;
+    }
+    write_action_file(&file.path, &updated, opts)?;
+
+    println!("{}", common);
+    Ok(())
+}
+
+fn update(command: &Value, opts: &Value) -> Result<(), String> {
+    let selector = get_string(command, "selector").unwrap_or_default();
+    let mut action_update = ActionUpdate {
+        name: get_string(command, "action_name"),
+        state: get_string(command, "state")
+            .map(|state| state.parse::<ActionState>())
+            .transpose()?,
+        description: get_string(command, "description"),
+        priority: command
+            .get("priority")
+            .and_then(Value::as_u64)
+            .map(|priority| priority as usize),
+        add_contexts: get_strings(command, "add_contexts"),
+        remove_contexts: get_strings(command, "remove_contexts"),
+        recurrence: get_string(command, "every")
+            .map(|rule| rule.parse::<Recurrence>())
+            .transpose()?,
+        remove_recurrence: get_bool(command, "no_every"),
+        ..Default::default()
+    };
+
+    let blockers = [
+        get_strings(command, "add_blocked_by"),
+        get_strings(command, "remove_blocked_by"),
+    ];
+    if blockers.iter().any(|ids| !ids.is_empty()) {
+        let workspace = Workspace::load(&workspace_paths(opts)?)?;
+        action_update.add_blocked_by = resolve_ids(&workspace, &blockers[0])?;
+        action_update.remove_blocked_by = resolve_ids(&workspace, &blockers[1])?;
+    }
+
+    let (path, source, selector) = select_target(&selector, opts, false)?;
+    // a new state goes through the same steps as start, complete and reopen, so the completed
+    // date and the next occurrence of a repeat follow along; the position keeps naming the action
+    // once a next occurrence with its name sits after it or the update renames it
+    let selector = position_selector(&source, &selector)?;
+    let closes = action_update.state.is_some_and(|state| !state.is_open());
+    let transitioned = match action_update.state.take() {
+        Some(ActionState::Completed) => complete_action(
+            &source,
+            &selector,
+            get_policy(command, opts)?,
+            get_recur_from(command, opts)?,
+            Local::now(),
+            Uuid::now_v7,
+        )?,
+        Some(state) => transition_action(
+            &source,
+            &selector,
+            state,
+            get_policy(command, opts)?,
+            Local::now(),
+        )?,
+        None => source.clone(),
+    };
+    let updated = update_action(&transitioned, &selector, &action_update)?;
+    if !action_update.add_blocked_by.is_empty() {
+        check_dependencies(opts, &path, &updated)?;
+    }
+    match closes {
+        true => write_and_unblock(&path, &source, &updated, opts, false),
+        false => write_action_file(&path, &updated, opts),
+    }
+}
+
+// the position of the one action the selector matches, counting from 1 in file order
+fn position_selector(source: &str, selector: &str) -> Result<String, String> {
+    let actions = map_source(source)?.actions;
+    let path = select_one(&actions, selector)?;
+    let position = walk(&actions)
+        .iter()
+        .position(|(walked, _)| *walked == path)
+        .ok_or_else(|| format!("no action matches {}", selector))?;
+    Ok((position + 1).to_string())
+}
+
+// ids can be given whole or as the shortest prefix `read` shows, with or without their `#`
+fn resolve_ids(workspace: &Workspace, ids: &[String]) -> Result<Vec<Uuid>, String> {
+    let actions = workspace.actions();
+    ids.iter()
+        .map(|id| {
+            if let Ok(id) = parse_id(id) {
+                return Ok(id);
             }
+            let selector = format!("#{}", id.trim_start_matches('#'));
+            let path = select_one(&actions, &selector)?;
+            get_action(&actions, &path)
+                .and_then(|action| action.common.id)
+                .ok_or_else(|| format!("no action has the id {}", id))
+        })
+        .collect()
+}
+
+// what an action is blocked by can be in any file, so the change is checked against the whole
+// workspace as it would be afterwards, leaving whatever was already wrong to lint
+fn check_dependencies(opts: &Value, path: &Path, updated: &str) -> Result<(), String> {
+    let before = DependencyGraph::new(&Workspace::load(&workspace_paths(opts)?)?.actions());
+    DependencyGraph::new(&workspace_with(opts, path, updated)?.actions()).check_changes(&before)
+}
+
+// every file in the workspace as it is, apart from `path` which has its changes
+fn workspace_with(opts: &Value, path: &Path, updated: &str) -> Result<Workspace, String> {
+    let sources = workspace_paths(opts)?
+        .into_iter()
+        .map(|other| match other == path {
+            true => Ok((other, updated.to_string())),
+            false => read_action_file(&other).map(|source| (other, source)),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Workspace::from_sources(sources)
+}
+
+// finishing an action can free the ones in any file that were waiting on it, so they are written
+// or shown in the diff along with the change itself
+fn write_and_unblock(
+    path: &Path,
+    source: &str,
+    updated: &str,
+    opts: &Value,
+    dry_run: bool,
+) -> Result<(), String> {
+    let workspace = workspace_with(opts, path, updated)?;
+    let actions = workspace.actions();
+    let mut unblocked: Vec<Vec<ActionPath>> = vec![Vec::new(); workspace.files.len()];
+    for merged_path in to_unblock(&actions) {
+        if let Some((file, local_path)) = workspace.locate(&merged_path) {
+            unblocked[file].push(local_path);
+        }
+        if let Some(action) = get_action(&actions, &merged_path).filter(|_| !dry_run) {
+            println!("unblocked {}", action.common.name);
+        }
+    }
+    for (file, paths) in workspace.files.iter().zip(unblocked) {
+        let freed = match paths.is_empty() {
+            true => file.source.clone(),
+            false => unblock_actions(&file.source, &paths)?,
+        };
+        if file.path == path {
+            write_or_show_diff(path, source, &freed, opts, dry_run)?;
+        } else if !paths.is_empty() {
+            write_or_show_diff(&file.path, &file.source, &freed, opts, dry_run)?;
+        }
+    }
+    Ok(())
+}
+
+fn next(command: &Value, opts: &Value) -> Result<(), String> {
+    let workspace = Workspace::load(&workspace_paths(opts)?)?;
+    // the `context` setting can be a single context or a list of them
+    let contexts = match get_strings(command, "contexts") {
+        contexts if !contexts.is_empty() => contexts,
+        _ => match opts.get("context") {
+            Some(Value::String(context)) => vec![context.clone()],
+            _ => get_strings(opts, "context"),
+        },
+    };
+    let options = RankOptions {
+        contexts: contexts
+            .iter()
+            .map(|context| normalize_context(context))
+            .collect(),
+        now: Local::now(),
+    };
+    let mut ranked = rank_actions(&workspace.actions(), &options);
+    if let Some(limit) = command.get("limit").and_then(Value::as_u64) {
+        ranked.truncate(limit as usize);
+    }
+
+    match get_string(command, "format").as_deref() {
+        Some("json") => println!(
+            "{}",
+            serde_json::to_string_pretty(&ranked)
+                .map_err(|e| format!("unable to serialize the ranking: {}", e))?
+        ),
+        _ => print!("{}", render_ranking(&ranked)),
+    }
+    Ok(())
+}
+
+fn agenda(command: &Value, opts: &Value) -> Result<(), String> {
+    let workspace = Workspace::load(&workspace_paths(opts)?)?;
+    let days = match get_bool(command, "week") {
+        true => 7,
+        false => command.get("days").and_then(Value::as_u64).unwrap_or(7),
+    };
+    let now = Local::now();
+    let agenda = build_agenda(&workspace.actions(), days, now);
+
+    match get_string(command, "format").as_deref() {
+        Some("json") => println!(
+            "{}",
+            serde_json::to_string_pretty(&agenda)
+                .map_err(|e| format!("unable to serialize the agenda: {}", e))?
+        ),
+        _ => {
+            // colors are only for a terminal, and never when NO_COLOR is set
+            let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+            print!("{}", render_agenda(&agenda, now, color));
+        }
+    }
+    Ok(())
+}
+
+// saves what the full screen view changes straight to the action file
+struct FileStore<'a> {
+    path: PathBuf,
+    opts: &'a Value,
+}
+
+impl Store for FileStore<'_> {
+    fn save(&mut self, source: &str) -> Result<String, String> {
+        let contents = with_assigned_ids(source, self.opts)?;
+        fs::write(&self.path, &contents)
+            .map_err(|e| format!("unable to write {}: {}", self.path.display(), e))?;
+        Ok(contents)
+    }
+}
+
+fn tui(opts: &Value) -> Result<(), String> {
+    let path = get_action_path(opts)?;
+    // a file that is not there yet just starts out empty
+    let source = match fs::read_to_string(&path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        read => read.map_err(|e| format!("unable to read {}: {}", path.display(), e))?,
+    };
+    let mut app = App::new(
+        &source,
+        FileStore {
+            path: path.clone(),
+            opts,
+        },
+    )
+    .map_err(|e| format!("{}:{}", path.display(), e))?;
+    app.title = path.display().to_string();
+    app.policy = get_policy(&Value::Null, opts)?;
+    app.recur_from = get_string(opts, "recur_from")
+        .map(|from| from.parse::<RecurFrom>())
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut terminal = ratatui::try_init().map_err(|e| {
+        ratatui::restore();
+        format!("unable to open the full screen view: {}", e)
+    })?;
+    let result = (|| {
+        while !app.quit {
+            terminal
+                .draw(|frame| app.draw(frame))
+                .map_err(|e| format!("unable to draw: {}", e))?;
+            if let Event::Key(key) =
+                event::read().map_err(|e| format!("unable to read a key: {}", e))?
+                && key.kind == KeyEventKind::Press
+            {
+                app.handle_key(key, Local::now());
+            }
+        }
+        Ok(())
+    })();
+    ratatui::restore();
+    result
+}
+
+fn export(command: &Value, opts: &Value) -> Result<(), String> {
+    let workspace = Workspace::load(&workspace_paths(opts)?)?;
+    let actions = workspace.actions();
+    let format = get_string(command, "format").unwrap_or_else(|| "ics".to_string());
+    let exported = export_actions(&actions, &format, Local::now())?;
+
+    // without an id the exported todo changes whenever the action is moved or renamed
+    let missing = walk(&actions)
+        .iter()
+        .filter(|(_, action)| action.common.id.is_none())
+        .count();
+    if missing > 0 {
+        let actions = match missing {
+            1 => "1 action has".to_string(),
+            _ => format!("{} actions have", missing),
+        };
+        eprintln!(
+            "{} no id, so calendars will see them as new whenever they move or are renamed, \
+             `cliche ids assign` gives them one",
+            actions
+        );
+    }
+
+    // the rule's time of day would be local against a DTSTART in UTC, so calendars repeat at the
+    // do-date's time instead
+    let moved = walk(&actions)
+        .iter()
+        .filter(|(_, action)| moves_repeat_time(action))
+        .count();
+    match moved {
+        0 => {}
+        1 => eprintln!(
+            "1 action repeats at a time of day other than its do-date's, calendars will repeat it \
+             at the do-date's time instead"
+        ),
+        _ => eprintln!(
+            "{} actions repeat at a time of day other than their do-date's, calendars will repeat \
+             them at the do-date's time instead",
+            moved
+        ),
+    }
+
+    match get_string(command, "output") {
+        Some(output) => {
+            fs::write(&output, exported).map_err(|e| format!("unable to write {}: {}", output, e))
+        }
+        None => {
+            print!("{}", exported);
+            Ok(())
         }
     }
 }
+
+fn graph(command: &Value, opts: &Value) -> Result<(), String> {
+    let workspace = Workspace::load(&workspace_paths(opts)?)?;
+    let format = get_string(command, "format").unwrap_or_else(|| "dot".to_string());
+    print!("{}", render_graph(&workspace.actions(), &format)?);
+    Ok(())
+}
+
+fn delete(command: &Value, opts: &Value) -> Result<(), String> {
+    let selector = get_string(command, "selector").unwrap_or_default();
+    let children = if get_bool(command, "subtree") {
+        ChildHandling::Subtree
+    } else if get_bool(command, "promote") {
+        ChildHandling::Promote
+    } else {
+        ChildHandling::Refuse
+    };
+
+    let (path, source, selector) = select_target(&selector, opts, false)?;
+    let updated = delete_action(&source, &selector, children)?;
+    write_or_show_diff(&path, &source, &updated, opts, get_bool(command, "dry_run"))
+}
+
+fn edit(command: &Value, opts: &Value) -> Result<(), String> {
+    let selector = get_string(command, "selector").unwrap_or_default();
+    let (path, source, selector) = select_target(&selector, opts, false)?;
+    let subtree = extract_subtree(&source, &selector)?;
+
+    // the extension lets editors pick up the highlighting for action files
+    let temp_path =
+        std::env::temp_dir().join(format!("cliche-edit-{}.actions", Uuid::now_v7().simple()));
+    // made fresh here, so a file or link someone else left at the path is never written through
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .map_err(|e| format!("unable to create {}: {}", temp_path.display(), e))?;
+    let mut text = subtree.text.clone();
+    let updated = loop {
+        fs::write(&temp_path, &text)
+            .map_err(|e| format!("unable to write {}: {}", temp_path.display(), e))?;
+        run_editor(&temp_path)?;
+        let edited = strip_comments(&read_action_file(&temp_path)?);
+        if edited.trim().is_empty() || edited == subtree.text {
+            let _ = fs::remove_file(&temp_path);
+            println!("nothing changed");
+            return Ok(());
+        }
+        let spliced = splice_subtree(&source, &subtree, &edited)
+            .and_then(|updated| check_dependencies(opts, &path, &updated).map(|_| updated));
+        match spliced {
+            Ok(updated) => break updated,
+            // saving the same rejected text again means there is nothing more to try
+            Err(problems) if strip_comments(&text) == edited => {
+                return Err(format!(
+                    "{}\nnothing was changed, the edit is left in {}",
+                    problems,
+                    temp_path.display()
+                ));
+            }
+            Err(problems) => text = with_problems(&edited, &problems),
+        }
+    };
+
+    // whatever happened to the file while the editor was open is not ours to overwrite
+    if read_action_file(&path)? != source {
+        return Err(format!(
+            "{} changed while it was being edited, the edit is left in {}",
+            path.display(),
+            temp_path.display()
+        ));
+    }
+    // closing an action in the editor frees what waited on it, the same as completing it would
+    write_and_unblock(&path, &source, &updated, opts, false)?;
+    let _ = fs::remove_file(&temp_path);
+    Ok(())
+}
+
+// $VISUAL, then $EDITOR, then vi, run through the shell so either can carry arguments of its own
+fn run_editor(path: &Path) -> Result<(), String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()
+        .map_err(|e| format!("unable to start {}: {}", editor, e))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!(
+            "{} exited with {}, nothing was changed",
+            editor, status
+        )),
+    }
+}
+
+fn transition(command: &Value, opts: &Value, state: ActionState) -> Result<(), String> {
+    let selector = get_string(command, "selector").unwrap_or_default();
+    let policy = get_policy(command, opts)?;
+
+    let prefer_open = state != ActionState::NotStarted;
+    let (path, source, selector) = select_target(&selector, opts, prefer_open)?;
+    let updated = transition_action(&source, &selector, state, policy, Local::now())?;
+    let dry_run = get_bool(command, "dry_run");
+    match state.is_open() {
+        true => write_or_show_diff(&path, &source, &updated, opts, dry_run),
+        false => write_and_unblock(&path, &source, &updated, opts, dry_run),
+    }
+}
+
+fn complete(command: &Value, opts: &Value) -> Result<(), String> {
+    let selector = get_string(command, "selector").unwrap_or_default();
+    let policy = get_policy(command, opts)?;
+    let from = get_recur_from(command, opts)?;
+
+    let (path, source, selector) = select_target(&selector, opts, true)?;
+    let updated = complete_action(&source, &selector, policy, from, Local::now(), Uuid::now_v7)?;
+    let dry_run = get_bool(command, "dry_run");
+    write_and_unblock(&path, &source, &updated, opts, dry_run)
+}
+
+fn get_recur_from(command: &Value, opts: &Value) -> Result<RecurFrom, String> {
+    Ok(get_string(command, "from")
+        .or_else(|| get_string(opts, "recur_from"))
+        .map(|from| from.parse::<RecurFrom>())
+        .transpose()?
+        .unwrap_or_default())
+}
+
+fn get_policy(command: &Value, opts: &Value) -> Result<TransitionPolicy, String> {
+    Ok(get_string(command, "policy")
+        .or_else(|| get_string(opts, "transition_policy"))
+        .map(|policy| policy.parse::<TransitionPolicy>())
+        .transpose()?
+        .unwrap_or_default())
+}
+
+fn fmt(command: &Value, opts: &Value) -> Result<(), String> {
+    let check = get_bool(command, "check");
+    let file = get_string(command, "file");
+
+    // editors hand the buffer over on stdin and take the result back from stdout
+    if file.as_deref() == Some("-") {
+        let mut source = String::new();
+        std::io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|e| format!("unable to read stdin: {}", e))?;
+        let formatted = format_source(&source)?;
+        if check {
+            return check_formatted("<stdin>", &source, &formatted);
+        }
+        print!("{}", formatted);
+        return Ok(());
+    }
+
+    let paths = match file {
+        Some(file) => vec![PathBuf::from(file)],
+        None => workspace_paths(opts)?,
+    };
+    // every file gets its turn even when an earlier one fails
+    let failures: Vec<String> = paths
+        .iter()
+        .filter_map(|path| fmt_file(path, check).err())
+        .collect();
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(failures.join("\n")),
+    }
+}
+
+fn fmt_file(path: &Path, check: bool) -> Result<(), String> {
+    let source = read_action_file(path)?;
+    let formatted = format_source(&source).map_err(|e| format!("{}:{}", path.display(), e))?;
+    if check {
+        return check_formatted(&path.display().to_string(), &source, &formatted);
+    }
+    if formatted != source {
+        fs::write(path, formatted)
+            .map_err(|e| format!("unable to write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+fn check_formatted(name: &str, source: &str, formatted: &str) -> Result<(), String> {
+    if source == formatted {
+        return Ok(());
+    }
+    print!("{}", unified_diff(source, formatted, name, name));
+    Err(format!("{} is not formatted", name))
+}
+
+fn lint(command: &Value, opts: &Value) -> Result<(), String> {
+    let config = LintConfig::from_opts(opts)?;
+    let paths = match get_string(command, "file") {
+        Some(file) => vec![PathBuf::from(file)],
+        None => workspace_paths(opts)?,
+    };
+    // actions can be blocked by ones in any workspace file, even when only one file is linted
+    let mut ids = HashSet::new();
+    for path in workspace_paths(opts).unwrap_or_default() {
+        if let Ok(source) = read_action_file(&path) {
+            ids.extend(action_ids(&source).unwrap_or_default());
+        }
+    }
+    let files = paths
+        .iter()
+        .map(|path| {
+            let source = read_action_file(path)?;
+            let findings = lint_in_workspace(&source, &config, Local::now(), &ids)?;
+            Ok((path.display().to_string(), source, findings))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let format = get_string(command, "format").unwrap_or_else(|| "text".to_string());
+    match files.as_slice() {
+        [(path, source, findings)] => {
+            print!("{}", render_findings(findings, path, source, &format)?)
+        }
+        _ => print!("{}", render_file_findings(&files, &format)?),
+    }
+
+    let errors = files
+        .iter()
+        .flat_map(|(_, _, findings)| findings)
+        .filter(|finding| finding.diagnostic.severity == Severity::Error)
+        .count();
+    let name = match files.as_slice() {
+        [(path, _, _)] => path.clone(),
+        _ => "the workspace".to_string(),
+    };
+    match errors {
+        0 => Ok(()),
+        1 => Err(format!("{} has 1 error", name)),
+        _ => Err(format!("{} has {} errors", name, errors)),
+    }
+}
+
+fn ids(command: &Value, opts: &Value) -> Result<(), String> {
+    let subcommand = command.get("command").cloned().unwrap_or_default();
+    match subcommand.get("name").and_then(Value::as_str) {
+        Some("assign") => {
+            let dry_run = get_bool(&subcommand, "dry_run");
+            let mut total = 0;
+            for path in workspace_paths(opts)? {
+                let source = read_action_file(&path)?;
+                let (updated, count) = assign_ids(&source, Uuid::now_v7)?;
+                write_or_show_diff(&path, &source, &updated, opts, dry_run)?;
+                total += count;
+            }
+            if !dry_run {
+                println!("assigned {} ids", total);
+            }
+            Ok(())
+        }
+        _ => Err("unknown ids command".to_string()),
+    }
+}
+
+fn archive(command: &Value, opts: &Value) -> Result<(), String> {
+    let older_than = get_string(command, "older_than")
+        .map(|age| parse_duration(&age))
+        .transpose()?;
+    let dry_run = get_bool(command, "dry_run");
+    let archive_dir = archive_dir(opts)?;
+
+    // an archive file is never archived into itself
+    let mut archives = Vec::new();
+    for path in workspace_paths(opts)? {
+        if is_within(&path, &archive_dir) {
+            continue;
+        }
+        let source = read_action_file(&path)?;
+        let archive = archive_actions(&source, older_than, Local::now())
+            .map_err(|e| format!("{}:{}", path.display(), e))?;
+        archives.push((path, source, archive));
+    }
+    let archived: Vec<&ArchivedAction> = archives
+        .iter()
+        .flat_map(|(_, _, archive)| &archive.archived)
+        .collect();
+
+    let mut months: Vec<&str> = archived
+        .iter()
+        .map(|action| action.month.as_str())
+        .collect();
+    months.sort();
+    months.dedup();
+
+    // the archive is written before anything leaves the active files, so a failure loses nothing
+    for month in months {
+        let archive_path = archive_dir.join(format!("{}.actions", month));
+        let name = archive_path.display().to_string();
+        let existing = match fs::read_to_string(&archive_path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            read => read.map_err(|e| format!("unable to read {}: {}", name, e))?,
+        };
+        let moved: Vec<&ArchivedAction> = archived
+            .iter()
+            .filter(|action| action.month == month)
+            .copied()
+            .collect();
+        let contents = append_to_archive(&existing, &moved);
+        if dry_run {
+            print!("{}", unified_diff(&existing, &contents, &name, &name));
+            continue;
+        }
+        fs::create_dir_all(&archive_dir)
+            .map_err(|e| format!("unable to create {}: {}", archive_dir.display(), e))?;
+        fs::write(&archive_path, contents)
+            .map_err(|e| format!("unable to write {}: {}", name, e))?;
+        println!("archived {} actions to {}", moved.len(), name);
+    }
+
+    // what is left should not go on waiting for actions that are now only in the archive
+    let ids: Vec<Uuid> = archived
+        .iter()
+        .flat_map(|action| action.ids.iter().copied())
+        .collect();
+    for (path, source, archive) in &archives {
+        let remaining = drop_blockers(&archive.remaining, &ids)
+            .map_err(|e| format!("{}:{}", path.display(), e))?;
+        if remaining != *source {
+            write_or_show_diff(path, source, &remaining, opts, dry_run)?;
+        }
+    }
+    Ok(())
+}
+
+// archives live next to the action file, which is in the data dir unless configured otherwise
+fn archive_dir(opts: &Value) -> Result<PathBuf, String> {
+    let action_path = get_action_path(opts)?;
+    Ok(action_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join("archive"))
+}
+
+// a dry run prints what would change rather than changing it
+fn write_or_show_diff(
+    path: &Path,
+    source: &str,
+    updated: &str,
+    opts: &Value,
+    dry_run: bool,
+) -> Result<(), String> {
+    if dry_run {
+        let updated = with_assigned_ids(updated, opts)?;
+        let name = path.display().to_string();
+        print!("{}", unified_diff(source, &updated, &name, &name));
+        return Ok(());
+    }
+    write_action_file(path, updated, opts)
+}
+
+// with the `assign_ids` setting on, nothing is written without every action having an id
+fn with_assigned_ids(contents: &str, opts: &Value) -> Result<String, String> {
+    if !get_bool(opts, "assign_ids") {
+        return Ok(contents.to_string());
+    }
+    assign_ids(contents, Uuid::now_v7).map(|(updated, _)| updated)
+}
+
+// settings from the environment arrive as strings, so `CLICHE_ASSIGN_IDS=true` counts as well
+fn get_bool(command: &Value, key: &str) -> bool {
+    match command.get(key) {
+        Some(Value::Bool(value)) => *value,
+        Some(Value::String(value)) => value.eq_ignore_ascii_case("true") || value.trim() == "1",
+        _ => false,
+    }
+}
+
+fn get_strings(command: &Value, key: &str) -> Vec<String> {
+    command
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect()
+}
+
+fn get_string(command: &Value, key: &str) -> Option<String> {
+    command.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+// contexts can be given with or without their `@`
+fn get_contexts(command: &Value) -> Option<Vec<String>> {
+    let contexts: Vec<String> = get_strings(command, "contexts")
+        .iter()
+        .map(|context| normalize_context(context))
+        .collect();
+    (!contexts.is_empty()).then_some(contexts)
+}
+
+fn parse_id(id: &str) -> Result<Uuid, String> {
+    Uuid::parse_str(id.trim_start_matches('#')).map_err(|e| format!("invalid id {}: {}", id, e))
+}
+
+fn get_action_path(opts: &Value) -> Result<PathBuf, String> {
+    opts.get("action_path")
+        .and_then(Value::as_str)
+        .map(PathBuf::from)
+        .ok_or_else(|| "no action_path configured".to_string())
+}
+
+// the `workspace` setting takes a list of files, directories and glob patterns, or just one of
+// them, and without it the action file is the whole workspace
+fn workspace_paths(opts: &Value) -> Result<Vec<PathBuf>, String> {
+    let entries: Vec<String> = match opts.get("workspace") {
+        Some(Value::String(entry)) => vec![entry.clone()],
+        Some(Value::Array(entries)) => entries
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => return Ok(vec![get_action_path(opts)?]),
+    };
+    let archives: Vec<PathBuf> = archive_dir(opts).into_iter().collect();
+    let paths = resolve_entries(&entries, &archives)?;
+    if paths.is_empty() {
+        return Err("the workspace setting names no action files".to_string());
+    }
+    Ok(paths)
+}
+
+fn read_action_file(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("unable to read {}: {}", path.display(), e))
+}
+
+fn write_action_file(path: &Path, contents: &str, opts: &Value) -> Result<(), String> {
+    let contents = with_assigned_ids(contents, opts)?;
+    fs::write(path, contents).map_err(|e| format!("unable to write {}: {}", path.display(), e))
+}
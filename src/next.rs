@@ -0,0 +1,258 @@
+//! Picking what to work on next
+//!
+//! Every open action with nothing open left underneath it gets a score made of a few factors, each
+//! kept along with the reason for it so the ranking can explain itself. Higher scores go first and
+//! ties stay in file order.
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::entities::{Action, ActionPath, ActionState, ancestors, walk};
+use crate::ids::shortest_prefixes;
+
+/// Priority 1 is worth this much, each step down the scale is worth 10 less
+const TOP_PRIORITY_POINTS: i64 = 40;
+const OVERDUE_POINTS: i64 = 50;
+const DUE_TODAY_POINTS: i64 = 40;
+/// How many days ahead a do-date starts to count, a day closer is worth 4 more
+const DUE_SOON_DAYS: i64 = 7;
+const IN_PROGRESS_POINTS: i64 = 20;
+/// Work left sitting in progress gains 2 a day for up to this many days
+const IN_PROGRESS_MAX_DAYS: i64 = 10;
+const CONTEXT_POINTS: i64 = 25;
+const BLOCKED_POINTS: i64 = -100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FactorKind {
+    Priority,
+    DoDate,
+    InProgress,
+    Context,
+    Blocked,
+}
+
+/// One part of a score and why the action got it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Factor {
+    pub kind: FactorKind,
+    pub points: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RankedAction {
+    pub path: ActionPath,
+    pub name: String,
+    pub short_id: Option<String>,
+    pub score: i64,
+    /// Only the factors that added or took away something
+    pub factors: Vec<Factor>,
+}
+
+/// What the ranking takes into account besides the actions themselves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankOptions {
+    /// The contexts you are in right now, with their `@`, empty when that does not matter
+    pub contexts: Vec<String>,
+    pub now: DateTime<Local>,
+}
+
+// how many whole days there are from today to the day of `date`, negative when it has passed
+fn days_until(date: &DateTime<Local>, now: &DateTime<Local>) -> i64 {
+    (date.date_naive() - now.date_naive()).num_days()
+}
+
+fn days_text(days: i64) -> String {
+    match days {
+        1 => "1 day".to_string(),
+        _ => format!("{} days", days),
+    }
+}
+
+// when the id was handed out, which is as close as the file gets to saying when work began
+fn id_time(id: &Uuid) -> Option<DateTime<Local>> {
+    let (seconds, nanos) = id.get_timestamp()?.to_unix();
+    Local.timestamp_opt(seconds as i64, nanos).single()
+}
+
+fn priority_factor(action: &Action) -> Option<Factor> {
+    let priority = action.common.priority?;
+    let points = (TOP_PRIORITY_POINTS - 10 * (priority as i64 - 1)).max(0);
+    (points > 0).then(|| Factor {
+        kind: FactorKind::Priority,
+        points,
+        reason: format!("priority {}", priority),
+    })
+}
+
+fn do_date_factor(action: &Action, now: &DateTime<Local>) -> Option<Factor> {
+    let do_date = action.common.do_date_time.as_ref()?;
+    let days = days_until(do_date, now);
+    let (points, reason) = match days {
+        ..0 => (OVERDUE_POINTS, format!("overdue by {}", days_text(-days))),
+        0 => (DUE_TODAY_POINTS, "due today".to_string()),
+        1..DUE_SOON_DAYS => (
+            4 * (DUE_SOON_DAYS - days),
+            format!("due in {}", days_text(days)),
+        ),
+        _ => return None,
+    };
+    Some(Factor {
+        kind: FactorKind::DoDate,
+        points,
+        reason,
+    })
+}
+
+// the file does not say when an action was started, so an id's age stands in as the longest it
+// can have been going
+fn in_progress_factor(action: &Action, now: &DateTime<Local>) -> Option<Factor> {
+    if action.common.state != ActionState::InProgress {
+        return None;
+    }
+    let days = action
+        .common
+        .id
+        .as_ref()
+        .and_then(id_time)
+        .map(|created| (*now - created).num_days().max(0))
+        .unwrap_or(0);
+    let reason = match days {
+        0 => "in progress".to_string(),
+        _ => format!("in progress, created {} ago", days_text(days)),
+    };
+    Some(Factor {
+        kind: FactorKind::InProgress,
+        points: IN_PROGRESS_POINTS + 2 * days.min(IN_PROGRESS_MAX_DAYS),
+        reason,
+    })
+}
+
+// an action without contexts can be done anywhere, so only the ones naming somewhere else lose out
+fn context_factor(action: &Action, contexts: &[String]) -> Option<Factor> {
+    let own = action.common.context_list.as_deref().unwrap_or_default();
+    if contexts.is_empty() || own.is_empty() {
+        return None;
+    }
+    match own.iter().find(|context| contexts.contains(context)) {
+        Some(context) => Some(Factor {
+            kind: FactorKind::Context,
+            points: CONTEXT_POINTS,
+            reason: format!("matches {}", context),
+        }),
+        None => Some(Factor {
+            kind: FactorKind::Context,
+            points: -CONTEXT_POINTS,
+            reason: format!("needs {}", own.join(",")),
+        }),
+    }
+}
+
+// marked blocked, or waiting on something it or one of its parents is blocked by
+fn blocked_factor(
+    action_list: &[Action],
+    path: &[usize],
+    action: &Action,
+    by_id: &HashMap<Uuid, &Action>,
+) -> Option<Factor> {
+    if action.common.state == ActionState::BlockedorAwaiting {
+        return Some(Factor {
+            kind: FactorKind::Blocked,
+            points: BLOCKED_POINTS,
+            reason: "marked blocked".to_string(),
+        });
+    }
+    let waiting_on: Vec<String> = std::iter::once(action)
+        .chain(ancestors(action_list, path))
+        .flat_map(|action| &action.common.blocked_by)
+        .filter_map(|id| match by_id.get(id) {
+            Some(blocker) if !blocker.common.state.is_open() => None,
+            Some(blocker) => Some(blocker.common.name.clone()),
+            None => Some(format!("unknown #{}", id.simple())),
+        })
+        .collect();
+    (!waiting_on.is_empty()).then(|| Factor {
+        kind: FactorKind::Blocked,
+        points: BLOCKED_POINTS,
+        reason: format!("waiting on {}", waiting_on.join(", ")),
+    })
+}
+
+/// The open actions with nothing open underneath them, best first
+///
+/// Blocked actions are still ranked so they can be listed, they just sink below everything that
+/// can be worked on.
+pub fn rank_actions(action_list: &[Action], options: &RankOptions) -> Vec<RankedAction> {
+    let walked = walk(action_list);
+    let by_id: HashMap<Uuid, &Action> = walked
+        .iter()
+        .filter_map(|(_, action)| action.common.id.map(|id| (id, *action)))
+        .collect();
+    let prefixes = shortest_prefixes(action_list);
+
+    let mut ranked: Vec<RankedAction> = walked
+        .iter()
+        .filter(|(path, action)| {
+            action.common.state.is_open()
+                && action
+                    .children
+                    .iter()
+                    .all(|child| !child.common.state.is_open())
+                && ancestors(action_list, path).all(|ancestor| ancestor.common.state.is_open())
+        })
+        .map(|(path, action)| {
+            let factors: Vec<Factor> = [
+                priority_factor(action),
+                do_date_factor(action, &options.now),
+                in_progress_factor(action, &options.now),
+                context_factor(action, &options.contexts),
+                blocked_factor(action_list, path, action, &by_id),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            RankedAction {
+                path: path.clone(),
+                name: action.common.name.clone(),
+                short_id: action.common.id.and_then(|id| prefixes.get(&id).cloned()),
+                score: factors.iter().map(|factor| factor.points).sum(),
+                factors,
+            }
+        })
+        .collect();
+    // sorting is stable, so equal scores keep their order in the file
+    ranked.sort_by_key(|action| std::cmp::Reverse(action.score));
+    ranked
+}
+
+/// A numbered line per action with its score, and the reasons for it underneath
+pub fn render_ranking(ranked: &[RankedAction]) -> String {
+    let mut output = String::new();
+    for (index, action) in ranked.iter().enumerate() {
+        let id = action
+            .short_id
+            .as_ref()
+            .map(|id| format!(" #{}", id))
+            .unwrap_or_default();
+        output.push_str(&format!(
+            "{}. {}{} ({})\n",
+            index + 1,
+            action.name,
+            id,
+            action.score
+        ));
+        if action.factors.is_empty() {
+            output.push_str("   nothing stands out\n");
+            continue;
+        }
+        let reasons: Vec<String> = action
+            .factors
+            .iter()
+            .map(|factor| format!("{} {:+}", factor.reason, factor.points))
+            .collect();
+        output.push_str(&format!("   {}\n", reasons.join(", ")));
+    }
+    output
+}
@@ -0,0 +1,214 @@
+//! Turning an action list into text for the terminal, each format is a pure function of the list
+use crate::entities::{
+    ActionList, CommonActionProperties, FlatAction, action_line, flatten, to_action_file, walk,
+};
+use crate::ids::shortest_prefixes;
+use crate::query::Group;
+
+pub const OUTPUT_FORMATS: [&str; 4] = ["native", "json", "ndjson", "table"];
+
+pub fn render(action_list: &ActionList, format: &str) -> Result<String, String> {
+    match format {
+        "native" => Ok(render_native(action_list)),
+        "json" => render_json(action_list),
+        "ndjson" => render_ndjson(action_list),
+        "table" => Ok(render_table(action_list)),
+        _ => Err(format!(
+            "unknown output format {}, expected one of {}",
+            format,
+            OUTPUT_FORMATS.join(", ")
+        )),
+    }
+}
+
+/// The same syntax as the action file itself, one action per line
+pub fn render_native(action_list: &ActionList) -> String {
+    to_action_file(action_list)
+}
+
+/// The whole tree as a single pretty printed document
+pub fn render_json(action_list: &ActionList) -> Result<String, String> {
+    serde_json::to_string_pretty(action_list)
+        .map(|json| format!("{}\n", json))
+        .map_err(|e| format!("unable to serialize actions: {}", e))
+}
+
+/// Every action on its own line, flattened out of the tree so that line based tools can work with
+/// them, the path and depth say where each one came from
+pub fn render_ndjson(action_list: &ActionList) -> Result<String, String> {
+    flatten(action_list)
+        .iter()
+        .map(|action| {
+            serde_json::to_string(action)
+                .map(|json| format!("{}\n", json))
+                .map_err(|e| format!("unable to serialize actions: {}", e))
+        })
+        .collect()
+}
+
+const TABLE_HEADERS: [&str; 6] = ["STATE", "PRIORITY", "NAME", "CONTEXTS", "DO", "ID"];
+
+/// Columns padded to line up, with names indented to show the hierarchy and ids cut down to their
+/// shortest unique prefix, written with the `#` a selector takes them with
+pub fn render_table(action_list: &ActionList) -> String {
+    let prefixes = shortest_prefixes(action_list);
+    let rows: Vec<Vec<String>> = walk(action_list)
+        .into_iter()
+        .map(|(_, action)| {
+            let short_id = action.common.id.and_then(|id| prefixes.get(&id));
+            table_row(&action.common, action.depth, short_id)
+        })
+        .collect();
+
+    render_columns(&TABLE_HEADERS.map(str::to_string), &rows)
+}
+
+fn table_row(
+    common: &CommonActionProperties,
+    indent: usize,
+    short_id: Option<&String>,
+) -> Vec<String> {
+    vec![
+        format!("({})", common.state),
+        common
+            .priority
+            .map(|priority| priority.to_string())
+            .unwrap_or_default(),
+        format!("{}{}", "  ".repeat(indent), common.name),
+        common.context_list.clone().unwrap_or_default().join(","),
+        common
+            .do_date_time
+            .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default(),
+        short_id.map(|id| format!("#{}", id)).unwrap_or_default(),
+    ]
+}
+
+/// Query results under a heading per group when they are grouped, and with the number from
+/// `query::number_actions` in front of each one when they are numbered
+///
+/// Results still in file order keep the indentation of the tree, sorted ones are shown flat since
+/// their depth no longer says anything about their neighbours
+pub fn render_groups(groups: &[Group], format: &str) -> Result<String, String> {
+    let grouped = groups.iter().any(|group| group.key.is_some());
+    let numbered = groups
+        .iter()
+        .flat_map(|group| &group.actions)
+        .any(|action| action.get("number").is_some());
+    match format {
+        "json" => {
+            let json = if grouped {
+                serde_json::to_string_pretty(groups)
+            } else {
+                serde_json::to_string_pretty(
+                    &groups
+                        .iter()
+                        .flat_map(|group| &group.actions)
+                        .collect::<Vec<_>>(),
+                )
+            };
+            json.map(|json| format!("{}\n", json))
+                .map_err(|e| format!("unable to serialize actions: {}", e))
+        }
+        "ndjson" => groups
+            .iter()
+            .flat_map(|group| {
+                group.actions.iter().map(|action| {
+                    let mut action = action.clone();
+                    if let (Some(key), Some(fields)) = (&group.key, action.as_object_mut()) {
+                        fields.insert("group".to_string(), key.clone().into());
+                    }
+                    serde_json::to_string(&action)
+                        .map(|json| format!("{}\n", json))
+                        .map_err(|e| format!("unable to serialize actions: {}", e))
+                })
+            })
+            .collect(),
+        "native" | "table" => {
+            let mut text = String::new();
+            for (index, group) in groups.iter().enumerate() {
+                let actions: Vec<FlatAction> = group
+                    .actions
+                    .iter()
+                    .map(|action| serde_json::from_value(action.clone()))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| format!("unable to read query results: {}", e))?;
+                let numbers: Vec<String> = group
+                    .actions
+                    .iter()
+                    .map(|action| match action.get("number") {
+                        Some(number) => format!("[{}]", number),
+                        None => String::new(),
+                    })
+                    .collect();
+                let in_file_order =
+                    !grouped && actions.windows(2).all(|pair| pair[0].path < pair[1].path);
+                let depth = |action: &FlatAction| if in_file_order { action.depth } else { 0 };
+
+                if let Some(key) = &group.key {
+                    if index > 0 {
+                        text.push('\n');
+                    }
+                    text.push_str(&format!("{}\n", key));
+                }
+                if format == "native" {
+                    for (action, number) in actions.iter().zip(&numbers) {
+                        if numbered {
+                            text.push_str(&format!("{:5} ", number));
+                        }
+                        text.push_str(&action_line(&action.common, depth(action)));
+                    }
+                } else {
+                    let rows: Vec<Vec<String>> = actions
+                        .iter()
+                        .zip(&numbers)
+                        .map(|(action, number)| {
+                            let row =
+                                table_row(&action.common, depth(action), action.short_id.as_ref());
+                            match numbered {
+                                true => std::iter::once(number.clone()).chain(row).collect(),
+                                false => row,
+                            }
+                        })
+                        .collect();
+                    let headers: Vec<String> = std::iter::once("")
+                        .filter(|_| numbered)
+                        .chain(TABLE_HEADERS)
+                        .map(str::to_string)
+                        .collect();
+                    text.push_str(&render_columns(&headers, &rows));
+                }
+            }
+            Ok(text)
+        }
+        _ => Err(format!(
+            "unknown output format {}, expected one of {}",
+            format,
+            OUTPUT_FORMATS.join(", ")
+        )),
+    }
+}
+
+pub(crate) fn render_columns(headers: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    std::iter::once(headers)
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = *width))
+                .collect();
+            format!("{}\n", cells.join("  ").trim_end())
+        })
+        .collect()
+}
@@ -0,0 +1,782 @@
+//! Filtering, sorting and grouping actions with a small query language
+//!
+//! Queries run against the JSON form of an action rather than the structs, so anything holding a
+//! serialized action (a `FlatAction`, or an `Action` with its `common` properties) can be queried
+//! the same way:
+//!
+//! `state != completed and priority <= 2 and context in (@work,@phone) and do < today+3d`
+//!
+//! Fields are `state`, `name`, `description`, `priority`, `context`, `do`, `completed`, `id`,
+//! `depth` and `parent`. Comparisons are `=`, `!=`, `<`, `<=`, `>`, `>=` and `~` for "contains",
+//! combined with `and`, `or`, `not` and parentheses. Dates can be absolute (`2026-10-20`) or
+//! relative to `now`, `today`, `tomorrow` or `yesterday` with an offset in minutes, hours, days or
+//! weeks (`today+3d`, `now-2h`).
+use chrono::{DateTime, Duration, Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+use crate::entities::{ActionPath, ActionState, parse_action_date_time};
+use crate::selector::Comparison;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    State,
+    Name,
+    Description,
+    Priority,
+    Context,
+    Do,
+    Completed,
+    Id,
+    Depth,
+    Parent,
+}
+
+impl std::str::FromStr for Field {
+    type Err = String;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "state" => Ok(Field::State),
+            "name" => Ok(Field::Name),
+            "description" => Ok(Field::Description),
+            "priority" => Ok(Field::Priority),
+            "context" | "contexts" | "context_list" => Ok(Field::Context),
+            "do" | "do_date" | "do_date_time" => Ok(Field::Do),
+            "completed" | "completed_date" | "completed_date_time" => Ok(Field::Completed),
+            "id" => Ok(Field::Id),
+            "depth" => Ok(Field::Depth),
+            "parent" => Ok(Field::Parent),
+            _ => Err(format!(
+                "unknown field {}, expected one of state, name, description, priority, context, do, completed, id, depth or parent",
+                name
+            )),
+        }
+    }
+}
+
+impl Field {
+    fn value<'a>(&self, action: &'a Value) -> &'a Value {
+        let common = &action["common"];
+        match self {
+            Field::State => &common["state"],
+            Field::Name => &common["name"],
+            Field::Description => &common["description"],
+            Field::Priority => &common["priority"],
+            Field::Context => &common["context_list"],
+            Field::Do => &common["do_date_time"],
+            Field::Completed => &common["completed_date_time"],
+            Field::Id => &common["id"],
+            Field::Depth => &action["depth"],
+            Field::Parent => &action["parent"],
+        }
+    }
+
+    fn is_date(&self) -> bool {
+        matches!(self, Field::Do | Field::Completed)
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Field::Priority | Field::Depth)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Compare(Comparison),
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateBase {
+    Now,
+    Today,
+    At(DateTime<Local>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Text(String),
+    Number(f64),
+    /// `open` and `closed` are states too, as far as queries are concerned
+    State(String),
+    Date(DateBase, Duration),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+    Not(Box<Expression>),
+    Compare(Field, Operator, Literal),
+    In(Field, Vec<Literal>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Operator(String),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            '\'' | '"' => {
+                chars.next();
+                let quoted: String = chars.by_ref().take_while(|next| *next != c).collect();
+                tokens.push(Token::Quoted(quoted));
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                let mut operator = String::from(c);
+                chars.next();
+                if chars.peek() == Some(&'=') {
+                    operator.push('=');
+                    chars.next();
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "(),'\"=!<>~".contains(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or_expression(&mut self) -> Result<Expression, String> {
+        let mut terms = vec![self.and_expression()?];
+        while self.peek_keyword("or") {
+            self.next();
+            terms.push(self.and_expression()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            Expression::Or(terms)
+        })
+    }
+
+    fn and_expression(&mut self) -> Result<Expression, String> {
+        let mut terms = vec![self.unary()?];
+        while self.peek_keyword("and") {
+            self.next();
+            terms.push(self.unary()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            Expression::And(terms)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.peek_keyword("not") {
+            self.next();
+            return Ok(Expression::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.next();
+            let inner = self.or_expression()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(inner),
+                _ => Err("missing a closing parenthesis".to_string()),
+            };
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expression, String> {
+        let field: Field = match self.next() {
+            Some(Token::Word(word)) => word.parse()?,
+            other => return Err(format!("expected a field but found {}", describe(&other))),
+        };
+
+        let negated_in = self.peek_keyword("not");
+        if negated_in {
+            self.next();
+        }
+        if self.peek_keyword("in") {
+            self.next();
+            let values = self.list(field)?;
+            let membership = Expression::In(field, values);
+            return Ok(if negated_in {
+                Expression::Not(Box::new(membership))
+            } else {
+                membership
+            });
+        }
+        if negated_in {
+            return Err("expected `in` after `not`".to_string());
+        }
+
+        let operator = match self.next() {
+            Some(Token::Operator(operator)) => match operator.as_str() {
+                "=" | "==" => Operator::Compare(Comparison::Equal),
+                "!=" => Operator::Compare(Comparison::NotEqual),
+                "<" => Operator::Compare(Comparison::Less),
+                "<=" => Operator::Compare(Comparison::LessOrEqual),
+                ">" => Operator::Compare(Comparison::Greater),
+                ">=" => Operator::Compare(Comparison::GreaterOrEqual),
+                "~" => Operator::Contains,
+                _ => return Err(format!("unknown operator {}", operator)),
+            },
+            other => {
+                return Err(format!(
+                    "expected an operator but found {}",
+                    describe(&other)
+                ));
+            }
+        };
+        let literal = self.literal(field)?;
+        Ok(Expression::Compare(field, operator, literal))
+    }
+
+    fn list(&mut self, field: Field) -> Result<Vec<Literal>, String> {
+        if self.next() != Some(Token::Open) {
+            return Err("expected `(` to start a list".to_string());
+        }
+        let mut values = Vec::new();
+        loop {
+            values.push(self.literal(field)?);
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::Close) => return Ok(values),
+                other => {
+                    return Err(format!(
+                        "expected `,` or `)` but found {}",
+                        describe(&other)
+                    ));
+                }
+            }
+        }
+    }
+
+    fn literal(&mut self, field: Field) -> Result<Literal, String> {
+        let text = match self.next() {
+            Some(Token::Word(word)) | Some(Token::Quoted(word)) => word,
+            other => return Err(format!("expected a value but found {}", describe(&other))),
+        };
+        parse_literal(field, &text)
+    }
+}
+
+fn describe(token: &Option<Token>) -> String {
+    match token {
+        None => "the end of the query".to_string(),
+        Some(Token::Word(word)) | Some(Token::Operator(word)) => format!("`{}`", word),
+        Some(Token::Quoted(text)) => format!("'{}'", text),
+        Some(Token::Open) => "`(`".to_string(),
+        Some(Token::Close) => "`)`".to_string(),
+        Some(Token::Comma) => "`,`".to_string(),
+    }
+}
+
+// values are read according to the field they are compared with, so mistakes show up before the
+// query runs rather than as a silently empty result
+fn parse_literal(field: Field, text: &str) -> Result<Literal, String> {
+    if field.is_number() {
+        return text
+            .parse::<f64>()
+            .map(Literal::Number)
+            .map_err(|_| format!("{} is not a number", text));
+    }
+    if field.is_date() {
+        return parse_date_literal(text);
+    }
+    match field {
+        Field::State => match text.to_lowercase().as_str() {
+            "open" | "closed" => Ok(Literal::State(text.to_lowercase())),
+            _ => {
+                let state: ActionState = text.parse()?;
+                let serialized = serde_json::to_value(state).map_err(|e| e.to_string())?;
+                Ok(Literal::State(
+                    serialized.as_str().unwrap_or_default().to_string(),
+                ))
+            }
+        },
+        Field::Context => Ok(Literal::Text(format!("@{}", text.trim_start_matches('@')))),
+        _ => Ok(Literal::Text(text.to_string())),
+    }
+}
+
+fn parse_date_literal(text: &str) -> Result<Literal, String> {
+    let split = text
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| *c == '+' || *c == '-')
+        .map(|(index, _)| index);
+    let (base_text, offset_text) = match split {
+        Some(index) if text[..index].chars().all(|c| c.is_ascii_alphabetic()) => {
+            (&text[..index], &text[index..])
+        }
+        _ => (text, ""),
+    };
+
+    let (base, day_offset) = match base_text.to_lowercase().as_str() {
+        "now" => (DateBase::Now, 0),
+        "today" => (DateBase::Today, 0),
+        "tomorrow" => (DateBase::Today, 1),
+        "yesterday" => (DateBase::Today, -1),
+        _ => (DateBase::At(parse_action_date_time(text)?), 0),
+    };
+
+    let mut offset = Duration::days(day_offset);
+    if !offset_text.is_empty() {
+        offset = offset
+            .checked_add(&parse_offset(offset_text)?)
+            .ok_or_else(|| format!("{} is too far from {}", offset_text, base_text))?;
+    }
+    Ok(Literal::Date(base, offset))
+}
+
+fn parse_offset(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("{} is not an offset like +3d, -2h, +1w or +30m", text);
+    let (sign, rest) = match text.split_at(1) {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return Err(invalid()),
+    };
+    // an offset that is only too long says so, rather than that it is not an offset at all
+    let well_formed = rest
+        .get(..rest.len().saturating_sub(1))
+        .is_some_and(|amount| amount.parse::<i64>().is_ok());
+    let duration = parse_duration(rest).map_err(|message| match well_formed {
+        true => message,
+        false => invalid(),
+    })?;
+    duration.checked_mul(sign).ok_or_else(invalid)
+}
+
+/// A length of time written as a number and a unit: `30m`, `2h`, `14d` or `1w`
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("{} is not a duration like 14d, 2h, 1w or 30m", text);
+    let unit = text.chars().last().ok_or_else(invalid)?;
+    let amount: i64 = text[..text.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    let duration = match unit {
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        'w' => Duration::try_weeks(amount),
+        _ => return Err(invalid()),
+    };
+    duration.ok_or_else(|| format!("{} is longer than any duration that can be kept", text))
+}
+
+impl std::str::FromStr for Expression {
+    type Err = String;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let expression = parser.or_expression()?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some(_) => Err(format!(
+                "unexpected {} in the query",
+                describe(&parser.next())
+            )),
+        }
+    }
+}
+
+// nothing when the offset runs past the dates that can be represented
+fn resolve_date(
+    base: &DateBase,
+    offset: &Duration,
+    now: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    let base = match base {
+        DateBase::Now => now,
+        DateBase::Today => now.with_time(NaiveTime::MIN).earliest().unwrap_or(now),
+        DateBase::At(date) => *date,
+    };
+    base.checked_add_signed(*offset)
+}
+
+fn check_date(literal: &Literal, now: DateTime<Local>) -> Result<(), String> {
+    match literal {
+        Literal::Date(base, offset) if resolve_date(base, offset, now).is_none() => Err(format!(
+            "{} days from {} is past any date there can be",
+            offset.num_days(),
+            match base {
+                DateBase::Now => "now".to_string(),
+                DateBase::Today => "today".to_string(),
+                DateBase::At(date) => date.format("%Y-%m-%d %H:%M").to_string(),
+            }
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn value_date(value: &Value) -> Option<DateTime<Local>> {
+    value
+        .as_str()
+        .and_then(|text| DateTime::parse_from_rfc3339(text).ok())
+        .map(|date| date.with_timezone(&Local))
+}
+
+impl Expression {
+    /// An error for the first date that lands past the ones that can be represented once it is
+    /// measured from `now`, rather than leaving it to never match
+    pub fn check_dates(&self, now: DateTime<Local>) -> Result<(), String> {
+        match self {
+            Expression::And(terms) | Expression::Or(terms) => {
+                terms.iter().try_for_each(|term| term.check_dates(now))
+            }
+            Expression::Not(term) => term.check_dates(now),
+            Expression::Compare(_, _, literal) => check_date(literal, now),
+            Expression::In(_, literals) => literals
+                .iter()
+                .try_for_each(|literal| check_date(literal, now)),
+        }
+    }
+
+    /// Whether the serialized action satisfies this expression, relative dates are measured from
+    /// `now`
+    pub fn matches(&self, action: &Value, now: DateTime<Local>) -> bool {
+        match self {
+            Expression::And(terms) => terms.iter().all(|term| term.matches(action, now)),
+            Expression::Or(terms) => terms.iter().any(|term| term.matches(action, now)),
+            Expression::Not(term) => !term.matches(action, now),
+            Expression::Compare(field, operator, literal) => {
+                compare(field.value(action), operator, literal, now)
+            }
+            Expression::In(field, literals) => literals.iter().any(|literal| {
+                compare(
+                    field.value(action),
+                    &Operator::Compare(Comparison::Equal),
+                    literal,
+                    now,
+                )
+            }),
+        }
+    }
+}
+
+// a missing value only ever satisfies `!=`, the same way an action without a priority is not
+// "priority <= 2" but is "priority != 2"
+fn compare(value: &Value, operator: &Operator, literal: &Literal, now: DateTime<Local>) -> bool {
+    if value.is_null() {
+        return *operator == Operator::Compare(Comparison::NotEqual);
+    }
+
+    // lists (contexts) match when any of their entries do, except for `!=` where none may
+    if let Value::Array(items) = value {
+        return if *operator == Operator::Compare(Comparison::NotEqual) {
+            !items
+                .iter()
+                .any(|item| compare(item, &Operator::Compare(Comparison::Equal), literal, now))
+        } else {
+            items
+                .iter()
+                .any(|item| compare(item, operator, literal, now))
+        };
+    }
+
+    match (literal, operator) {
+        (Literal::State(state), Operator::Compare(comparison)) => {
+            let own = value.as_str().unwrap_or_default();
+            let equal = match state.as_str() {
+                "open" | "closed" => {
+                    let is_open = own
+                        .parse::<ActionState>()
+                        .or_else(|_| serde_json::from_value::<ActionState>(value.clone()))
+                        .map(|own_state| own_state.is_open())
+                        .unwrap_or(false);
+                    is_open == (state == "open")
+                }
+                _ => own == state,
+            };
+            match comparison {
+                Comparison::Equal => equal,
+                Comparison::NotEqual => !equal,
+                _ => false,
+            }
+        }
+        (Literal::Number(number), Operator::Compare(comparison)) => value
+            .as_f64()
+            .is_some_and(|own| comparison.compare(&own, number)),
+        (Literal::Date(base, offset), Operator::Compare(comparison)) => value_date(value)
+            .zip(resolve_date(base, offset, now))
+            .is_some_and(|(own, date)| comparison.compare(&own, &date)),
+        (Literal::Text(text), Operator::Contains) | (Literal::State(text), Operator::Contains) => {
+            value_text(value)
+                .to_lowercase()
+                .contains(&text.to_lowercase())
+        }
+        (Literal::Text(text), Operator::Compare(comparison)) => {
+            comparison.compare(&value_text(value).as_str(), &text.as_str())
+        }
+        (Literal::Number(number), Operator::Contains) => {
+            value_text(value).contains(&number.to_string())
+        }
+        (Literal::Date(..), Operator::Contains) => false,
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Everything `read` can do to a list of actions beyond printing them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryOptions {
+    pub filter: Option<String>,
+    /// Comma separated fields, each one descending when it starts with `-` (`priority,-do`)
+    pub sort: Option<String>,
+    /// `context`, `state` or `parent`
+    pub group_by: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// A group of actions sharing the same key, actions that are not grouped land in a single group
+/// without one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Group {
+    pub key: Option<String>,
+    pub actions: Vec<Value>,
+}
+
+/// Filter, sort, limit and then group serialized actions, in that order
+pub fn query_actions(
+    actions: &[Value],
+    options: &QueryOptions,
+    now: DateTime<Local>,
+) -> Result<Vec<Group>, String> {
+    let filter = options
+        .filter
+        .as_deref()
+        .filter(|filter| !filter.trim().is_empty())
+        .map(str::parse::<Expression>)
+        .transpose()?;
+    if let Some(filter) = &filter {
+        filter.check_dates(now)?;
+    }
+
+    let mut matching: Vec<Value> = actions
+        .iter()
+        .filter(|action| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(action, now))
+        })
+        .cloned()
+        .collect();
+
+    if let Some(sort) = &options.sort {
+        sort_actions(&mut matching, sort)?;
+    }
+    if let Some(limit) = options.limit {
+        matching.truncate(limit);
+    }
+
+    match &options.group_by {
+        Some(group_by) => group_actions(matching, group_by),
+        None => Ok(vec![Group {
+            key: None,
+            actions: matching,
+        }]),
+    }
+}
+
+/// Sort serialized actions by comma separated fields, missing values always go last
+pub fn sort_actions(actions: &mut [Value], sort: &str) -> Result<(), String> {
+    let keys: Vec<(Field, bool)> = sort
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(|key| match key.strip_prefix('-') {
+            Some(field) => field.parse().map(|field| (field, true)),
+            None => key.parse().map(|field| (field, false)),
+        })
+        .collect::<Result<_, _>>()?;
+
+    actions.sort_by(|left, right| {
+        keys.iter()
+            .map(|(field, descending)| {
+                compare_values(field, field.value(left), field.value(right), *descending)
+            })
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+    Ok(())
+}
+
+fn compare_values(field: &Field, left: &Value, right: &Value, descending: bool) -> Ordering {
+    let ordering = match (left.is_null(), right.is_null()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        (false, false) => {
+            if field.is_date() {
+                value_date(left).cmp(&value_date(right))
+            } else if field.is_number() {
+                left.as_f64()
+                    .partial_cmp(&right.as_f64())
+                    .unwrap_or(Ordering::Equal)
+            } else if *field == Field::State {
+                state_rank(left).cmp(&state_rank(right))
+            } else {
+                value_text(left)
+                    .to_lowercase()
+                    .cmp(&value_text(right).to_lowercase())
+            }
+        }
+    };
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+// the work in front of you first, the work behind you last
+fn state_rank(value: &Value) -> usize {
+    match serde_json::from_value::<ActionState>(value.clone()) {
+        Ok(ActionState::InProgress) => 0,
+        Ok(ActionState::NotStarted) => 1,
+        Ok(ActionState::BlockedorAwaiting) => 2,
+        Ok(ActionState::Completed) => 3,
+        Ok(ActionState::Cancelled) => 4,
+        Err(_) => 5,
+    }
+}
+
+/// Split serialized actions into groups, keeping the order in which each key first shows up
+///
+/// Actions with several contexts show up under each of them
+pub fn group_actions(actions: Vec<Value>, group_by: &str) -> Result<Vec<Group>, String> {
+    let keys_for = |action: &Value| -> Result<Vec<String>, String> {
+        match group_by {
+            "context" => {
+                let contexts: Vec<String> = Field::Context
+                    .value(action)
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(value_text)
+                    .collect();
+                Ok(if contexts.is_empty() {
+                    vec!["(no context)".to_string()]
+                } else {
+                    contexts
+                })
+            }
+            "state" => Ok(vec![value_text(Field::State.value(action))]),
+            "parent" => Ok(vec![
+                Field::Parent
+                    .value(action)
+                    .as_str()
+                    .unwrap_or("(root)")
+                    .to_string(),
+            ]),
+            "file" => Ok(vec![
+                action
+                    .get("file")
+                    .and_then(Value::as_str)
+                    .unwrap_or("(no file)")
+                    .to_string(),
+            ]),
+            _ => Err(format!(
+                "cannot group by {}, expected context, state, parent or file",
+                group_by
+            )),
+        }
+    };
+
+    let mut groups: Vec<Group> = Vec::new();
+    for action in actions {
+        for key in keys_for(&action)? {
+            match groups
+                .iter_mut()
+                .find(|group| group.key.as_deref() == Some(key.as_str()))
+            {
+                Some(group) => group.actions.push(action.clone()),
+                None => groups.push(Group {
+                    key: Some(key),
+                    actions: vec![action.clone()],
+                }),
+            }
+        }
+    }
+    Ok(groups)
+}
+
+/// Number the actions 1 and up in the order they will be shown, storing it under `number`, and
+/// return the path of each numbered action in that order
+///
+/// An action showing up in more than one group keeps the number it got first
+pub fn number_actions(groups: &mut [Group]) -> Vec<ActionPath> {
+    let mut paths: Vec<ActionPath> = Vec::new();
+    for action in groups.iter_mut().flat_map(|group| group.actions.iter_mut()) {
+        let Ok(path) = serde_json::from_value::<ActionPath>(action["path"].clone()) else {
+            continue;
+        };
+        let number = match paths.iter().position(|shown| *shown == path) {
+            Some(index) => index + 1,
+            None => {
+                paths.push(path);
+                paths.len()
+            }
+        };
+        if let Some(fields) = action.as_object_mut() {
+            fields.insert("number".to_string(), number.into());
+        }
+    }
+    paths
+}
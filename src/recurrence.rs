@@ -0,0 +1,604 @@
+//! Actions that repeat
+//!
+//! The grammar keeps a repeat at the end of the do-date, either `RDa` for every day or `RW` with
+//! the days of the week, each optionally followed by a time of day:
+//!
+//! ```text
+//! ( ) Weekly review @2026-10-19 09:00 RW Mon 09:00
+//! ( ) Water the plants @2026-10-18 RDa
+//! ```
+//!
+//! The grammar has nowhere to keep an interval, so repeats every few days or weeks and monthly or
+//! yearly ones go in the context list instead, the same way what an action is blocked by does, and
+//! take their time of day from the do-date:
+//!
+//! ```text
+//! ( ) Pay rent +every-1m @2026-11-01
+//! ( ) Sprint review +work,every-2w @2026-10-23 15:00
+//! ```
+//!
+//! Repeats can also be given as `every mon,thu at 09:00`, `every 2w`, `every month` or as an
+//! iCalendar RRULE like `FREQ=WEEKLY;BYDAY=MO,TH;BYHOUR=9`, as long as the result is one of
+//! these. Anything else, like the second Tuesday of the month, is refused with an error rather
+//! than quietly turned into something close.
+//! Completing a repeating action leaves it where it is as a record and adds the next occurrence
+//! right after it.
+use chrono::{DateTime, Datelike, Duration, Local, Months, NaiveTime, TimeZone, Timelike, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::edits::{insert_at, line_after, map_source};
+use crate::entities::{ActionState, CommonActionProperties, action_line};
+use crate::selector::select_open;
+use crate::transitions::{TransitionPolicy, transition_action};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recurrence {
+    /// Every day, written `RDa`
+    Daily { time: Option<NaiveTime> },
+    /// Every week on the given days, or a week on from the last time when there are none, written
+    /// `RW`
+    Weekly {
+        days: Vec<Weekday>,
+        time: Option<NaiveTime>,
+    },
+    /// Every `count` days, weeks, months or years, written in the context list as `every-2w`
+    Interval { count: u32, period: Period },
+}
+
+/// The unit an interval repeat counts in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+const PERIODS: [(Period, char, &str, &str); 4] = [
+    (Period::Days, 'd', "day", "DAILY"),
+    (Period::Weeks, 'w', "week", "WEEKLY"),
+    (Period::Months, 'm', "month", "MONTHLY"),
+    (Period::Years, 'y', "year", "YEARLY"),
+];
+
+/// Contexts starting with this hold an interval repeat, like `every-2w`
+pub const INTERVAL_PREFIX: &str = "every-";
+
+// the spellings the grammar accepts, which are not quite the usual ones
+const GRAMMAR_DAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "Mon"),
+    (Weekday::Tue, "Tue"),
+    (Weekday::Wed, "Wen"),
+    (Weekday::Thu, "Thurs"),
+    (Weekday::Fri, "Fri"),
+    (Weekday::Sat, "Sat"),
+    (Weekday::Sun, "Sun"),
+];
+
+const RRULE_DAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "MO"),
+    (Weekday::Tue, "TU"),
+    (Weekday::Wed, "WE"),
+    (Weekday::Thu, "TH"),
+    (Weekday::Fri, "FR"),
+    (Weekday::Sat, "SA"),
+    (Weekday::Sun, "SU"),
+];
+
+fn period_letter(period: Period) -> char {
+    PERIODS
+        .iter()
+        .find(|(known, ..)| *known == period)
+        .map(|(_, letter, ..)| *letter)
+        .unwrap_or('d')
+}
+
+fn grammar_day(text: &str) -> Option<Weekday> {
+    GRAMMAR_DAYS
+        .iter()
+        .find(|(_, name)| *name == text)
+        .map(|(day, _)| *day)
+}
+
+fn sorted_days(mut days: Vec<Weekday>) -> Vec<Weekday> {
+    days.sort_by_key(Weekday::num_days_from_monday);
+    days.dedup();
+    days
+}
+
+// `09:00`, `9:00`, `01:05PM` and `01:05 pm` all say the same kind of thing
+fn parse_time(text: &str) -> Result<NaiveTime, String> {
+    let compact = text.replace(' ', "").to_uppercase();
+    ["%H:%M", "%I:%M%p"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(&compact, format).ok())
+        .ok_or_else(|| format!("{} is not a time of day like 09:00 or 01:05PM", text))
+}
+
+impl Recurrence {
+    /// The next time this comes due after `start`, at the rule's time of day or else at the time
+    /// `start` had
+    pub fn next(&self, start: DateTime<Local>) -> DateTime<Local> {
+        let date = start.date_naive();
+        let (next_date, time) = match self {
+            Recurrence::Daily { time } => (date + Duration::days(1), time),
+            Recurrence::Weekly { days, time } if days.is_empty() => {
+                (date + Duration::weeks(1), time)
+            }
+            Recurrence::Weekly { days, time } => {
+                let next_date = (1..=7)
+                    .map(|offset| date + Duration::days(offset))
+                    .find(|candidate| days.contains(&candidate.weekday()))
+                    .unwrap_or(date + Duration::weeks(1));
+                (next_date, time)
+            }
+            // a month on from the 31st lands on the last day of a shorter month
+            Recurrence::Interval { count, period } => {
+                let next_date = match period {
+                    Period::Days => date.checked_add_days(chrono::Days::new(*count as u64)),
+                    Period::Weeks => date.checked_add_days(chrono::Days::new(7 * *count as u64)),
+                    Period::Months => date.checked_add_months(Months::new(*count)),
+                    Period::Years => date.checked_add_months(Months::new(12 * *count)),
+                };
+                (next_date.unwrap_or(date), &None)
+            }
+        };
+        let naive = next_date.and_time(time.unwrap_or(start.time()));
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap_or_else(|| Local.from_utc_datetime(&naive))
+    }
+
+    /// The first time this comes due after `start` that is also after `now`, so finishing late
+    /// does not leave a trail of occurrences that are already overdue
+    pub fn next_after(&self, start: DateTime<Local>, now: DateTime<Local>) -> DateTime<Local> {
+        let mut next = self.next(start);
+        while next <= now {
+            next = self.next(next);
+        }
+        next
+    }
+
+    /// The time of day the repeat sets, when it sets one rather than keeping the do-date's
+    pub fn time(&self) -> Option<NaiveTime> {
+        match self {
+            Recurrence::Daily { time } | Recurrence::Weekly { time, .. } => *time,
+            Recurrence::Interval { .. } => None,
+        }
+    }
+
+    /// The same repeat on the same days, leaving the time to the do-date
+    pub fn without_time(&self) -> Self {
+        match self {
+            Recurrence::Daily { .. } => Recurrence::Daily { time: None },
+            Recurrence::Weekly { days, .. } => Recurrence::Weekly {
+                days: days.clone(),
+                time: None,
+            },
+            Recurrence::Interval { .. } => self.clone(),
+        }
+    }
+
+    /// The repeat as written at the end of the do-date, which intervals have no way to be
+    pub fn do_date_text(&self) -> Option<String> {
+        match self {
+            Recurrence::Interval { .. } => None,
+            _ => Some(self.to_string()),
+        }
+    }
+
+    /// The repeat as a context, which is where intervals are kept
+    pub fn context(&self) -> Option<String> {
+        match self {
+            Recurrence::Interval { count, period } => Some(format!(
+                "{}{}{}",
+                INTERVAL_PREFIX,
+                count,
+                period_letter(*period)
+            )),
+            _ => None,
+        }
+    }
+
+    /// The interval repeat a context like `every-2w` holds, if it is one
+    pub fn from_context(context: &str) -> Option<Self> {
+        let rest = context.strip_prefix(INTERVAL_PREFIX)?;
+        match Self::interval(rest)? {
+            Ok(recurrence @ Recurrence::Interval { .. }) => Some(recurrence),
+            _ => None,
+        }
+    }
+
+    // `2w`, `3 days` or `1 month`, where one day or week is the same as `RDa` or `RW`; nothing when
+    // the text does not look like an interval at all
+    fn interval(text: &str) -> Option<Result<Self, String>> {
+        let text = text.trim();
+        let split = text
+            .find(|c: char| !c.is_ascii_digit())
+            .filter(|index| *index > 0)?;
+        let (amount, unit) = (&text[..split], text[split..].trim());
+        let (period, ..) = PERIODS.iter().find(|(_, letter, name, _)| {
+            unit.len() == 1 && unit.starts_with(*letter)
+                || unit == *name
+                || unit.strip_suffix('s') == Some(name)
+        })?;
+        let Ok(count) = amount.parse::<u32>() else {
+            return Some(Err(format!("{} is too many to repeat every", amount)));
+        };
+        Some(match (count, period) {
+            (0, _) => Err(format!(
+                "{} does not repeat, it needs a count above 0",
+                text
+            )),
+            (1, Period::Days) => Ok(Recurrence::Daily { time: None }),
+            (1, Period::Weeks) => Self::weekly(Vec::new(), None),
+            (count, period) => Ok(Recurrence::Interval {
+                count,
+                period: *period,
+            }),
+        })
+    }
+
+    /// The same repeat as an iCalendar RRULE value, which reads back as this one
+    pub fn to_rrule(&self) -> String {
+        let (frequency, days, time) = match self {
+            Recurrence::Daily { time } => ("DAILY", &[][..], time),
+            Recurrence::Weekly { days, time } => ("WEEKLY", &days[..], time),
+            Recurrence::Interval { count, period } => {
+                let frequency = PERIODS
+                    .iter()
+                    .find(|(known, ..)| known == period)
+                    .map(|(.., frequency)| *frequency)
+                    .unwrap_or_default();
+                return match count {
+                    1 => format!("FREQ={}", frequency),
+                    count => format!("FREQ={};INTERVAL={}", frequency, count),
+                };
+            }
+        };
+        let mut rule = format!("FREQ={}", frequency);
+        let days: Vec<&str> = days
+            .iter()
+            .filter_map(|day| RRULE_DAYS.iter().find(|(known, _)| known == day))
+            .map(|(_, name)| *name)
+            .collect();
+        if !days.is_empty() {
+            rule.push_str(&format!(";BYDAY={}", days.join(",")));
+        }
+        if let Some(time) = time {
+            rule.push_str(&format!(
+                ";BYHOUR={};BYMINUTE={}",
+                time.hour(),
+                time.minute()
+            ));
+        }
+        rule
+    }
+
+    fn parse_grammar(text: &str) -> Result<Self, String> {
+        let invalid = || format!("{} is not a repeat like RDa or RW Mon Thurs 09:00", text);
+        let (weekly, rest) = if let Some(rest) = text.strip_prefix("RDa") {
+            (false, rest)
+        } else if let Some(rest) = text.strip_prefix("RW") {
+            (true, rest)
+        } else {
+            return Err(invalid());
+        };
+
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        let day_count = words
+            .iter()
+            .take_while(|word| grammar_day(word).is_some())
+            .count();
+        let days: Vec<Weekday> = words[..day_count]
+            .iter()
+            .filter_map(|word| grammar_day(word))
+            .collect();
+        let time = match words[day_count..].concat() {
+            time if time.is_empty() => None,
+            time => Some(parse_time(&time).map_err(|_| invalid())?),
+        };
+        if !weekly && !days.is_empty() {
+            return Err(invalid());
+        }
+        Ok(match weekly {
+            true => Recurrence::Weekly {
+                days: sorted_days(days),
+                time,
+            },
+            false => Recurrence::Daily { time },
+        })
+    }
+
+    fn parse_every(text: &str) -> Result<Self, String> {
+        let rest = text["every".len()..].trim();
+        let (period, time) = match rest.split_once(" at ") {
+            Some((period, time)) => (period.trim(), Some(parse_time(time.trim())?)),
+            None => (rest, None),
+        };
+        let period = period.to_lowercase();
+        let interval = match period.as_str() {
+            "month" | "monthly" => Some(Ok(Recurrence::Interval {
+                count: 1,
+                period: Period::Months,
+            })),
+            "year" | "yearly" | "annually" => Some(Ok(Recurrence::Interval {
+                count: 1,
+                period: Period::Years,
+            })),
+            _ => Self::interval(&period),
+        };
+        match (interval, time) {
+            (Some(Ok(Recurrence::Interval { .. })), Some(_)) => {
+                return Err(format!(
+                    "{} repeats on an interval, which takes its time of day from the do-date",
+                    text
+                ));
+            }
+            (Some(Ok(Recurrence::Daily { .. })), time) => return Ok(Recurrence::Daily { time }),
+            (Some(Ok(Recurrence::Weekly { .. })), time) => return Self::weekly(Vec::new(), time),
+            (Some(result), _) => return result,
+            (None, _) => {}
+        }
+
+        match period.as_str() {
+            "day" | "daily" | "d" => return Ok(Recurrence::Daily { time }),
+            "week" | "weekly" | "w" => return Self::weekly(Vec::new(), time),
+            "weekday" | "weekdays" => {
+                return Self::weekly(
+                    vec![
+                        Weekday::Mon,
+                        Weekday::Tue,
+                        Weekday::Wed,
+                        Weekday::Thu,
+                        Weekday::Fri,
+                    ],
+                    time,
+                );
+            }
+            "weekend" | "weekends" => {
+                return Self::weekly(vec![Weekday::Sat, Weekday::Sun], time);
+            }
+            _ => {}
+        }
+        if period.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(format!(
+                "{} is not a repeat like every 2w or every 3 days",
+                text
+            ));
+        }
+
+        let days = period
+            .split([',', ' '])
+            .filter(|day| !day.is_empty())
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| format!("{} is not a day of the week in {}", day, text))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::weekly(days, time)
+    }
+
+    fn parse_rrule(text: &str) -> Result<Self, String> {
+        let rule = text.strip_prefix("RRULE:").unwrap_or(text);
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut days = Vec::new();
+        let mut hour = None;
+        let mut minute = None;
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("{} is not a KEY=VALUE part of an RRULE", part))?;
+            match key.to_uppercase().as_str() {
+                "FREQ" => frequency = Some(value.to_uppercase()),
+                "INTERVAL" => {
+                    interval = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| format!("{} is not an RRULE interval", value))?;
+                }
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        let day = RRULE_DAYS
+                            .iter()
+                            .find(|(_, name)| name.eq_ignore_ascii_case(day))
+                            .map(|(day, _)| *day)
+                            .ok_or_else(|| format!("{} is not an RRULE day", day))?;
+                        days.push(day);
+                    }
+                }
+                "BYHOUR" => hour = Some(value.parse::<u32>().map_err(|e| e.to_string())?),
+                "BYMINUTE" => minute = Some(value.parse::<u32>().map_err(|e| e.to_string())?),
+                other => {
+                    return Err(format!(
+                        "{} is not something action files can hold a repeat by",
+                        other
+                    ));
+                }
+            }
+        }
+        let time = match (hour, minute) {
+            (None, None) => None,
+            (hour, minute) => Some(
+                NaiveTime::from_hms_opt(hour.unwrap_or(0), minute.unwrap_or(0), 0)
+                    .ok_or_else(|| format!("{} has an invalid time of day", text))?,
+            ),
+        };
+        let frequency = frequency.ok_or_else(|| format!("{} has no FREQ", text))?;
+        let period = PERIODS
+            .iter()
+            .find(|(.., name)| *name == frequency)
+            .map(|(period, ..)| *period)
+            .ok_or_else(|| {
+                format!(
+                    "{} repeats {}, which action files have no way to hold",
+                    text,
+                    frequency.to_lowercase()
+                )
+            })?;
+        match (period, interval) {
+            (Period::Days, 1) if days.is_empty() => Ok(Recurrence::Daily { time }),
+            (Period::Days | Period::Weeks, 1) => Self::weekly(days, time),
+            // intervals are kept apart from the do-date, so they take its time and every day
+            _ if days.is_empty() && time.is_none() => Ok(Recurrence::Interval {
+                count: interval,
+                period,
+            }),
+            _ => Err(format!(
+                "{} repeats on set days or times at an interval, which action files have no way to hold",
+                text
+            )),
+        }
+    }
+
+    // `RW` only takes a time after at least one day
+    fn weekly(days: Vec<Weekday>, time: Option<NaiveTime>) -> Result<Self, String> {
+        if days.is_empty() && time.is_some() {
+            return Err(
+                "a weekly repeat needs its days to have a time, e.g. every mon at 09:00"
+                    .to_string(),
+            );
+        }
+        Ok(Recurrence::Weekly {
+            days: sorted_days(days),
+            time,
+        })
+    }
+}
+
+impl std::str::FromStr for Recurrence {
+    type Err = String;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if text.to_lowercase().starts_with("every") {
+            Self::parse_every(text)
+        } else if text.to_uppercase().contains("FREQ=") {
+            Self::parse_rrule(text)
+        } else {
+            Self::parse_grammar(text)
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = match self {
+            Recurrence::Daily { time } => {
+                write!(f, "RDa")?;
+                time
+            }
+            Recurrence::Weekly { days, time } => {
+                write!(f, "RW")?;
+                for day in days {
+                    if let Some((_, name)) = GRAMMAR_DAYS.iter().find(|(known, _)| known == day) {
+                        write!(f, " {}", name)?;
+                    }
+                }
+                time
+            }
+            // the form `every` reads back, the file keeps it as a context instead
+            Recurrence::Interval { count, period } => {
+                return write!(f, "every {}{}", count, period_letter(*period));
+            }
+        };
+        if let Some(time) = time {
+            write!(f, " {}", time.format("%H:%M"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Recurrence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Recurrence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// What the next occurrence of a repeating action is counted from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecurFrom {
+    /// The do-date of the occurrence being completed, so the schedule never drifts
+    #[default]
+    DoDate,
+    /// The moment it was completed, for things that should wait a full period after being done
+    Completion,
+}
+
+impl std::str::FromStr for RecurFrom {
+    type Err = String;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "do-date" => Ok(RecurFrom::DoDate),
+            "completion" => Ok(RecurFrom::Completion),
+            _ => Err(format!(
+                "unknown recur from {}, expected do-date or completion",
+                value
+            )),
+        }
+    }
+}
+
+/// Complete the action matching `selector`, following up a repeating one with its next occurrence
+///
+/// The next occurrence goes right after the completed action and its children, with the same
+/// name, description, priority, contexts and repeat, a new id from `new_id` and a do-date moved
+/// on from where `from` says. Its children are not copied along.
+pub fn complete_action(
+    source: &str,
+    selector: &str,
+    policy: TransitionPolicy,
+    from: RecurFrom,
+    now: DateTime<Local>,
+    new_id: impl FnOnce() -> Uuid,
+) -> Result<String, String> {
+    let source_map = map_source(source)?;
+    let path = select_open(&source_map.actions, selector)?;
+    let action = source_map
+        .action(&path)
+        .ok_or_else(|| format!("no action matches {}", selector))?;
+    let completed = transition_action(source, selector, ActionState::Completed, policy, now)?;
+
+    // completing something already completed should not schedule it a second time
+    let Some(recurrence) = &action.common.recurrence else {
+        return Ok(completed);
+    };
+    if action.common.state == ActionState::Completed {
+        return Ok(completed);
+    }
+
+    let start = match from {
+        RecurFrom::DoDate => action.common.do_date_time.unwrap_or(now),
+        RecurFrom::Completion => now,
+    };
+    let next = CommonActionProperties {
+        state: ActionState::NotStarted,
+        id: Some(new_id()),
+        do_date_time: Some(recurrence.next_after(start, now)),
+        completed_date_time: None,
+        ..action.common.clone()
+    };
+
+    let completed_map = map_source(&completed)?;
+    let location = completed_map
+        .location(&path)
+        .ok_or_else(|| format!("{} has no location", action.common.name))?;
+    let position = line_after(&completed, &location.range);
+    Ok(insert_at(
+        &completed,
+        position,
+        &action_line(&next, action.depth),
+    ))
+}
@@ -0,0 +1,344 @@
+//! Picking actions out of an action list with a short piece of text
+//!
+//! Every command that works on existing actions takes a selector, which can be:
+//! - the start of an id after a `#` (`#0192`), or a whole id with or without the `#`, since a
+//!   bare start of one reads as a position or a name
+//! - the position of the action in the file counting from 1, parents before children (`3`)
+//! - a path of names from a root action down (`Project/Subtask`)
+//! - predicates on the properties, all of which have to match
+//!   (`state:open priority:<3 context:@work`)
+//! - a name, matched exactly and only falling back to ignoring case, so a typo never lands on some
+//!   other action (`Weekly review`)
+//! - a loose match on the name with a leading `~` (`~wkly rev`)
+//! - a name that would read as one of the others with a leading `=`, for names that are all digits
+//!   or start with `#` or `~` (`=2024`)
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entities::{Action, ActionPath, ActionState, get_action, walk};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind", content = "value")]
+pub enum Selector {
+    /// The start of an id in lowercase hex without dashes, a full id is just a long prefix
+    Id(String),
+    Index(usize),
+    Path(Vec<String>),
+    Name(String),
+    Fuzzy(String),
+    Predicates(Vec<Predicate>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "field", content = "value")]
+pub enum Predicate {
+    State(StateMatch),
+    Priority(Comparison, usize),
+    Context(String),
+    Name(String),
+    Id(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateMatch {
+    Open,
+    Closed,
+    Is(ActionState),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Comparison {
+    #[serde(rename = "<")]
+    Less,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+    #[serde(rename = "=")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = ">")]
+    Greater,
+}
+
+impl Comparison {
+    pub fn compare<T: PartialOrd>(&self, left: &T, right: &T) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+
+    // splits the operator off the front of `text`, a bare value means equality
+    fn split(text: &str) -> (Comparison, &str) {
+        for (operator, comparison) in [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("!=", Comparison::NotEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ] {
+            if let Some(rest) = text.strip_prefix(operator) {
+                return (comparison, rest);
+            }
+        }
+        (Comparison::Equal, text)
+    }
+}
+
+const PREDICATE_KEYS: [&str; 5] = ["state", "priority", "context", "name", "id"];
+
+impl std::str::FromStr for Selector {
+    type Err = String;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("the selector is empty".to_string());
+        }
+
+        if let Some(name) = text.strip_prefix('=') {
+            return Ok(Selector::Name(name.trim().to_string()));
+        }
+        if let Some(id) = text.strip_prefix('#') {
+            return Ok(Selector::Id(parse_id_prefix(id)?));
+        }
+        if let Ok(id) = Uuid::parse_str(text) {
+            return Ok(Selector::Id(id.simple().to_string()));
+        }
+        if text.chars().all(|c| c.is_ascii_digit()) {
+            let index: usize = text
+                .parse()
+                .map_err(|_| format!("{} is too large to be a position", text))?;
+            return Ok(Selector::Index(index));
+        }
+        if let Some(name) = text.strip_prefix('~') {
+            return Ok(Selector::Fuzzy(name.trim().to_string()));
+        }
+        if is_predicate_list(text) {
+            return text
+                .split_whitespace()
+                .map(parse_predicate)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Selector::Predicates);
+        }
+        if text.contains('/') {
+            return Ok(Selector::Path(
+                text.split('/')
+                    .map(|segment| segment.trim().to_string())
+                    .collect(),
+            ));
+        }
+        Ok(Selector::Name(text.to_string()))
+    }
+}
+
+fn is_predicate_list(text: &str) -> bool {
+    text.split_whitespace().all(|token| {
+        token
+            .split_once(':')
+            .is_some_and(|(key, _)| PREDICATE_KEYS.contains(&key))
+    })
+}
+
+fn parse_id_prefix(text: &str) -> Result<String, String> {
+    let prefix: String = text.chars().filter(|c| *c != '-').collect();
+    if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("#{} is not the start of an id", text));
+    }
+    Ok(prefix.to_lowercase())
+}
+
+fn parse_predicate(token: &str) -> Result<Predicate, String> {
+    let (key, value) = token
+        .split_once(':')
+        .ok_or_else(|| format!("{} is not a key:value predicate", token))?;
+    match key {
+        "state" => Ok(Predicate::State(match value.to_lowercase().as_str() {
+            "open" => StateMatch::Open,
+            "closed" => StateMatch::Closed,
+            _ => StateMatch::Is(value.parse()?),
+        })),
+        "priority" => {
+            let (comparison, number) = Comparison::split(value);
+            let priority = number
+                .parse()
+                .map_err(|_| format!("{} is not a priority", number))?;
+            Ok(Predicate::Priority(comparison, priority))
+        }
+        "context" => Ok(Predicate::Context(format!(
+            "@{}",
+            value.trim_start_matches('@')
+        ))),
+        "name" => Ok(Predicate::Name(value.to_lowercase())),
+        "id" => Ok(Predicate::Id(parse_id_prefix(
+            value.trim_start_matches('#'),
+        )?)),
+        _ => Err(format!(
+            "unknown predicate {}, expected one of {}",
+            key,
+            PREDICATE_KEYS.join(", ")
+        )),
+    }
+}
+
+impl Predicate {
+    pub fn matches(&self, action: &Action) -> bool {
+        let common = &action.common;
+        match self {
+            Predicate::State(StateMatch::Open) => common.state.is_open(),
+            Predicate::State(StateMatch::Closed) => !common.state.is_open(),
+            Predicate::State(StateMatch::Is(state)) => common.state == *state,
+            Predicate::Priority(comparison, priority) => common
+                .priority
+                .is_some_and(|own| comparison.compare(&own, priority)),
+            Predicate::Context(context) => common
+                .context_list
+                .as_ref()
+                .is_some_and(|contexts| contexts.contains(context)),
+            Predicate::Name(text) => common.name.to_lowercase().contains(text.as_str()),
+            Predicate::Id(prefix) => id_starts_with(action, prefix),
+        }
+    }
+}
+
+fn id_starts_with(action: &Action, prefix: &str) -> bool {
+    action
+        .common
+        .id
+        .is_some_and(|id| id.simple().to_string().starts_with(prefix))
+}
+
+// every letter of the pattern shows up in the name, in order, ignoring case
+fn fuzzy_matches(name: &str, pattern: &str) -> bool {
+    let mut name_chars = name.chars().flat_map(char::to_lowercase);
+    pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .all(|wanted| name_chars.any(|c| c == wanted))
+}
+
+impl Selector {
+    /// The path of every action this selector matches, in file order
+    pub fn resolve(&self, action_list: &[Action]) -> Vec<ActionPath> {
+        let actions = walk(action_list);
+        let matching = |keep: &dyn Fn(&Action) -> bool| -> Vec<ActionPath> {
+            actions
+                .iter()
+                .filter(|(_, action)| keep(action))
+                .map(|(path, _)| path.clone())
+                .collect()
+        };
+
+        match self {
+            Selector::Id(prefix) => matching(&|action| id_starts_with(action, prefix)),
+            Selector::Index(index) => index
+                .checked_sub(1)
+                .and_then(|position| actions.get(position))
+                .map(|(path, _)| vec![path.clone()])
+                .unwrap_or_default(),
+            Selector::Path(segments) => {
+                let by_path: Vec<ActionPath> = actions
+                    .iter()
+                    .filter(|(path, _)| path.len() == segments.len())
+                    .filter(|(path, _)| {
+                        (1..=path.len()).all(|length| {
+                            get_action(action_list, &path[..length]).is_some_and(|ancestor| {
+                                ancestor.common.name == segments[length - 1]
+                            })
+                        })
+                    })
+                    .map(|(path, _)| path.clone())
+                    .collect();
+
+                // names are allowed to hold a `/` of their own
+                if by_path.is_empty() {
+                    return Selector::Name(segments.join("/")).resolve(action_list);
+                }
+                by_path
+            }
+            Selector::Name(name) => {
+                let exact = matching(&|action| action.common.name == *name);
+                if !exact.is_empty() {
+                    return exact;
+                }
+                let lowered = name.to_lowercase();
+                matching(&|action| action.common.name.to_lowercase() == lowered)
+            }
+            Selector::Fuzzy(pattern) => {
+                matching(&|action| fuzzy_matches(&action.common.name, pattern))
+            }
+            Selector::Predicates(predicates) => {
+                matching(&|action| predicates.iter().all(|predicate| predicate.matches(action)))
+            }
+        }
+    }
+}
+
+/// The path of every action the selector matches, in file order
+pub fn select(action_list: &[Action], selector: &str) -> Result<Vec<ActionPath>, String> {
+    Ok(selector.parse::<Selector>()?.resolve(action_list))
+}
+
+/// The single action the selector matches, anything else is an error since commands that change
+/// the file should never have to guess
+pub fn select_one(action_list: &[Action], selector: &str) -> Result<ActionPath, String> {
+    let matches = select(action_list, selector)?;
+    only_match(action_list, selector, matches)
+}
+
+/// Like [`select_one`], except that when several actions match and just one of them is open it
+/// is the one picked, so a repeating action can be completed by name next to its earlier records
+pub fn select_open(action_list: &[Action], selector: &str) -> Result<ActionPath, String> {
+    let matches = prefer_open(action_list, select(action_list, selector)?);
+    only_match(action_list, selector, matches)
+}
+
+/// The one open action among several matches, or the matches as they were when there is not
+/// exactly one
+pub fn prefer_open(action_list: &[Action], matches: Vec<ActionPath>) -> Vec<ActionPath> {
+    let open: Vec<&ActionPath> = matches
+        .iter()
+        .filter(|path| {
+            get_action(action_list, path).is_some_and(|action| action.common.state.is_open())
+        })
+        .collect();
+    match open.as_slice() {
+        [path] if matches.len() > 1 => vec![(*path).clone()],
+        _ => matches,
+    }
+}
+
+fn only_match(
+    action_list: &[Action],
+    selector: &str,
+    mut matches: Vec<ActionPath>,
+) -> Result<ActionPath, String> {
+    match matches.len() {
+        0 => Err(format!("no action matches {}", selector)),
+        1 => Ok(matches.remove(0)),
+        count => {
+            let candidates: Vec<String> = matches
+                .iter()
+                .filter_map(|path| get_action(action_list, path))
+                .map(|action| match action.common.id {
+                    Some(id) => format!("  {} #{}", action.common.name, id.simple()),
+                    None => format!("  {}", action.common.name),
+                })
+                .collect();
+            Err(format!(
+                "{} actions match {}, narrow it down or use an id:\n{}",
+                count,
+                selector,
+                candidates.join("\n")
+            ))
+        }
+    }
+}
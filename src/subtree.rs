@@ -0,0 +1,154 @@
+//! Editing one action and everything under it as plain text
+//!
+//! The subtree is cut out of the source with its lines moved up to the top level, so it reads as
+//! an action file of its own, and once it has been edited it is checked and moved back down to
+//! where it came from. Everything else in the source is left exactly as it was.
+use std::collections::HashMap;
+use std::ops::Range;
+use uuid::Uuid;
+
+use crate::COMMENT_PREFIX;
+use crate::edits::{
+    ActionLocation, TextEdit, apply_edits, line_after, line_start, map_source, validate_common,
+};
+use crate::entities::{MAX_ACTION_DEPTH, walk};
+use crate::selector::select_one;
+
+/// An action and its descendants as cut out of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subtree {
+    /// The whole lines it takes up in the source
+    pub range: Range<usize>,
+    /// How deep the action sits in the source
+    pub depth: usize,
+    /// Its lines with the action itself at the top level and its descendants under it
+    pub text: String,
+}
+
+// the marker in front of an action at `depth`, the same one `entities::action_line` writes
+fn depth_prefix(depth: usize) -> String {
+    match depth {
+        0 => String::new(),
+        _ => format!("{}{} ", "\t".repeat(depth), ">".repeat(depth)),
+    }
+}
+
+// the edit giving the action at `location` the marker for `depth`, in place of whatever
+// indentation and marker it was written with
+fn reindent(source: &str, location: &ActionLocation, depth: usize) -> Option<TextEdit> {
+    let state = location.property("state")?;
+    Some(TextEdit {
+        range: line_start(source, location.range.start)..state.start,
+        text: depth_prefix(depth),
+    })
+}
+
+/// The action matching `selector` and its descendants, moved up to the top level
+pub fn extract_subtree(source: &str, selector: &str) -> Result<Subtree, String> {
+    let source_map = map_source(source)?;
+    let path = select_one(&source_map.actions, selector)?;
+    let location = source_map
+        .location(&path)
+        .ok_or_else(|| format!("no action matches {}", selector))?;
+    let range = line_start(source, location.range.start)..line_after(source, &location.range);
+    let depth = path.len() - 1;
+
+    let edits: Vec<TextEdit> = source_map
+        .locations
+        .iter()
+        .filter(|location| location.path.starts_with(&path))
+        .filter_map(|location| reindent(source, location, location.path.len() - 1 - depth))
+        .map(|edit| TextEdit {
+            range: edit.range.start - range.start..edit.range.end - range.start,
+            text: edit.text,
+        })
+        .collect();
+    Ok(Subtree {
+        text: apply_edits(&source[range.clone()], &edits),
+        range,
+        depth,
+    })
+}
+
+/// The source with `edited` in place of the subtree, moved back down to the depth it came from
+///
+/// The edited text has to parse on its own and fit where the subtree was: nested no deeper than
+/// the grammar allows, a story only on root actions and no id used twice in the file. It can hold
+/// any number of actions, which all take the place of the one that was cut out.
+pub fn splice_subtree(source: &str, subtree: &Subtree, edited: &str) -> Result<String, String> {
+    let edited_map = map_source(edited)?;
+    for ((path, action), location) in walk(&edited_map.actions)
+        .into_iter()
+        .zip(&edited_map.locations)
+    {
+        validate_common(&action.common).map_err(|e| format!("{}: {}", action.common.name, e))?;
+        if subtree.depth + path.len() - 1 > MAX_ACTION_DEPTH {
+            return Err(format!(
+                "{} would be nested deeper than the {} levels an action file allows",
+                action.common.name, MAX_ACTION_DEPTH
+            ));
+        }
+        if subtree.depth > 0 && path.len() == 1 && location.property("story").is_some() {
+            return Err(format!(
+                "{} cannot have a story once it is moved back under its parent",
+                action.common.name
+            ));
+        }
+    }
+
+    let edits: Vec<TextEdit> = edited_map
+        .locations
+        .iter()
+        .filter_map(|location| reindent(edited, location, subtree.depth + location.path.len() - 1))
+        .collect();
+    let mut text = apply_edits(edited, &edits);
+    if !text.is_empty() && !text.ends_with('\n') && source[..subtree.range.end].ends_with('\n') {
+        text.push('\n');
+    }
+
+    let mut updated = source.to_string();
+    updated.replace_range(subtree.range.clone(), &text);
+    let updated_map = map_source(&updated)
+        .map_err(|e| format!("the edit does not fit back into the file: {}", e))?;
+
+    let mut names: HashMap<Uuid, &str> = HashMap::new();
+    for (_, action) in walk(&updated_map.actions) {
+        let Some(id) = action.common.id else {
+            continue;
+        };
+        if let Some(other) = names.insert(id, &action.common.name) {
+            return Err(format!(
+                "{} and {} both have the id {}",
+                other, action.common.name, id
+            ));
+        }
+    }
+    Ok(updated)
+}
+
+/// The text with the comment lines at its top taken off
+pub fn strip_comments(text: &str) -> String {
+    let mut lines = text.split_inclusive('\n').peekable();
+    while lines
+        .next_if(|line| line.trim_start().starts_with(COMMENT_PREFIX))
+        .is_some()
+    {}
+    lines.collect()
+}
+
+/// The text with the problems that kept it from being saved written above it as comments
+pub fn with_problems(text: &str, problems: &str) -> String {
+    let mut commented = format!(
+        "{} the edit was not saved, fix it and save again or empty the file to give up\n",
+        COMMENT_PREFIX
+    );
+    commented.push_str(&format!(
+        "{} line numbers count from the first line after these comments\n",
+        COMMENT_PREFIX
+    ));
+    for problem in problems.lines() {
+        commented.push_str(&format!("{} {}\n", COMMENT_PREFIX, problem));
+    }
+    commented.push_str(&strip_comments(text));
+    commented
+}
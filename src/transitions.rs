@@ -0,0 +1,135 @@
+//! Moving actions between states, keeping the completed date in step with the state
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::edits::{SourceMap, TextEdit, apply_edits, map_source, set_property};
+use crate::entities::{Action, ActionState, format_action_date_time, walk};
+use crate::selector::{select_one, select_open};
+
+/// How a change of state treats the descendants of the action being changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionPolicy {
+    /// Only the selected action changes
+    #[default]
+    None,
+    /// Completing or cancelling an action does the same to every open descendant
+    Cascade,
+    /// An action cannot be completed while any of its descendants are still open
+    Refuse,
+}
+
+impl std::str::FromStr for TransitionPolicy {
+    type Err = String;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "none" => Ok(TransitionPolicy::None),
+            "cascade" => Ok(TransitionPolicy::Cascade),
+            "refuse" => Ok(TransitionPolicy::Refuse),
+            _ => Err(format!(
+                "unknown transition policy {}, expected none, cascade or refuse",
+                value
+            )),
+        }
+    }
+}
+
+/// Move the action matching `selector` into `state`
+///
+/// Completing stamps the completed date with `now` unless the action was already completed, and
+/// moving into any open state clears it again. A selector matching several actions picks the one
+/// that is still open, except when reopening, which is meant for closed ones.
+pub fn transition_action(
+    source: &str,
+    selector: &str,
+    state: ActionState,
+    policy: TransitionPolicy,
+    now: DateTime<Local>,
+) -> Result<String, String> {
+    let source_map = map_source(source)?;
+    let path = if state == ActionState::NotStarted {
+        select_one(&source_map.actions, selector)?
+    } else {
+        select_open(&source_map.actions, selector)?
+    };
+    let action = source_map
+        .action(&path)
+        .ok_or_else(|| format!("no action matches {}", selector))?;
+
+    let open_descendants: Vec<&Action> = action
+        .depth_first()
+        .skip(1)
+        .filter(|descendant| descendant.common.state.is_open())
+        .collect();
+
+    if policy == TransitionPolicy::Refuse
+        && state == ActionState::Completed
+        && !open_descendants.is_empty()
+    {
+        let names: Vec<&str> = open_descendants
+            .iter()
+            .map(|descendant| descendant.common.name.as_str())
+            .collect();
+        return Err(format!(
+            "{} still has open children: {}",
+            action.common.name,
+            names.join(", ")
+        ));
+    }
+
+    let mut targets = vec![path.clone()];
+    if policy == TransitionPolicy::Cascade && !state.is_open() {
+        targets.extend(
+            walk(&action.children)
+                .into_iter()
+                .filter(|(_, descendant)| descendant.common.state.is_open())
+                .map(|(child_path, _)| [path.clone(), child_path].concat()),
+        );
+    }
+
+    let edits: Vec<TextEdit> = targets
+        .iter()
+        .flat_map(|target| state_edits(source, &source_map, target, state, now))
+        .collect();
+    Ok(apply_edits(source, &edits))
+}
+
+fn state_edits(
+    source: &str,
+    source_map: &SourceMap,
+    path: &[usize],
+    state: ActionState,
+    now: DateTime<Local>,
+) -> Vec<TextEdit> {
+    let (Some(action), Some(location)) = (source_map.action(path), source_map.location(path))
+    else {
+        return Vec::new();
+    };
+    let common = &action.common;
+
+    let mut edits = Vec::new();
+    if common.state != state {
+        edits.extend(set_property(
+            source,
+            location,
+            "state",
+            Some(&format!("({})", state)),
+        ));
+    }
+
+    let completed_date = match state {
+        ActionState::Completed => common.completed_date_time.or(Some(now)),
+        ActionState::Cancelled => common.completed_date_time,
+        _ => None,
+    };
+    if completed_date != common.completed_date_time {
+        let text = completed_date.map(|date| format!("%{}", format_action_date_time(&date)));
+        edits.extend(set_property(
+            source,
+            location,
+            "completed_date",
+            text.as_deref(),
+        ));
+    }
+    edits
+}
@@ -6,6 +6,61 @@ pub fn create_tree_wrapper(tree: Tree, source: String) -> TreeWrapper {
     TreeWrapper { tree, source }
 }
 
+// the grammar spells out every level of the hierarchy as its own node kind, indexed here by depth
+pub const ACTION_NODE_KINDS: [&str; 6] = [
+    "root_action",
+    "child_action",
+    "grandchild_action",
+    "great_grandchild_action",
+    "double_great_grandchild_action",
+    "leaf_action",
+];
+
+// the node holding the children of an action at the same depth, leaf actions have none
+pub const ACTION_LIST_NODE_KINDS: [&str; 5] = [
+    "child_action_list",
+    "grandchild_action_list",
+    "great_grandchild_action_list",
+    "double_great_grandchild_action_list",
+    "leaf_action_list",
+];
+
+// the properties of a `core_action` in the order the grammar expects them
+pub const PROPERTY_NODE_KINDS: [&str; 8] = [
+    "state",
+    "name",
+    "description",
+    "priority",
+    "context_list",
+    "do_date_or_time",
+    "completed_date",
+    "id",
+];
+
+pub fn action_node_depth(kind: &str) -> Option<usize> {
+    ACTION_NODE_KINDS
+        .iter()
+        .position(|action_kind| *action_kind == kind)
+}
+
+/// Every action node underneath `node` in file order, which lines up with `entities::walk` for
+/// any file that parses cleanly
+pub fn action_nodes<'a>(node: &Node<'a>) -> Vec<Node<'a>> {
+    let mut binding = node.walk();
+    let children: Vec<Node<'a>> = node.children(&mut binding).collect();
+    children
+        .into_iter()
+        .flat_map(|child| {
+            let mut nodes = Vec::new();
+            if action_node_depth(child.kind()).is_some() {
+                nodes.push(child);
+            }
+            nodes.extend(action_nodes(&child));
+            nodes
+        })
+        .collect()
+}
+
 // we need both the tree and the source to do our type conversions properly
 pub struct TreeWrapper {
     pub tree: Tree,
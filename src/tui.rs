@@ -0,0 +1,641 @@
+//! The full screen view that opens when no subcommand is given
+//!
+//! The tree of actions is shown one action per line and changed with single keys, much like
+//! lazygit or visidata. Every change goes through the same edits as the subcommands and is saved
+//! through a `Store` right away, so the file on disk never lags behind the screen. Drawing and key
+//! handling are plain functions of the `App`, so any ratatui backend can drive it, including the
+//! in-memory `TestBackend`.
+use chrono::{DateTime, Local};
+use ratatui::Frame;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::dependencies::{to_unblock, unblock_actions};
+use crate::edits::{
+    ActionUpdate, apply_edits, map_source, normalize_context, set_property, update_action,
+};
+use crate::entities::{
+    Action, ActionList, ActionPath, ActionState, format_action_date_time, get_action, walk,
+};
+use crate::get_action_list_struct;
+use crate::recurrence::{RecurFrom, complete_action};
+use crate::selector::select;
+use crate::transitions::{TransitionPolicy, transition_action};
+
+/// Where the app keeps the action file between changes
+pub trait Store {
+    /// Keep `source` as the new contents of the file, handing back what was actually kept, which
+    /// may differ when saving adds ids
+    fn save(&mut self, source: &str) -> Result<String, String>;
+}
+
+/// A store that only remembers what it was given, for tests
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStore {
+    /// Every save in order, the last one is the current contents
+    pub saves: Vec<String>,
+}
+
+impl Store for MemoryStore {
+    fn save(&mut self, source: &str) -> Result<String, String> {
+        self.saves.push(source.to_string());
+        Ok(source.to_string())
+    }
+}
+
+/// A property being typed in at the bottom of the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Priority,
+    Contexts,
+    Filter,
+}
+
+impl Field {
+    fn label(&self) -> &'static str {
+        match self {
+            Field::Name => "rename",
+            Field::Priority => "priority",
+            Field::Contexts => "contexts",
+            Field::Filter => "filter",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Input { field: Field, buffer: String },
+    Help,
+}
+
+/// One line of the tree as it is shown
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row<'a> {
+    pub path: ActionPath,
+    pub action: &'a Action,
+}
+
+const HELP: [(&str, &str); 16] = [
+    ("j k", "move down and up"),
+    ("g G", "go to the first and last action"),
+    (
+        "h l",
+        "fold or go to the parent, unfold or go to the first child",
+    ),
+    ("tab", "fold or unfold"),
+    ("space", "complete, or reopen what is completed"),
+    (
+        "x - = _",
+        "toggle completed, in progress, blocked or cancelled",
+    ),
+    ("r", "rename"),
+    ("p", "set the priority, empty removes it"),
+    ("c", "set the contexts, comma separated"),
+    (
+        "/",
+        "filter with a selector such as `state:open context:@work`",
+    ),
+    ("esc", "clear the filter, or quit when there is none"),
+    ("u", "undo"),
+    ("ctrl-r", "redo"),
+    ("?", "show this help"),
+    ("q", "quit"),
+    ("", "every change is saved right away"),
+];
+
+pub struct App<S: Store> {
+    pub store: S,
+    /// Shown at the top, usually the path of the file
+    pub title: String,
+    /// How completing or cancelling treats descendants
+    pub policy: TransitionPolicy,
+    /// What the next occurrence of a repeating action counts from
+    pub recur_from: RecurFrom,
+    pub mode: Mode,
+    /// Set once the app should close
+    pub quit: bool,
+    source: String,
+    actions: ActionList,
+    cursor: usize,
+    folded: HashSet<ActionPath>,
+    filter: Option<String>,
+    undo: Vec<String>,
+    redo: Vec<String>,
+    message: Option<String>,
+    list_state: ListState,
+}
+
+impl<S: Store> App<S> {
+    /// An app showing `source`, which has to parse cleanly since every change is an edit of it
+    pub fn new(source: &str, store: S) -> Result<Self, String> {
+        let actions = get_action_list_struct(&serde_json::json!({}), source)?;
+        Ok(App {
+            store,
+            title: String::new(),
+            policy: TransitionPolicy::default(),
+            recur_from: RecurFrom::default(),
+            mode: Mode::Normal,
+            quit: false,
+            source: source.to_string(),
+            actions,
+            cursor: 0,
+            folded: HashSet::new(),
+            filter: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            message: None,
+            list_state: ListState::default(),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The message from the last key, such as an error from an edit that was refused
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The actions that are shown, leaving out the children of folded actions and, while a
+    /// filter is set, everything that neither matches it nor has a match underneath it
+    pub fn rows(&self) -> Vec<Row<'_>> {
+        let matches: Option<Vec<ActionPath>> = self
+            .filter
+            .as_ref()
+            .map(|filter| select(&self.actions, filter).unwrap_or_default());
+        walk(&self.actions)
+            .into_iter()
+            .filter(|(path, _)| {
+                !(1..path.len()).any(|length| self.folded.contains(&path[..length]))
+            })
+            .filter(|(path, _)| {
+                matches.as_ref().is_none_or(|matches| {
+                    matches
+                        .iter()
+                        .any(|matched| matched.starts_with(path.as_slice()))
+                })
+            })
+            .map(|(path, action)| Row { path, action })
+            .collect()
+    }
+
+    /// The path of the action under the cursor
+    pub fn selected(&self) -> Option<ActionPath> {
+        self.rows().get(self.cursor).map(|row| row.path.clone())
+    }
+
+    fn select_path(&mut self, path: &[usize]) {
+        if let Some(index) = self.rows().iter().position(|row| row.path == path) {
+            self.cursor = index;
+        }
+    }
+
+    fn clamp_cursor(&mut self) {
+        self.cursor = self.cursor.min(self.rows().len().saturating_sub(1));
+    }
+
+    // the position selectors understand, counting every action in the file from 1
+    fn position(path: &[usize], actions: &[Action]) -> Option<String> {
+        walk(actions)
+            .iter()
+            .position(|(walked, _)| walked == path)
+            .map(|index| (index + 1).to_string())
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, now: DateTime<Local>) {
+        match self.mode.clone() {
+            Mode::Help => self.mode = Mode::Normal,
+            Mode::Input { field, buffer } => self.handle_input(key, field, buffer),
+            Mode::Normal => {
+                self.message = None;
+                self.handle_normal(key, now);
+            }
+        }
+    }
+
+    fn handle_normal(&mut self, key: KeyEvent, now: DateTime<Local>) {
+        let rows = self.rows().len();
+        let selected = self.selected();
+        match key.code {
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => self.redo(),
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc => {
+                if self.filter.take().is_none() {
+                    self.quit = true;
+                }
+                match selected {
+                    Some(path) => self.select_path(&path),
+                    None => self.cursor = 0,
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.cursor = (self.cursor + 1).min(rows.saturating_sub(1))
+            }
+            KeyCode::Char('k') | KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Char('g') | KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('G') | KeyCode::End => self.cursor = rows.saturating_sub(1),
+            KeyCode::Char('h') | KeyCode::Left => self.fold_or_leave(),
+            KeyCode::Char('l') | KeyCode::Right => self.unfold_or_enter(),
+            KeyCode::Tab => {
+                if let Some(path) = selected
+                    && !self.folded.remove(&path)
+                {
+                    self.folded.insert(path);
+                }
+            }
+            KeyCode::Char(' ') | KeyCode::Char('x') => self.toggle(ActionState::Completed, now),
+            KeyCode::Char('-') => self.toggle(ActionState::InProgress, now),
+            KeyCode::Char('=') => self.toggle(ActionState::BlockedorAwaiting, now),
+            KeyCode::Char('_') => self.toggle(ActionState::Cancelled, now),
+            KeyCode::Char('r') | KeyCode::Char('i') => self.start_input(Field::Name),
+            KeyCode::Char('p') => self.start_input(Field::Priority),
+            KeyCode::Char('c') | KeyCode::Char('+') => self.start_input(Field::Contexts),
+            KeyCode::Char('/') => self.start_input(Field::Filter),
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('?') => self.mode = Mode::Help,
+            _ => {}
+        }
+    }
+
+    fn selected_action(&self) -> Option<&Action> {
+        let path = self.selected()?;
+        get_action(&self.actions, &path)
+    }
+
+    fn fold_or_leave(&mut self) {
+        let Some(path) = self.selected() else {
+            return;
+        };
+        let has_children = self
+            .selected_action()
+            .is_some_and(|action| !action.children.is_empty());
+        if has_children && !self.folded.contains(&path) {
+            self.folded.insert(path);
+        } else if path.len() > 1 {
+            self.select_path(&path[..path.len() - 1]);
+        }
+    }
+
+    fn unfold_or_enter(&mut self) {
+        let Some(path) = self.selected() else {
+            return;
+        };
+        if self.folded.remove(&path) {
+            return;
+        }
+        let mut child = path.clone();
+        child.push(0);
+        self.select_path(&child);
+    }
+
+    fn start_input(&mut self, field: Field) {
+        let action = self.selected_action();
+        let buffer = match field {
+            Field::Name => action.map(|action| action.common.name.clone()),
+            Field::Priority => {
+                action.and_then(|action| action.common.priority.map(|p| p.to_string()))
+            }
+            Field::Contexts => action.map(|action| {
+                action
+                    .common
+                    .context_list
+                    .iter()
+                    .flatten()
+                    .map(|context| context.trim_start_matches('@'))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+            Field::Filter => self.filter.clone(),
+        };
+        if action.is_none() && field != Field::Filter {
+            return;
+        }
+        self.mode = Mode::Input {
+            field,
+            buffer: buffer.unwrap_or_default(),
+        };
+    }
+
+    fn handle_input(&mut self, key: KeyEvent, field: Field, mut buffer: String) {
+        match key.code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Enter => {
+                self.mode = Mode::Normal;
+                self.submit(field, buffer.trim());
+            }
+            KeyCode::Backspace => {
+                buffer.pop();
+                self.mode = Mode::Input { field, buffer };
+            }
+            KeyCode::Char(c) => {
+                buffer.push(c);
+                self.mode = Mode::Input { field, buffer };
+            }
+            _ => {}
+        }
+    }
+
+    fn submit(&mut self, field: Field, text: &str) {
+        if field == Field::Filter {
+            self.filter = (!text.is_empty()).then(|| text.to_string());
+            if let Some(Err(e)) = self
+                .filter
+                .as_ref()
+                .map(|filter| select(&self.actions, filter))
+            {
+                self.message = Some(e);
+            }
+            self.cursor = 0;
+            return;
+        }
+        let Some(path) = self.selected() else {
+            return;
+        };
+        let Some(selector) = Self::position(&path, &self.actions) else {
+            return;
+        };
+        let updated = match field {
+            Field::Name => update_action(
+                &self.source,
+                &selector,
+                &ActionUpdate {
+                    name: Some(text.to_string()),
+                    ..Default::default()
+                },
+            ),
+            Field::Priority => self.set_priority(&path, &selector, text),
+            Field::Contexts => self.set_contexts(&selector, text),
+            Field::Filter => return,
+        };
+        self.change(updated);
+    }
+
+    fn set_priority(&self, path: &[usize], selector: &str, text: &str) -> Result<String, String> {
+        if text.is_empty() {
+            let source_map = map_source(&self.source)?;
+            let edit = source_map
+                .location(path)
+                .and_then(|location| set_property(&self.source, location, "priority", None));
+            return Ok(match edit {
+                Some(edit) => apply_edits(&self.source, &[edit]),
+                None => self.source.clone(),
+            });
+        }
+        let priority = text
+            .trim_start_matches('!')
+            .parse::<usize>()
+            .map_err(|_| format!("{} is not a priority", text))?;
+        update_action(
+            &self.source,
+            selector,
+            &ActionUpdate {
+                priority: Some(priority),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn set_contexts(&self, selector: &str, text: &str) -> Result<String, String> {
+        let wanted: Vec<String> = text
+            .split([',', ' '])
+            .filter(|context| !context.trim().is_empty())
+            .map(normalize_context)
+            .collect();
+        let current: Vec<String> = self
+            .selected_action()
+            .and_then(|action| action.common.context_list.clone())
+            .unwrap_or_default();
+        update_action(
+            &self.source,
+            selector,
+            &ActionUpdate {
+                add_contexts: wanted
+                    .iter()
+                    .filter(|context| !current.contains(context))
+                    .cloned()
+                    .collect(),
+                remove_contexts: current
+                    .iter()
+                    .filter(|context| !wanted.contains(context))
+                    .cloned()
+                    .collect(),
+                ..Default::default()
+            },
+        )
+    }
+
+    // moving into a state the action is already in moves it back to not started instead
+    fn toggle(&mut self, state: ActionState, now: DateTime<Local>) {
+        let Some(action) = self.selected_action() else {
+            return;
+        };
+        let target = match action.common.state == state {
+            true => ActionState::NotStarted,
+            false => state,
+        };
+        let Some(selector) = self
+            .selected()
+            .and_then(|path| Self::position(&path, &self.actions))
+        else {
+            return;
+        };
+        let updated = match target {
+            ActionState::Completed => complete_action(
+                &self.source,
+                &selector,
+                self.policy,
+                self.recur_from,
+                now,
+                Uuid::now_v7,
+            ),
+            _ => transition_action(&self.source, &selector, target, self.policy, now),
+        };
+        // finishing an action frees whatever was only waiting on it, as part of the same change
+        let updated = updated.and_then(|updated| match target.is_open() {
+            true => Ok(updated),
+            false => {
+                let actions = get_action_list_struct(&serde_json::json!({}), &updated)?;
+                unblock_actions(&updated, &to_unblock(&actions))
+            }
+        });
+        self.change(updated);
+    }
+
+    // every change is one step of undo, saved as soon as it is made
+    fn change(&mut self, updated: Result<String, String>) {
+        match updated {
+            Ok(updated) if updated == self.source => {}
+            Ok(updated) => {
+                let previous = std::mem::replace(&mut self.source, updated);
+                self.undo.push(previous);
+                self.redo.clear();
+                self.save();
+            }
+            Err(e) => self.message = Some(e),
+        }
+    }
+
+    fn undo(&mut self) {
+        match self.undo.pop() {
+            Some(previous) => {
+                let current = std::mem::replace(&mut self.source, previous);
+                self.redo.push(current);
+                self.save();
+                // a failed save says so instead
+                self.message
+                    .get_or_insert_with(|| "undid the last change".to_string());
+            }
+            None => self.message = Some("nothing to undo".to_string()),
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.redo.pop() {
+            Some(next) => {
+                let current = std::mem::replace(&mut self.source, next);
+                self.undo.push(current);
+                self.save();
+            }
+            None => self.message = Some("nothing to redo".to_string()),
+        }
+    }
+
+    fn save(&mut self) {
+        let selected = self.selected();
+        match self.store.save(&self.source) {
+            Ok(saved) => self.source = saved,
+            Err(e) => self.message = Some(e),
+        }
+        match get_action_list_struct(&serde_json::json!({}), &self.source) {
+            Ok(actions) => self.actions = actions,
+            Err(e) => self.message = Some(e.to_string()),
+        }
+        if let Some(path) = selected {
+            self.select_path(&path);
+        }
+        self.clamp_cursor();
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [title_area, list_area, status_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let mut title = vec![Span::styled(
+            self.title.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        if let Some(filter) = &self.filter {
+            title.push(Span::styled(
+                format!("  / {}", filter),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        frame.render_widget(Paragraph::new(Line::from(title)), title_area);
+
+        let items: Vec<ListItem> = self
+            .rows()
+            .iter()
+            .map(|row| ListItem::new(self.row_line(row)))
+            .collect();
+        let list =
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        self.list_state.select(Some(self.cursor));
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let status = match &self.mode {
+            Mode::Input { field, buffer } => format!("{}: {}_", field.label(), buffer),
+            _ => self.message.clone().unwrap_or_else(|| {
+                "space done  r rename  p priority  c contexts  / filter  u undo  ? help  q quit"
+                    .to_string()
+            }),
+        };
+        frame.render_widget(
+            Paragraph::new(status).style(Style::default().fg(Color::DarkGray)),
+            status_area,
+        );
+
+        if self.mode == Mode::Help {
+            self.draw_help(frame, list_area);
+        }
+    }
+
+    fn row_line(&self, row: &Row) -> Line<'static> {
+        let common = &row.action.common;
+        let marker = match (
+            row.action.children.is_empty(),
+            self.folded.contains(&row.path),
+        ) {
+            (true, _) => "  ",
+            (false, true) => "▸ ",
+            (false, false) => "▾ ",
+        };
+        let name_style = match common.state {
+            ActionState::Completed | ActionState::Cancelled => Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::CROSSED_OUT),
+            ActionState::InProgress => Style::default().fg(Color::Green),
+            ActionState::BlockedorAwaiting => Style::default().fg(Color::Red),
+            ActionState::NotStarted => Style::default(),
+        };
+        let mut spans = vec![
+            Span::raw(format!("{}{}", "  ".repeat(row.action.depth), marker)),
+            Span::styled(format!("({}) {}", common.state, common.name), name_style),
+        ];
+        if let Some(priority) = common.priority {
+            spans.push(Span::styled(
+                format!(" !{}", priority),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        if let Some(contexts) = &common.context_list {
+            spans.push(Span::styled(
+                format!(" {}", contexts.join(" ")),
+                Style::default().fg(Color::Cyan),
+            ));
+        }
+        if let Some(do_date) = &common.do_date_time {
+            let repeat = common
+                .recurrence
+                .as_ref()
+                .map(|recurrence| format!(" {}", recurrence))
+                .unwrap_or_default();
+            spans.push(Span::styled(
+                format!(" @{}{}", format_action_date_time(do_date), repeat),
+                Style::default().fg(Color::Magenta),
+            ));
+        }
+        Line::from(spans)
+    }
+
+    fn draw_help(&self, frame: &mut Frame, area: Rect) {
+        let width = 72.min(area.width);
+        let height = (HELP.len() as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        let lines: Vec<Line> = HELP
+            .iter()
+            .map(|(keys, what)| Line::from(format!("{:>8}  {}", keys, what)))
+            .collect();
+        frame.render_widget(Clear, popup);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" keys ")),
+            popup,
+        );
+    }
+}
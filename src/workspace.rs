@@ -0,0 +1,235 @@
+//! Working with actions spread over several files as if they were one list
+//!
+//! The `workspace` setting lists files, directories (every `.actions` file directly inside) and
+//! glob patterns. Each file is parsed into its own list and remembers where it came from, reads
+//! see the lists one after the other, and anything that changes an action is sent back to the file
+//! it was found in.
+use glob::glob;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::diagnostics::Diagnostic;
+use crate::entities::{
+    Action, ActionList, ActionPath, FlatAction, depth_first, flatten, get_action, walk,
+};
+use crate::get_action_list_lenient;
+use crate::selector::{prefer_open, select};
+
+pub const ACTION_FILE_EXTENSION: &str = "actions";
+
+pub struct WorkspaceFile {
+    pub path: PathBuf,
+    pub source: String,
+    pub actions: ActionList,
+    /// Problems found while parsing, the actions that could be read are still in `actions`
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub struct Workspace {
+    pub files: Vec<WorkspaceFile>,
+}
+
+fn expand_home(entry: &str) -> PathBuf {
+    match (entry.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(entry),
+    }
+}
+
+/// Whether `path` is `dir` or somewhere underneath it, however either of them is spelled
+pub fn is_within(path: &Path, dir: &Path) -> bool {
+    let resolve = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    resolve(path).starts_with(resolve(dir))
+}
+
+/// Every file the workspace entries name, in the order the entries are given and sorted within
+/// each one, without repeats
+///
+/// Anything under one of the `excluded` directories is left out even when an entry names it, which
+/// keeps archived actions from being read back in as part of the workspace.
+pub fn resolve_entries(entries: &[String], excluded: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for entry in entries {
+        let expanded = expand_home(entry);
+        let mut found: Vec<PathBuf> = if expanded.is_dir() {
+            std::fs::read_dir(&expanded)
+                .map_err(|e| format!("unable to list {}: {}", expanded.display(), e))?
+                .filter_map(|dir_entry| dir_entry.ok().map(|dir_entry| dir_entry.path()))
+                .filter(|path| {
+                    path.is_file()
+                        && path.extension().and_then(|ext| ext.to_str())
+                            == Some(ACTION_FILE_EXTENSION)
+                })
+                .collect()
+        } else if entry.contains(['*', '?', '[']) {
+            glob(&expanded.to_string_lossy())
+                .map_err(|e| format!("invalid workspace pattern {}: {}", entry, e))?
+                .filter_map(Result::ok)
+                .filter(|path| path.is_file())
+                .collect()
+        } else {
+            vec![expanded]
+        };
+        found.sort();
+        for path in found {
+            if !paths.contains(&path) && !excluded.iter().any(|dir| is_within(&path, dir)) {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+impl Workspace {
+    /// Parse already read files, leniently so that one broken file does not hide the others
+    pub fn from_sources(sources: Vec<(PathBuf, String)>) -> Result<Self, String> {
+        let files = sources
+            .into_iter()
+            .map(|(path, source)| {
+                let parsed = get_action_list_lenient(&serde_json::json!({}), &source)?;
+                Ok(WorkspaceFile {
+                    path,
+                    source,
+                    actions: parsed.actions,
+                    diagnostics: parsed.diagnostics,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Workspace { files })
+    }
+
+    /// Read and parse every file
+    pub fn load(paths: &[PathBuf]) -> Result<Self, String> {
+        let sources = paths
+            .iter()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .map(|source| (path.clone(), source))
+                    .map_err(|e| format!("unable to read {}: {}", path.display(), e))
+            })
+            .collect::<Result<_, _>>()?;
+        Workspace::from_sources(sources)
+    }
+
+    /// The root actions of every file, one file after the other
+    pub fn actions(&self) -> ActionList {
+        self.files
+            .iter()
+            .flat_map(|file| file.actions.iter().cloned())
+            .collect()
+    }
+
+    /// Every action flattened, tagged with the file it came from, and with paths into `actions`
+    pub fn flatten(&self) -> Vec<FlatAction> {
+        let merged = self.actions();
+        let mut flat = flatten(&merged);
+        for action in &mut flat {
+            if let Some((file, _)) = self.locate(&action.path) {
+                action.file = Some(self.files[file].path.display().to_string());
+            }
+        }
+        flat
+    }
+
+    /// The file an action of `actions` is in and its path within that file
+    pub fn locate(&self, merged_path: &[usize]) -> Option<(usize, ActionPath)> {
+        let (root, rest) = merged_path.split_first()?;
+        let mut offset = 0;
+        for (index, file) in self.files.iter().enumerate() {
+            if *root < offset + file.actions.len() {
+                let mut path = vec![root - offset];
+                path.extend_from_slice(rest);
+                return Some((index, path));
+            }
+            offset += file.actions.len();
+        }
+        None
+    }
+
+    /// The file holding the action with this id
+    pub fn file_with_id(&self, id: Uuid) -> Option<usize> {
+        self.files
+            .iter()
+            .position(|file| depth_first(&file.actions).any(|action| action.common.id == Some(id)))
+    }
+
+    /// The one action the selector matches across every file, as the file it is in and a
+    /// selector that picks exactly that action out of the file on its own
+    pub fn select_one(&self, selector: &str) -> Result<(usize, String), String> {
+        let merged = self.actions();
+        let matches = select(&merged, selector)?;
+        self.only_match(&merged, selector, matches)
+    }
+
+    /// Like [`Workspace::select_one`], except that when several actions match and just one of
+    /// them is open it is the one picked
+    pub fn select_open(&self, selector: &str) -> Result<(usize, String), String> {
+        let merged = self.actions();
+        let matches = prefer_open(&merged, select(&merged, selector)?);
+        self.only_match(&merged, selector, matches)
+    }
+
+    fn only_match(
+        &self,
+        merged: &[Action],
+        selector: &str,
+        matches: Vec<ActionPath>,
+    ) -> Result<(usize, String), String> {
+        match matches.as_slice() {
+            [] => Err(format!("no action matches {}", selector)),
+            [merged_path] => {
+                let (file, path) = self
+                    .locate(merged_path)
+                    .ok_or_else(|| format!("no action matches {}", selector))?;
+                let position = walk(&self.files[file].actions)
+                    .iter()
+                    .position(|(walked, _)| *walked == path)
+                    .ok_or_else(|| format!("no action matches {}", selector))?;
+                Ok((file, (position + 1).to_string()))
+            }
+            _ => {
+                let candidates: Vec<String> = matches
+                    .iter()
+                    .filter_map(|merged_path| {
+                        let action = get_action(merged, merged_path)?;
+                        let (file, _) = self.locate(merged_path)?;
+                        let id = action
+                            .common
+                            .id
+                            .map(|id| format!(" #{}", id.simple()))
+                            .unwrap_or_default();
+                        Some(format!(
+                            "  {}{} ({})",
+                            action.common.name,
+                            id,
+                            self.files[file].path.display()
+                        ))
+                    })
+                    .collect();
+                Err(format!(
+                    "{} actions match {}, narrow it down or use an id:\n{}",
+                    matches.len(),
+                    selector,
+                    candidates.join("\n")
+                ))
+            }
+        }
+    }
+
+    /// Every file's name and contents together, which changes whenever any one file does
+    pub fn fingerprint(&self) -> String {
+        self.files
+            .iter()
+            .map(|file| format!("{}\n{}", file.path.display(), file.source))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The workspace file at `path`, however the path is spelled
+    pub fn file(&self, path: &Path) -> Option<usize> {
+        let wanted = path.canonicalize().ok();
+        self.files.iter().position(|file| {
+            file.path == path || (wanted.is_some() && file.path.canonicalize().ok() == wanted)
+        })
+    }
+}
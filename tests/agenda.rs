@@ -0,0 +1,97 @@
+mod common;
+
+use cliche::agenda::*;
+use common::{now, parse};
+
+const SOURCE: &str = "\
+( ) Gather numbers @2026-10-16 09:30 #01951111cfa6718db303d7107f4005b3
+( ) Project
+\t> (-) Refactor parser @2026-10-18 14:00
+\t> ( ) Write docs @2026-10-18
+\t> (x) Old step @2026-10-18
+(x) Closed
+\t> ( ) Left behind @2026-10-18
+( ) Water plants @2026-10-19
+(=) Call back @2026-10-19 08:15
+( ) Plan trip @2026-10-22 18:00
+( ) Far off @2026-11-30
+( ) Someday
+";
+
+fn agenda(days: u64) -> Agenda {
+    let action_list = parse(SOURCE);
+    build_agenda(&action_list, days, now())
+}
+
+fn names(entries: &[AgendaEntry]) -> Vec<&str> {
+    entries.iter().map(|entry| entry.name.as_str()).collect()
+}
+
+#[test]
+fn sorts_open_actions_into_buckets_by_do_date() {
+    let week = agenda(7);
+    assert_eq!(names(&week.overdue), ["Gather numbers"]);
+    // whole days come before the timed actions of the same day
+    assert_eq!(names(&week.today), ["Write docs", "Refactor parser"]);
+    assert_eq!(names(&week.tomorrow), ["Water plants", "Call back"]);
+    assert_eq!(names(&week.later), ["Plan trip"]);
+
+    let refactor = &week.today[1];
+    assert_eq!(refactor.path, [1, 0]);
+    assert_eq!(refactor.parent.as_deref(), Some("Project"));
+    assert!(refactor.timed);
+    assert!(!week.today[0].timed);
+}
+
+#[test]
+fn the_window_decides_how_far_ahead_to_look() {
+    let today = agenda(1);
+    assert_eq!(names(&today.overdue), ["Gather numbers"]);
+    assert_eq!(today.today.len(), 2);
+    assert!(today.tomorrow.is_empty() && today.later.is_empty());
+
+    let two_days = agenda(2);
+    assert_eq!(two_days.tomorrow.len(), 2);
+    assert!(two_days.later.is_empty());
+
+    assert_eq!(names(&agenda(60).later), ["Plan trip", "Far off"]);
+}
+
+#[test]
+fn renders_a_section_per_bucket() {
+    assert_eq!(
+        render_agenda(&agenda(7), now(), false),
+        "\
+Overdue
+  Fri Oct 16 2026 09:30  ( ) Gather numbers #0195
+
+Today Sun Oct 18
+         ( ) Write docs in Project
+  14:00  (-) Refactor parser in Project
+
+Tomorrow Mon Oct 19
+         ( ) Water plants
+  08:15  (=) Call back
+
+Later
+  Thu Oct 22 2026 18:00  ( ) Plan trip
+"
+    );
+    let colored = render_agenda(&agenda(1), now(), true);
+    assert!(colored.starts_with("\x1b[1;31mOverdue\x1b[0m\n"));
+    assert!(colored.contains("\x1b[2min Project\x1b[0m"));
+
+    assert_eq!(
+        render_agenda(&Agenda::default(), now(), false),
+        "nothing due\n"
+    );
+}
+
+#[test]
+fn serializes_every_bucket() {
+    let json = serde_json::to_value(agenda(1)).unwrap();
+    assert_eq!(json["tomorrow"], serde_json::json!([]));
+    assert_eq!(json["today"][1]["name"], "Refactor parser");
+    assert_eq!(json["today"][1]["parent"], "Project");
+    assert_eq!(json["today"][1]["timed"], true);
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use chrono::Duration;
+use cliche::archive::*;
+use cliche::dependencies::drop_blockers;
+use cliche::query::parse_duration;
+use common::now;
+use uuid::Uuid;
+
+const SOURCE: &str = "\
+(x) Old %2026-09-01 10:00 #01951111cfa6718db303d7107f4005b3
+\t> (_) Dropped
+( ) Open
+\t> (x) Done child %2026-01-01 10:00
+(x) Recent %2026-10-17 10:00
+(_) Undated
+";
+
+#[test]
+fn moves_fully_closed_root_subtrees_as_written() {
+    let archive = archive_actions(SOURCE, None, now()).unwrap();
+    assert_eq!(
+        archive.remaining,
+        "( ) Open\n\t> (x) Done child %2026-01-01 10:00\n"
+    );
+    assert_eq!(
+        archive.archived,
+        [
+            ArchivedAction {
+                month: "2026-09".to_string(),
+                name: "Old".to_string(),
+                text:
+                    "(x) Old %2026-09-01 10:00 #01951111cfa6718db303d7107f4005b3\n\t> (_) Dropped\n"
+                        .to_string(),
+                ids: vec![Uuid::parse_str("01951111cfa6718db303d7107f4005b3").unwrap()],
+            },
+            ArchivedAction {
+                month: "2026-10".to_string(),
+                name: "Recent".to_string(),
+                text: "(x) Recent %2026-10-17 10:00\n".to_string(),
+                ids: Vec::new(),
+            },
+            ArchivedAction {
+                month: "2026-10".to_string(),
+                name: "Undated".to_string(),
+                text: "(_) Undated\n".to_string(),
+                ids: Vec::new(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn keeps_what_was_finished_recently_or_at_an_unknown_time() {
+    let archive = archive_actions(SOURCE, Some(parse_duration("14d").unwrap()), now()).unwrap();
+    let names: Vec<&str> = archive
+        .archived
+        .iter()
+        .map(|action| action.name.as_str())
+        .collect();
+    assert_eq!(names, ["Old"]);
+    assert!(archive.remaining.contains("Recent"));
+    assert!(archive.remaining.contains("Undated"));
+
+    let nothing = archive_actions(SOURCE, Some(Duration::weeks(52)), now()).unwrap();
+    assert!(nothing.archived.is_empty());
+    assert_eq!(nothing.remaining, SOURCE);
+
+    // an age reaching back before any date there can be is an error rather than a panic
+    let ages = Duration::try_days(99_999_999_999).unwrap();
+    assert!(archive_actions(SOURCE, Some(ages), now()).is_err());
+}
+
+#[test]
+fn actions_stop_waiting_on_what_was_archived() {
+    let source = "\
+(x) Blocker %2026-10-01 10:00 #01951111cfa6718db303d7107f4005b4
+(=) Waits +work,blocked-by-01951111cfa6718db303d7107f4005b4
+(=) Also waits +blocked-by-01951111cfa6718db303d7107f4005b4,blocked-by-01951111cfa6718db303d7107f4005b5
+( ) Other #01951111cfa6718db303d7107f4005b5
+";
+    let archive = archive_actions(source, None, now()).unwrap();
+    let ids: Vec<Uuid> = archive
+        .archived
+        .iter()
+        .flat_map(|action| action.ids.clone())
+        .collect();
+    assert_eq!(
+        drop_blockers(&archive.remaining, &ids).unwrap(),
+        "\
+( ) Waits +work
+(=) Also waits +blocked-by-01951111cfa6718db303d7107f4005b5
+( ) Other #01951111cfa6718db303d7107f4005b5
+"
+    );
+}
+
+#[test]
+fn appends_to_the_end_of_an_archive_file() {
+    let archive = archive_actions(SOURCE, None, now()).unwrap();
+    let october: Vec<&ArchivedAction> = archive
+        .archived
+        .iter()
+        .filter(|action| action.month == "2026-10")
+        .collect();
+    assert_eq!(
+        append_to_archive("(x) Earlier %2026-10-02 08:00", &october),
+        "(x) Earlier %2026-10-02 08:00\n(x) Recent %2026-10-17 10:00\n(_) Undated\n"
+    );
+    assert_eq!(
+        append_to_archive("", &october),
+        "(x) Recent %2026-10-17 10:00\n(_) Undated\n"
+    );
+}
+
+#[test]
+fn archiving_twice_leaves_the_archive_alone() {
+    let dir = std::env::temp_dir().join(format!("cliche-archive-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("active.actions"), SOURCE).unwrap();
+    let archive_dir = dir.join("archive");
+    let patterns = [dir.join("**/*.actions").display().to_string()];
+    let excluded = [archive_dir.clone()];
+
+    // what `cliche archive` does with every file of the workspace
+    for _ in 0..2 {
+        let paths = cliche::workspace::resolve_entries(&patterns, &excluded).unwrap();
+        assert_eq!(paths, [dir.join("active.actions")]);
+        for path in paths {
+            let source = std::fs::read_to_string(&path).unwrap();
+            let archive = archive_actions(&source, None, now()).unwrap();
+            for action in &archive.archived {
+                std::fs::create_dir_all(&archive_dir).unwrap();
+                let archive_path = archive_dir.join(format!("{}.actions", action.month));
+                let existing = std::fs::read_to_string(&archive_path).unwrap_or_default();
+                std::fs::write(&archive_path, append_to_archive(&existing, &[action])).unwrap();
+            }
+            std::fs::write(&path, archive.remaining).unwrap();
+        }
+    }
+
+    let archived = std::fs::read_to_string(archive_dir.join("2026-09.actions")).unwrap();
+    assert!(archived.starts_with("(x) Old"), "{}", archived);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,16 @@
+//! Helpers the test files share, each one only uses some of them
+#![allow(dead_code)]
+
+use chrono::{DateTime, Local, TimeZone};
+use cliche::entities::ActionList;
+use cliche::get_action_list_struct;
+
+/// Parse an action file that is expected to be valid
+pub fn parse(source: &str) -> ActionList {
+    get_action_list_struct(&serde_json::json!({}), source).unwrap()
+}
+
+/// The moment every test treats as now, midday on a Sunday
+pub fn now() -> DateTime<Local> {
+    Local.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap()
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use cliche::dependencies::*;
+use cliche::entities::*;
+use common::parse;
+
+const SOURCE: &str = "\
+( ) Gather numbers #01951111cfa6718db303d7107f4005b3
+(=) Write report +work,blocked-by-01951111cfa6718db303d7107f4005b3 #01951112cfa6718db303d7107f4005b3
+\t> ( ) Outline
+(=) Waiting on a reply
+( ) Ship it +blocked-by-01951112cfa6718db303d7107f4005b3
+";
+
+#[test]
+fn blocked_by_references_are_kept_apart_from_contexts() {
+    let action_list = parse(SOURCE);
+    let report = &action_list[1].common;
+    assert_eq!(report.context_list, Some(vec!["@work".to_string()]));
+    assert_eq!(
+        report.blocked_by,
+        [uuid::Uuid::parse_str("01951111cfa6718db303d7107f4005b3").unwrap()]
+    );
+    assert_eq!(
+        report.to_string(),
+        "(=) Write report +work,blocked-by-01951111cfa6718db303d7107f4005b3 #01951112-cfa6-718d-b303-d7107f4005b3"
+    );
+}
+
+#[test]
+fn builds_a_graph_from_blocker_to_blocked() {
+    let graph = DependencyGraph::new(&parse(SOURCE));
+    let names: Vec<&str> = graph.nodes.iter().map(|node| node.name.as_str()).collect();
+    assert_eq!(names, ["Gather numbers", "Write report", "Ship it"]);
+    assert_eq!(graph.edges, [(0, 1), (1, 2)]);
+    assert!(graph.cycles().is_empty());
+    assert!(graph.check().is_ok());
+
+    let missing = DependencyGraph::new(&parse(
+        "( ) Lost +blocked-by-0195ffffcfa6718db303d7107f4005b3\n",
+    ));
+    assert_eq!(missing.missing.len(), 1);
+    assert!(missing.check().unwrap_err().contains("Lost"));
+}
+
+#[test]
+fn changes_are_only_held_up_by_what_they_bring_in() {
+    let before = DependencyGraph::new(&parse(
+        "( ) Lost +blocked-by-0195ffffcfa6718db303d7107f4005b3\n( ) Gather #01951111cfa6718db303d7107f4005b3\n",
+    ));
+    let added = DependencyGraph::new(&parse(
+        "( ) Lost +blocked-by-0195ffffcfa6718db303d7107f4005b3\n( ) Gather #01951111cfa6718db303d7107f4005b3\n( ) Report +blocked-by-01951111cfa6718db303d7107f4005b3\n",
+    ));
+    assert!(added.check().is_err());
+    assert_eq!(added.check_changes(&before), Ok(()));
+
+    let typo = DependencyGraph::new(&parse(
+        "( ) Lost +blocked-by-0195ffffcfa6718db303d7107f4005b3\n( ) Gather #01951111cfa6718db303d7107f4005b3\n( ) Report +blocked-by-0195eeeecfa6718db303d7107f4005b3\n",
+    ));
+    assert!(typo.check_changes(&before).unwrap_err().contains("Report"));
+
+    let circle = DependencyGraph::new(&parse(
+        "( ) Lost +blocked-by-0195ffffcfa6718db303d7107f4005b3\n( ) Gather +blocked-by-01951111cfa6718db303d7107f4005b3 #01951111cfa6718db303d7107f4005b3\n",
+    ));
+    assert!(
+        circle
+            .check_changes(&before)
+            .unwrap_err()
+            .contains("circle")
+    );
+    assert_eq!(circle.check_changes(&circle), Ok(()));
+}
+
+#[test]
+fn finds_cycles() {
+    let source = "\
+( ) A +blocked-by-0195000000007000800000000000000c #0195000000007000800000000000000a
+( ) B +blocked-by-0195000000007000800000000000000a #0195000000007000800000000000000b
+( ) C +blocked-by-0195000000007000800000000000000b #0195000000007000800000000000000c
+( ) D +blocked-by-0195000000007000800000000000000d #0195000000007000800000000000000d
+";
+    let graph = DependencyGraph::new(&parse(source));
+    let cycles: Vec<Vec<&str>> = graph
+        .cycles()
+        .iter()
+        .map(|cycle| {
+            cycle
+                .iter()
+                .map(|node| graph.nodes[*node].name.as_str())
+                .collect()
+        })
+        .collect();
+    assert_eq!(cycles, [vec!["A", "B", "C", "A"], vec!["D", "D"]]);
+    assert_eq!(
+        graph.check().unwrap_err(),
+        "actions cannot wait on each other in a circle: A -> B -> C -> A"
+    );
+}
+
+#[test]
+fn only_unblocked_open_actions_are_actionable() {
+    let action_list = parse(SOURCE);
+    let actionable: Vec<String> = actionable(&action_list)
+        .iter()
+        .map(|path| get_action(&action_list, path).unwrap().common.name.clone())
+        .collect();
+    // the outline waits along with the report it belongs to
+    assert_eq!(actionable, ["Gather numbers"]);
+    assert!(to_unblock(&action_list).is_empty());
+}
+
+#[test]
+fn finishing_a_blocker_frees_what_waited_on_it() {
+    let finished = SOURCE.replacen("( ) Gather", "(x) Gather", 1);
+    let action_list = parse(&finished);
+    assert_eq!(to_unblock(&action_list), [vec![1]]);
+
+    let updated = unblock_actions(&finished, &to_unblock(&action_list)).unwrap();
+    assert!(updated.contains("\n( ) Write report +work"));
+    // blocked on something outside the file stays blocked
+    assert!(updated.contains("(=) Waiting on a reply"));
+
+    let action_list = parse(&updated);
+    let names: Vec<&str> = actionable(&action_list)
+        .iter()
+        .map(|path| get_action(&action_list, path).unwrap().common.name.as_str())
+        .collect();
+    assert_eq!(names, ["Write report", "Outline"]);
+}
+
+#[test]
+fn draws_the_graph_as_dot_and_mermaid() {
+    let action_list = parse(&SOURCE.replacen("( ) Gather", "(x) Gather", 1));
+    assert_eq!(
+        render_graph(&action_list, "dot").unwrap(),
+        "\
+digraph actions {
+  rankdir=LR;
+  node [shape=box];
+  n0 [label=\"(x) Gather numbers #01951111\", style=dashed];
+  n1 [label=\"(=) Write report #01951112\"];
+  n2 [label=\"( ) Ship it\"];
+  n0 -> n1;
+  n1 -> n2;
+}
+"
+    );
+    let mermaid = render_graph(&action_list, "mermaid").unwrap();
+    assert!(mermaid.starts_with("flowchart LR\n"));
+    assert!(mermaid.contains("  n0[\"(x) Gather numbers #01951111\"]:::done\n"));
+    assert!(mermaid.contains("  n1 --> n2\n"));
+    assert!(render_graph(&action_list, "svg").is_err());
+}
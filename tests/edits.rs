@@ -0,0 +1,240 @@
+mod common;
+
+use chrono::{Local, TimeZone};
+use cliche::edits::*;
+use cliche::entities::*;
+use cliche::*;
+use common::parse;
+use uuid::Uuid;
+
+const PARENT_ID: &str = "01951111cfa6718db303d7107f4005b3";
+
+#[test]
+fn creates_root_actions_at_the_end_of_the_file() {
+    let common = CommonActionProperties {
+        name: "second".to_string(),
+        ..Default::default()
+    };
+
+    let updated = create_action("( ) first", &common, None).unwrap();
+
+    assert_eq!(updated, "( ) first\n( ) second\n");
+}
+
+#[test]
+fn creates_children_as_the_last_child_of_their_parent() {
+    let source = format!(
+        "( ) parent #{}\n\t> ( ) existing\n( ) next root\n",
+        PARENT_ID
+    );
+    let common = CommonActionProperties {
+        name: "new child".to_string(),
+        priority: Some(2),
+        context_list: Some(vec!["@home".to_string(), "@work".to_string()]),
+        do_date_time: Some(Local.with_ymd_and_hms(2026, 10, 20, 9, 0, 0).unwrap()),
+        ..Default::default()
+    };
+
+    let updated =
+        create_action(&source, &common, Some(Uuid::parse_str(PARENT_ID).unwrap())).unwrap();
+
+    assert_eq!(
+        updated,
+        format!(
+            "( ) parent #{}\n\t> ( ) existing\n\t> ( ) new child !2 +home,work @2026-10-20 09:00\n( ) next root\n",
+            PARENT_ID
+        )
+    );
+    let action_list = parse(&updated);
+    assert_eq!(action_list[0].children[1].common, common);
+    assert_eq!(action_list[1].common.name, "next root");
+}
+
+#[test]
+fn refuses_unknown_parents_and_unwritable_names() {
+    let common = CommonActionProperties {
+        name: "child".to_string(),
+        ..Default::default()
+    };
+    let missing = create_action("( ) parent\n", &common, Some(Uuid::now_v7())).unwrap_err();
+    assert!(missing.contains("no action has the id"));
+
+    let marker = CommonActionProperties {
+        name: "costs $5".to_string(),
+        ..Default::default()
+    };
+    assert!(create_action("", &marker, None).unwrap_err().contains('$'));
+
+    // a line break would leave the rest of the name reading as an action of its own
+    for (name, description) in [("a\nb", None), ("a\rb", None), ("Rent", Some("due\nsoon"))] {
+        let update = ActionUpdate {
+            name: Some(name.to_string()),
+            description: description.map(str::to_string),
+            ..Default::default()
+        };
+        let error = update_action("( ) Rent\n", "Rent", &update).unwrap_err();
+        assert!(error.contains("one line"), "{}", error);
+    }
+}
+
+#[test]
+fn updates_only_the_properties_that_changed() {
+    let source =
+        "( ) keep me   !1\n( ) Weekly review !2 +home @2019-01-01 12:01AM D120 RW Mon 01:05PM\n";
+    let update = ActionUpdate {
+        name: Some("Weekly planning".to_string()),
+        state: Some(ActionState::InProgress),
+        description: Some("with coffee".to_string()),
+        add_contexts: vec!["office".to_string()],
+        remove_contexts: vec!["@home".to_string()],
+        ..Default::default()
+    };
+
+    let updated = update_action(source, "Weekly review", &update).unwrap();
+
+    assert_eq!(
+        updated,
+        "( ) keep me   !1\n(-) Weekly planning $with coffee !2 +office @2019-01-01 12:01AM D120 RW Mon 01:05PM\n"
+    );
+}
+
+#[test]
+fn updates_multi_line_actions_in_place() {
+    let source = &tree_sitter_actions::get_test_data()["actions"]["with_everything"]["content"];
+    let update = ActionUpdate {
+        priority: Some(3),
+        remove_contexts: vec!["test_context".to_string(), "another-context".to_string()],
+        ..Default::default()
+    };
+
+    let updated = update_action(source, "Mega Action", &update).unwrap();
+
+    assert_eq!(
+        updated,
+        source
+            .replace("!1 \n", "!3 \n")
+            .replace(" \n+test_context,another-context", "")
+    );
+    let action_list = parse(&updated);
+    assert_eq!(action_list[0].common.priority, Some(3));
+    assert_eq!(action_list[0].common.context_list, None);
+    assert_eq!(action_list[0].children.len(), 1);
+}
+
+#[test]
+fn update_needs_exactly_one_match() {
+    let source = "( ) twin\n( ) twin\n";
+    let update = ActionUpdate {
+        priority: Some(1),
+        ..Default::default()
+    };
+
+    assert!(
+        update_action(source, "twin", &update)
+            .unwrap_err()
+            .contains("2 actions")
+    );
+    assert!(
+        update_action(source, "triplet", &update)
+            .unwrap_err()
+            .contains("no action")
+    );
+}
+
+const FAMILY: &str =
+    "( ) keep\n( ) parent\n\t> ( ) child\n\t\t>> ( ) grandchild\n\t> ( ) second child\n( ) last\n";
+
+#[test]
+fn delete_refuses_to_orphan_children_by_default() {
+    let error = delete_action(FAMILY, "parent", ChildHandling::Refuse).unwrap_err();
+    assert!(error.contains("2 children"));
+
+    let leaf = delete_action(FAMILY, "grandchild", ChildHandling::Refuse).unwrap();
+    assert_eq!(
+        leaf,
+        "( ) keep\n( ) parent\n\t> ( ) child\n\t> ( ) second child\n( ) last\n"
+    );
+}
+
+#[test]
+fn delete_removes_the_whole_subtree() {
+    let updated = delete_action(FAMILY, "parent", ChildHandling::Subtree).unwrap();
+    assert_eq!(updated, "( ) keep\n( ) last\n");
+}
+
+#[test]
+fn delete_promotes_children_into_the_parents_place() {
+    let updated = delete_action(FAMILY, "parent", ChildHandling::Promote).unwrap();
+
+    assert_eq!(
+        updated,
+        "( ) keep\n( ) child\n\t> ( ) grandchild\n( ) second child\n( ) last\n"
+    );
+    let names_and_depths: Vec<(String, usize)> = depth_first(&parse(&updated))
+        .map(|action| (action.common.name.clone(), action.depth))
+        .collect();
+    assert_eq!(names_and_depths[2], ("grandchild".to_string(), 1));
+}
+
+#[test]
+fn diffs_show_removed_lines_with_context() {
+    let updated = delete_action(FAMILY, "parent", ChildHandling::Subtree).unwrap();
+
+    let diff = cliche::diff::unified_diff(FAMILY, &updated, "a/active", "b/active");
+
+    assert_eq!(
+        diff,
+        "--- a/active\n+++ b/active\n@@ -1,6 +1,2 @@\n ( ) keep\n-( ) parent\n-\t> ( ) child\n-\t\t>> ( ) grandchild\n-\t> ( ) second child\n ( ) last\n"
+    );
+    assert_eq!(cliche::diff::unified_diff(FAMILY, FAMILY, "a", "b"), "");
+}
+
+#[test]
+fn json_diff_names_the_path_of_each_difference() {
+    let expected = serde_json::json!([{ "name": "a", "children": [1, 2], "gone": true }]);
+    let actual = serde_json::json!([{ "name": "b", "children": [1], "new": null }]);
+
+    assert_eq!(
+        cliche::diff::json_diff(&expected, &actual),
+        [
+            "$[0].children[1]: missing, expected 2",
+            "$[0].gone: missing, expected true",
+            "$[0].name: expected \"a\", found \"b\"",
+            "$[0].new: unexpected null",
+        ]
+    );
+    assert!(cliche::diff::json_diff(&expected, &expected).is_empty());
+}
+
+#[test]
+fn comments_stay_out_of_names_and_where_they_were() {
+    let source = "( ) One\n// note about two\n( ) Two !1\n";
+    let names: Vec<String> = parse(source)
+        .iter()
+        .map(|action| action.common.name.clone())
+        .collect();
+    assert_eq!(names, ["One", "Two"]);
+    let parsed = get_action_list_lenient(&serde_json::json!({}), source).unwrap();
+    assert!(parsed.diagnostics.is_empty());
+
+    let update = ActionUpdate {
+        name: Some("Uno".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        update_action(source, "One", &update).unwrap(),
+        "( ) Uno\n// note about two\n( ) Two !1\n"
+    );
+    let completed = cliche::transitions::transition_action(
+        source,
+        "One",
+        ActionState::Completed,
+        cliche::transitions::TransitionPolicy::None,
+        Local.with_ymd_and_hms(2026, 10, 18, 9, 0, 0).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        completed,
+        "(x) One %2026-10-18 09:00\n// note about two\n( ) Two !1\n"
+    );
+}
@@ -0,0 +1,93 @@
+use chrono::DateTime;
+use cliche::diff::json_diff;
+use cliche::*;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use tree_sitter_actions::get_test_data;
+
+// every fixture the grammar ships is parsed and compared with the JSON we expect for it, stored in
+// tests/snapshots/<category>/<name>.json. Run with UPDATE_SNAPSHOTS=1 to write them afresh after a
+// deliberate change, then review the diff like any other
+fn snapshot_path(category: &str, name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(category)
+        .join(format!("{}.json", name))
+}
+
+// dates are written without a timezone and read in the local one, so the offset is dropped to keep
+// snapshots the same wherever the tests run
+fn without_offsets(value: Value) -> Value {
+    match value {
+        Value::String(text) => match DateTime::parse_from_rfc3339(&text) {
+            Ok(date) => Value::String(date.naive_local().format("%Y-%m-%dT%H:%M:%S").to_string()),
+            Err(_) => Value::String(text),
+        },
+        Value::Array(items) => Value::Array(items.into_iter().map(without_offsets).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, without_offsets(value)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+#[test]
+fn every_fixture_matches_its_snapshot() {
+    let update = std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|value| value == "1");
+    let mut failures = Vec::new();
+    let mut fixture_count = 0;
+
+    for (category, fixtures) in &get_test_data() {
+        for (name, fixture) in fixtures {
+            fixture_count += 1;
+            let label = format!("{}/{}", category, name);
+            let actual = match get_action_list(&serde_json::json!({}), fixture["content"].clone()) {
+                Ok(actual) => without_offsets(actual),
+                Err(e) => {
+                    failures.push(format!(
+                        "{} ({}) does not parse:\n{}",
+                        label, fixture["description"], e
+                    ));
+                    continue;
+                }
+            };
+
+            let path = snapshot_path(category, name);
+            let expected = fs::read_to_string(&path)
+                .ok()
+                .and_then(|json| serde_json::from_str::<Value>(&json).ok());
+            match expected {
+                Some(expected) if !update => {
+                    let differences = json_diff(&expected, &actual);
+                    if !differences.is_empty() {
+                        failures.push(format!(
+                            "{} ({}) differs from {}:\n  {}",
+                            label,
+                            fixture["description"],
+                            path.display(),
+                            differences.join("\n  ")
+                        ));
+                    }
+                }
+                // a plain test run never writes into the source tree
+                None if !update => failures.push(format!(
+                    "{} has no snapshot at {}, run with UPDATE_SNAPSHOTS=1 to write it",
+                    label,
+                    path.display()
+                )),
+                _ => {
+                    fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    let json = serde_json::to_string_pretty(&actual).unwrap();
+                    fs::write(&path, format!("{}\n", json)).unwrap();
+                }
+            }
+        }
+    }
+
+    assert!(fixture_count > 0, "the grammar crate shipped no fixtures");
+    assert!(failures.is_empty(), "\n{}\n", failures.join("\n\n"));
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use cliche::format::*;
+use common::parse;
+use tree_sitter_actions::get_test_data;
+
+#[test]
+fn formatting_keeps_the_actions_of_every_fixture() {
+    for (category, fixtures) in &get_test_data() {
+        for (name, fixture) in fixtures {
+            let source = &fixture["content"];
+            let formatted = format_source(source).unwrap();
+
+            assert_eq!(parse(&formatted), parse(source), "{}/{}", category, name);
+            assert!(is_formatted(&formatted).unwrap(), "{}/{}", category, name);
+        }
+    }
+}
+
+#[test]
+fn writes_each_action_on_one_canonical_line() {
+    let source = "\
+(x) Mega Action
+$ descriptions
+!1
++a, b
+@2019-01-01 12:01AM   D120 RW Mon Tue 01:05PM
+#01951111cfa6718db303d7107f4005b3
+* Test Story
+  >   ( )   Child   !2
+";
+    assert_eq!(
+        format_source(source).unwrap(),
+        "\
+(x) Mega Action $descriptions !1 +a,b @2019-01-01 12:01AM D120 RW Mon Tue 01:05PM #01951111cfa6718db303d7107f4005b3 *Test Story
+\t> ( ) Child !2
+"
+    );
+}
+
+#[test]
+fn keeps_comments_and_single_blank_lines_between_groups() {
+    let source = "\n\n// work
+( ) One !1
+\t> ( ) Child
+  // later
+
+
+( ) Two
+
+
+";
+    assert_eq!(
+        format_source(source).unwrap(),
+        "\
+// work
+( ) One !1
+\t> ( ) Child
+  // later
+
+( ) Two
+"
+    );
+}
+
+#[test]
+fn refuses_files_that_do_not_parse() {
+    assert!(format_source("( ) One\n# not an id\n").is_err());
+    assert!(!is_formatted("( ) One  !1\n").unwrap());
+    assert_eq!(format_source("").unwrap(), "");
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use cliche::ical::*;
+use common::{now, parse};
+
+const SOURCE: &str = "\
+( ) Project $plans; notes, more !3 +office,home @2026-10-20 #01951111cfa6718db303d7107f4005b3
+\t> (-) Draft @2026-10-19 14:30 D30 RW Mon 02:30PM #01951111cfa6718db303d7107f4005b4
+\t> (x) Outline !12 %2026-10-17 10:00
+(=) Ship +blocked-by-01951111cfa6718db303d7107f4005b4 #01951111cfa6718db303d7107f4005b5
+(_) Dropped
+";
+
+fn utc(date_time: DateTime<Local>) -> String {
+    date_time
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+fn todos() -> Vec<Vec<String>> {
+    let action_list = parse(SOURCE);
+    let ics = to_ics(&action_list, now());
+    ics.replace("\r\n ", "")
+        .split("BEGIN:VTODO\r\n")
+        .skip(1)
+        .map(|todo| {
+            todo.split("\r\n")
+                .take_while(|line| *line != "END:VTODO")
+                .map(str::to_string)
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn wraps_every_action_in_a_calendar() {
+    let action_list = parse(SOURCE);
+    let ics = to_ics(&action_list, now());
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    // closed actions are there too, so calendars can tick them off
+    assert_eq!(ics.matches("BEGIN:VTODO").count(), 5);
+    assert!(!ics.replace("\r\n", "").contains('\n'));
+    assert!(export(&action_list, "vcs", now()).is_err());
+}
+
+#[test]
+fn maps_the_properties_of_an_action() {
+    let todos = todos();
+    let project = &todos[0];
+    assert_eq!(project[0], "UID:01951111-cfa6-718d-b303-d7107f4005b3");
+    assert_eq!(project[1], format!("DTSTAMP:{}", utc(now())));
+    assert!(project[2].starts_with("CREATED:2025"));
+    assert_eq!(
+        project[3..9],
+        [
+            "SUMMARY:Project",
+            r"DESCRIPTION:plans\; notes\, more",
+            "STATUS:NEEDS-ACTION",
+            "PRIORITY:3",
+            "CATEGORIES:office,home",
+            "DTSTART;VALUE=DATE:20261020",
+        ]
+    );
+    assert!(!project.iter().any(|line| line.starts_with("DUE")));
+
+    let draft = &todos[1];
+    assert!(draft.contains(&"STATUS:IN-PROCESS".to_string()));
+    let start = Local.with_ymd_and_hms(2026, 10, 19, 14, 30, 0).unwrap();
+    let end_of_day = Local.with_ymd_and_hms(2026, 10, 20, 0, 0, 0).unwrap();
+    assert!(draft.contains(&format!("DTSTART:{}", utc(start))));
+    assert!(draft.contains(&format!("DUE:{}", utc(end_of_day))));
+    assert!(draft.contains(&"RRULE:FREQ=WEEKLY;BYDAY=MO".to_string()));
+
+    let outline = &todos[2];
+    let completed = Local.with_ymd_and_hms(2026, 10, 17, 10, 0, 0).unwrap();
+    assert!(outline.contains(&"STATUS:COMPLETED".to_string()));
+    assert!(outline.contains(&format!("COMPLETED:{}", utc(completed))));
+    // iCalendar has nothing below 9
+    assert!(outline.contains(&"PRIORITY:9".to_string()));
+
+    assert!(todos[3].contains(&"STATUS:NEEDS-ACTION".to_string()));
+    assert!(todos[4].contains(&"STATUS:CANCELLED".to_string()));
+    assert!(!todos[4].iter().any(|line| line.starts_with("DTSTART")));
+}
+
+#[test]
+fn links_parents_children_and_blockers() {
+    let todos = todos();
+    let related = |todo: &[String]| -> Vec<String> {
+        todo.iter()
+            .filter(|line| line.starts_with("RELATED-TO"))
+            .cloned()
+            .collect()
+    };
+    let outline_uid = todos[2][0].trim_start_matches("UID:").to_string();
+    assert_eq!(
+        related(&todos[0]),
+        [
+            "RELATED-TO;RELTYPE=CHILD:01951111-cfa6-718d-b303-d7107f4005b4".to_string(),
+            format!("RELATED-TO;RELTYPE=CHILD:{}", outline_uid),
+        ]
+    );
+    assert_eq!(
+        related(&todos[2]),
+        ["RELATED-TO;RELTYPE=PARENT:01951111-cfa6-718d-b303-d7107f4005b3"]
+    );
+    assert_eq!(
+        related(&todos[3]),
+        ["RELATED-TO;RELTYPE=DEPENDS-ON:01951111-cfa6-718d-b303-d7107f4005b4"]
+    );
+}
+
+#[test]
+fn gives_actions_without_an_id_a_uid_and_folds_long_lines() {
+    let action_list = parse(SOURCE);
+    let outline = &action_list[0].children[1];
+    assert_eq!(uid(outline, &[0, 1]), uid(outline, &[0, 1]));
+    assert_ne!(uid(outline, &[0, 1]), uid(outline, &[1, 0]));
+    assert!(uid(outline, &[0, 1]).ends_with("@cliche"));
+
+    let long = format!("( ) {}\n", "é".repeat(60));
+    let action_list = parse(&long);
+    let ics = to_ics(&action_list, now());
+    for line in ics.split("\r\n") {
+        assert!(line.len() <= 75, "{}", line);
+    }
+    assert!(
+        ics.replace("\r\n ", "")
+            .contains(&format!("SUMMARY:{}", "é".repeat(60)))
+    );
+}
+
+#[test]
+fn says_which_repeats_lose_their_own_time() {
+    let source = "\
+( ) Standup !0 @2026-10-19 08:00 RDa 09:00
+( ) Review @2026-10-19 09:00 RW Mon 09:00
+( ) Bins @2026-10-20 RW Tue
+(x) Retro @2026-10-12 08:00 RW Mon 09:00 %2026-10-12 10:00
+";
+    let action_list = parse(source);
+    let moved: Vec<bool> = action_list.iter().map(moves_repeat_time).collect();
+    assert_eq!(moved, [true, false, false, false]);
+
+    // 0 is no priority at all in iCalendar
+    let standup = vtodo_properties(&action_list, &[0], &action_list[0], now());
+    assert!(standup.contains(&"PRIORITY:1".to_string()));
+}
+
+// the time of day of a repeat has to come out the same wherever the export runs, so this runs
+// itself again with the clock of a place well away from UTC
+#[test]
+fn repeats_keep_their_time_away_from_utc() {
+    if std::env::var("TZ").as_deref() != Ok("Asia/Kolkata") {
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["repeats_keep_their_time_away_from_utc", "--exact"])
+            .env("TZ", "Asia/Kolkata")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        return;
+    }
+
+    let source = "\
+( ) Standup @2026-10-19 09:00 RDa
+( ) Bins @2026-10-20 RW Tue
+(x) Review @2026-10-12 09:00 RW Mon %2026-10-12 10:00
+";
+    let action_list = parse(source);
+    let ics = to_ics(&action_list, now());
+    assert!(
+        ics.contains("DTSTART:20261019T033000Z\r\nDUE:20261019T183000Z\r\nRRULE:FREQ=DAILY\r\n")
+    );
+    assert!(ics.contains("DTSTART;VALUE=DATE:20261020\r\nRRULE:FREQ=WEEKLY;BYDAY=TU\r\n"));
+    assert_eq!(ics.matches("RRULE").count(), 2);
+    assert!(!ics.contains("BYHOUR"));
+}
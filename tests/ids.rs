@@ -0,0 +1,111 @@
+mod common;
+
+use cliche::entities::*;
+use cliche::ids::*;
+use cliche::selector::*;
+use common::parse;
+use uuid::Uuid;
+
+const SOURCE: &str = "\
+( ) Project !1 +work #01951111cfa6718db303d7107f4005b3
+\t> ( ) Subtask $details @2026-10-20 09:00 D30
+\t\t>> (x) Deeper %2026-10-10 10:00
+( ) Weekly review @2026-10-20 09:00 D120 RW Mon 01:05PM
+";
+
+fn counter() -> impl FnMut() -> Uuid {
+    let mut next = 0;
+    move || {
+        next += 1;
+        Uuid::from_u128(0x0195_0000_0000_7000_8000_0000_0000_0000 + next)
+    }
+}
+
+#[test]
+fn gives_every_action_without_an_id_a_new_one() {
+    let (updated, count) = assign_ids(SOURCE, counter()).unwrap();
+    assert_eq!(count, 3);
+    assert_eq!(
+        updated,
+        "\
+( ) Project !1 +work #01951111cfa6718db303d7107f4005b3
+\t> ( ) Subtask $details @2026-10-20 09:00 D30 #01950000-0000-7000-8000-000000000001
+\t\t>> (x) Deeper %2026-10-10 10:00 #01950000-0000-7000-8000-000000000002
+( ) Weekly review @2026-10-20 09:00 D120 RW Mon 01:05PM #01950000-0000-7000-8000-000000000003
+"
+    );
+
+    let ids: Vec<Uuid> = depth_first(&parse(&updated))
+        .map(|action| action.common.id.unwrap())
+        .collect();
+    assert_eq!(ids.len(), 4);
+}
+
+#[test]
+fn assigned_ids_round_trip_through_display() {
+    let (updated, _) = assign_ids(SOURCE, Uuid::now_v7).unwrap();
+    let action_list = parse(&updated);
+    let written: String = action_list
+        .iter()
+        .map(|action| action.to_string())
+        .collect();
+    assert_eq!(parse(&written), action_list);
+}
+
+#[test]
+fn leaves_files_that_already_have_ids_alone() {
+    let (updated, _) = assign_ids(SOURCE, counter()).unwrap();
+    let (again, count) = assign_ids(&updated, counter()).unwrap();
+    assert_eq!(count, 0);
+    assert_eq!(again, updated);
+}
+
+#[test]
+fn new_ids_are_time_ordered() {
+    let (updated, _) = assign_ids(SOURCE, Uuid::now_v7).unwrap();
+    let ids: Vec<Uuid> = depth_first(&parse(&updated))
+        .skip(1)
+        .map(|action| action.common.id.unwrap())
+        .collect();
+    assert!(ids.iter().all(|id| id.get_version_num() == 7));
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn prefixes_are_the_shortest_that_stay_unique() {
+    let action_list = parse(
+        "\
+( ) One #01951111cfa6718db303d7107f4005b3
+( ) Two #01951112cfa6718db303d7107f4005b3
+( ) Three #0a951111cfa6718db303d7107f4005b3
+( ) Four
+",
+    );
+    let prefixes = shortest_prefixes(&action_list);
+    let prefix = |index: usize| prefixes[&action_list[index].common.id.unwrap()].clone();
+
+    assert_eq!(prefixes.len(), 3);
+    assert_eq!(prefix(0), "01951111");
+    assert_eq!(prefix(1), "01951112");
+    assert_eq!(prefix(2), "0a95");
+
+    for index in 0..3 {
+        let selector = format!("#{}", prefix(index));
+        assert_eq!(select(&action_list, &selector).unwrap(), [vec![index]]);
+    }
+}
+
+#[test]
+fn shown_numbers_last_until_the_file_changes() {
+    let action_list = parse(SOURCE);
+    let session = Session::new(SOURCE, &action_list, &[vec![1], vec![0, 0]]);
+
+    assert_eq!(session.translate(SOURCE, "1").unwrap(), "4");
+    assert_eq!(session.translate(SOURCE, "2").unwrap(), "2");
+    assert_eq!(select_one(&action_list, "4").unwrap(), vec![1]);
+    assert_eq!(session.translate(SOURCE, "~week").unwrap(), "~week");
+    assert!(session.translate(SOURCE, "3").is_err());
+
+    let changed = SOURCE.replace("Subtask", "Renamed");
+    assert!(session.translate(&changed, "1").is_err());
+}
@@ -1,10 +1,11 @@
+use chrono::{FixedOffset, Local, TimeZone};
 use cliche::entities::*;
 use cliche::*;
 use tree_sitter_actions::get_test_data;
 
 // here, we are making use of the automatically generated test case file which we dynamically build
-// at build time, we are only doing one test for now but the plan is that all tests covered within
-// the primary structure of treesitter will be covered here to ensure parity
+// at build time, this one spells the expected struct out by hand while tests/fixtures.rs checks
+// every fixture against its snapshot to ensure parity
 #[test]
 fn convert_basic_action_from_examples() {
     let test_action = get_test_data()["children"]["minimal"]["content"].clone();
@@ -12,7 +13,7 @@ fn convert_basic_action_from_examples() {
     let test_config = serde_json::from_str(test_config_str).expect("unable to conver");
 
     let derived_struct = get_action_list_struct(&test_config, &test_action).unwrap();
-    let expected_struct = vec![RootAction {
+    let expected_struct = vec![Action {
         common: CommonActionProperties {
             state: ActionState::NotStarted,
             name: "test".to_string(),
@@ -22,10 +23,203 @@ fn convert_basic_action_from_examples() {
             id: None,
             do_date_time: None,
             completed_date_time: None,
+            recurrence: None,
+            blocked_by: vec![],
         },
         story: None,
-        children: None,
+        children: vec![],
+        depth: 0,
     }];
 
     assert_eq!(derived_struct, expected_struct);
 }
+
+// every dated fixture should keep its dates through a parse -> display -> parse round trip
+#[test]
+fn dates_round_trip_through_display() {
+    let test_config = serde_json::json!({});
+
+    for (name, fixture) in &get_test_data()["dates"] {
+        let parsed = get_action_list_struct(&test_config, &fixture["content"]).unwrap();
+        let common = &parsed[0].common;
+        assert!(
+            common.do_date_time.is_some() || common.completed_date_time.is_some(),
+            "{} lost its date on read",
+            name
+        );
+
+        let rendered = format!("{}\n", parsed[0]);
+        let reparsed = get_action_list_struct(&test_config, &rendered).unwrap();
+        assert_eq!(
+            reparsed, parsed,
+            "{} did not round trip as {}",
+            name, rendered
+        );
+    }
+}
+
+#[test]
+fn reads_do_and_completed_dates_from_examples() {
+    let test_config = serde_json::json!({});
+    let dates = &get_test_data()["dates"];
+
+    let do_date = get_action_list_struct(&test_config, &dates["with_do_date"]["content"]).unwrap();
+    assert_eq!(
+        do_date[0].common.do_date_time,
+        Some(Local.with_ymd_and_hms(2019, 1, 1, 0, 1, 0).unwrap())
+    );
+
+    let completed =
+        get_action_list_struct(&test_config, &dates["with_completed_date"]["content"]).unwrap();
+    assert_eq!(
+        completed[0].common.completed_date_time,
+        Some(Local.with_ymd_and_hms(2019, 1, 1, 0, 1, 0).unwrap())
+    );
+}
+
+#[test]
+fn parses_date_only_time_and_offset_forms() {
+    assert_eq!(
+        parse_action_date_time("2026-10-20").unwrap(),
+        Local.with_ymd_and_hms(2026, 10, 20, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+        parse_action_date_time("2026-10-20 09:00").unwrap(),
+        Local.with_ymd_and_hms(2026, 10, 20, 9, 0, 0).unwrap()
+    );
+    assert_eq!(
+        parse_action_date_time("2026-10-20 01:05 PM").unwrap(),
+        Local.with_ymd_and_hms(2026, 10, 20, 13, 5, 0).unwrap()
+    );
+    assert_eq!(
+        parse_action_date_time("2026-10-20T09:00+02:00").unwrap(),
+        FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2026, 10, 20, 9, 0, 0)
+            .unwrap()
+    );
+
+    let error = parse_action_date_time("next tuesday").unwrap_err();
+    assert!(error.contains("next tuesday"));
+}
+
+#[test]
+fn nests_every_level_of_children_into_one_tree() {
+    let test_config = serde_json::json!({});
+    let content = &get_test_data()["children"]["with_children"]["content"];
+    let action_list = get_action_list_struct(&test_config, content).unwrap();
+
+    assert_eq!(action_list.len(), 1);
+    let names_and_depths: Vec<(&str, usize)> = depth_first(&action_list)
+        .map(|action| (action.common.name.as_str(), action.depth))
+        .collect();
+    assert_eq!(
+        names_and_depths,
+        vec![
+            ("root test", 0),
+            ("child action", 1),
+            ("grandchild action", 2),
+            ("great grandchild action", 3),
+            ("double-great grandchild action", 4),
+            ("Leaf action", 5),
+        ]
+    );
+    assert!(depth_first(&action_list).last().unwrap().is_leaf());
+}
+
+#[test]
+fn serializes_children_under_their_per_level_names() {
+    let test_config = serde_json::json!({});
+    let content = &get_test_data()["children"]["with_children"]["content"];
+    let action_list = get_action_list_struct(&test_config, content).unwrap();
+
+    let value = serde_json::to_value(&action_list).unwrap();
+    let leaf = &value[0]["children"][0]["grandchildren"][0]["great_grandchildren"][0]["great_great_grandchildren"]
+        [0]["leaf_children"][0];
+    assert_eq!(leaf["common"]["name"], "Leaf action");
+    assert_eq!(leaf.as_object().unwrap().len(), 1);
+    assert_eq!(value[0]["story"], serde_json::Value::Null);
+
+    let read_back: ActionList = serde_json::from_value(value).unwrap();
+    assert_eq!(read_back, action_list);
+}
+
+#[test]
+fn walks_the_tree_in_every_direction() {
+    let test_config = serde_json::json!({});
+    let content = "( ) a\n> ( ) b\n>> ( ) d\n> ( ) c\n( ) e\n";
+    let action_list = get_action_list_struct(&test_config, content).unwrap();
+    let names = |actions: Vec<&Action>| -> Vec<String> {
+        actions
+            .iter()
+            .map(|action| action.common.name.clone())
+            .collect()
+    };
+
+    assert_eq!(
+        names(depth_first(&action_list).collect()),
+        ["a", "b", "d", "c", "e"]
+    );
+    assert_eq!(
+        names(breadth_first(&action_list).collect()),
+        ["a", "e", "b", "c", "d"]
+    );
+    assert_eq!(
+        names(action_list[0].breadth_first().collect()),
+        ["a", "b", "c", "d"]
+    );
+    assert_eq!(
+        names(ancestors(&action_list, &[0, 0, 0]).collect()),
+        ["b", "a"]
+    );
+    assert_eq!(names(siblings(&action_list, &[0, 1]).collect()), ["b"]);
+    assert_eq!(names(siblings(&action_list, &[1]).collect()), ["a"]);
+
+    let paths: Vec<ActionPath> = walk(&action_list)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    assert_eq!(
+        paths,
+        [vec![0], vec![0, 0], vec![0, 0, 0], vec![0, 1], vec![1]]
+    );
+    assert_eq!(
+        get_action(&action_list, &[0, 0, 0]).unwrap().common.name,
+        "d"
+    );
+}
+
+#[test]
+fn strict_parse_reports_malformed_lines_instead_of_panicking() {
+    let test_config = serde_json::json!({});
+    let error =
+        get_action_list_struct(&test_config, "( ) good\n(? bad\n( ) also good\n").unwrap_err();
+
+    assert_eq!(error.diagnostics.len(), 1);
+    let diagnostic = &error.diagnostics[0];
+    assert_eq!(diagnostic.severity, diagnostics::Severity::Error);
+    assert_eq!((diagnostic.line, diagnostic.column), (2, 1));
+    assert_eq!((diagnostic.start_byte, diagnostic.end_byte), (9, 15));
+    assert_eq!(diagnostic.node_kind, "action_list");
+    assert!(diagnostic.message.contains("(? bad"));
+}
+
+#[test]
+fn lenient_parse_keeps_what_it_can_read() {
+    let test_config = serde_json::json!({});
+    let parsed = get_action_list_lenient(
+        &test_config,
+        "( ) good\n(? bad\n( ) parent\n> ( ) broken child @2019-13-45\n> ( ) fine child\n",
+    )
+    .unwrap();
+
+    let names: Vec<&str> = depth_first(&parsed.actions)
+        .map(|action| action.common.name.as_str())
+        .collect();
+    assert_eq!(names, ["good", "parent", "fine child"]);
+
+    let lines: Vec<usize> = parsed.diagnostics.iter().map(|d| d.line).collect();
+    assert_eq!(lines, [2, 4]);
+    assert_eq!(parsed.diagnostics[1].node_kind, "do_date_or_time");
+    assert!(parsed.diagnostics[1].message.contains("do-date"));
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use cliche::diagnostics::Severity;
+use cliche::lint::*;
+use common::now;
+
+const SOURCE: &str = "\
+( ) One !9 +work,gym #01951111cfa6718db303d7107f4005b3
+(x) Done
+\t> ( ) Open child @2020-01-01 10:00
+( ) Reopened %2026-01-01 10:00 #01951111cfa6718db303d7107f4005b3
+( ) a
+> ( ) b
+>> ( ) c
+>>> ( ) d
+>>>> ( ) e
+>>>>> ( ) f
+>>>>>> ( ) g
+";
+
+fn rules(findings: &[Finding]) -> Vec<(usize, &str)> {
+    findings
+        .iter()
+        .map(|finding| (finding.diagnostic.line, finding.rule.as_str()))
+        .collect()
+}
+
+#[test]
+fn every_rule_reports_where_the_problem_is() {
+    let config = LintConfig {
+        contexts: Some(vec!["work".to_string()]),
+        ..LintConfig::default()
+    };
+    let findings = lint(SOURCE, &config, now()).unwrap();
+
+    assert_eq!(
+        rules(&findings),
+        [
+            (1, "priority-range"),
+            (1, "unknown-context"),
+            (2, "completed-without-date"),
+            (3, "open-under-closed"),
+            (3, "past-do-date"),
+            (4, "completed-date-on-open"),
+            (4, "duplicate-id"),
+            (11, "too-deep"),
+        ]
+    );
+    assert_eq!(findings[0].diagnostic.column, 9);
+    assert_eq!(findings[6].diagnostic.severity, Severity::Error);
+}
+
+#[test]
+fn levels_come_from_the_config() {
+    let opts = serde_json::json!({
+        "lint": {
+            "max_depth": 2,
+            "priority_max": 9,
+            "rules": { "past-do-date": "error", "completed-without-date": "off" },
+        }
+    });
+    let config = LintConfig::from_opts(&opts).unwrap();
+    let findings = lint(SOURCE, &config, now()).unwrap();
+    let found = rules(&findings);
+
+    assert!(!found.iter().any(|(_, rule)| *rule == "priority-range"));
+    assert!(
+        !found
+            .iter()
+            .any(|(_, rule)| *rule == "completed-without-date")
+    );
+    assert!(!found.iter().any(|(_, rule)| *rule == "unknown-context"));
+    assert_eq!(
+        found.iter().filter(|(_, rule)| *rule == "too-deep").count(),
+        4
+    );
+    let past = findings
+        .iter()
+        .find(|finding| finding.rule == "past-do-date")
+        .unwrap();
+    assert_eq!(past.diagnostic.severity, Severity::Error);
+
+    let typo = serde_json::json!({ "lint": { "rules": { "past-due": "off" } } });
+    assert!(LintConfig::from_opts(&typo).is_err());
+}
+
+#[test]
+fn broken_lines_do_not_stop_the_rest_being_checked() {
+    let source = "( ) One !9\n# not an id\n(x) Done\n";
+    let findings = lint(source, &LintConfig::default(), now()).unwrap();
+
+    assert_eq!(
+        rules(&findings),
+        [
+            (1, "priority-range"),
+            (2, "syntax"),
+            (3, "completed-without-date")
+        ]
+    );
+}
+
+#[test]
+fn comments_after_other_properties_are_not_syntax_errors() {
+    let source = "( ) One !1 +a\n  // note\n( ) Two !9\n";
+    let findings = lint(source, &LintConfig::default(), now()).unwrap();
+
+    assert_eq!(rules(&findings), [(3, "priority-range")]);
+}
+
+#[test]
+fn findings_render_as_text_json_and_sarif() {
+    let findings = lint("(x) Done\n", &LintConfig::default(), now()).unwrap();
+
+    assert_eq!(
+        render_findings(&findings, "a.actions", "(x) Done\n", "text").unwrap(),
+        "a.actions:1:1: warning[completed-without-date]: Done is completed but has no %date\n"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(
+        &render_findings(&findings, "a.actions", "(x) Done\n", "json").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(json[0]["rule"], "completed-without-date");
+    assert_eq!(json[0]["severity"], "warning");
+
+    let sarif: serde_json::Value = serde_json::from_str(
+        &render_findings(&findings, "a.actions", "(x) Done\n", "sarif").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(sarif["version"], "2.1.0");
+    let result = &sarif["runs"][0]["results"][0];
+    assert_eq!(result["ruleId"], "completed-without-date");
+    assert_eq!(result["level"], "warning");
+    assert_eq!(
+        result["locations"][0]["physicalLocation"]["region"]["startLine"],
+        1
+    );
+    assert_eq!(
+        sarif["runs"][0]["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .len(),
+        RULES.len()
+    );
+    assert!(render_findings(&findings, "a.actions", "(x) Done\n", "xml").is_err());
+}
+
+#[test]
+fn blockers_have_to_be_somewhere_in_the_workspace() {
+    let source = "( ) Report +blocked-by-01951111cfa6718db303d7107f4005b3\n( ) Own #01951112cfa6718db303d7107f4005b3\n( ) Next +blocked-by-01951112cfa6718db303d7107f4005b3\n";
+    let findings = lint(source, &LintConfig::default(), now()).unwrap();
+    assert_eq!(rules(&findings), [(1, "unknown-blocker")]);
+    assert!(findings[0].diagnostic.message.contains("Report"));
+
+    let other = action_ids("( ) Gather #01951111cfa6718db303d7107f4005b3\n").unwrap();
+    let findings = lint_in_workspace(source, &LintConfig::default(), now(), &other).unwrap();
+    assert!(findings.is_empty());
+}
+
+#[test]
+fn sarif_columns_count_utf16_code_units() {
+    let source = "( ) Café 😀 !9\n";
+    let findings = lint(source, &LintConfig::default(), now()).unwrap();
+    assert_eq!(rules(&findings), [(1, "priority-range")]);
+    // tree-sitter puts the priority at byte 15, which is 12 code units in
+    assert_eq!(findings[0].diagnostic.column, 16);
+
+    let sarif = sarif(&findings, "a.actions", source);
+    let region = &sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"];
+    assert_eq!(region["startColumn"], 13);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use cliche::next::*;
+use common::{now, parse};
+
+const SOURCE: &str = "\
+( ) Gather numbers !2 +office @2026-10-17 #01951111cfa6718db303d7107f4005b3
+(=) Write report +office,blocked-by-01951111cfa6718db303d7107f4005b3
+( ) Project
+\t> (-) Refactor parser !1 #0199f000cfa6718db303d7107f4005b3
+\t> (x) Old step
+( ) Water plants +home @2026-10-18
+( ) Someday !5 @2026-12-01
+(x) Done !1
+";
+
+fn rank(source: &str, contexts: &[&str]) -> Vec<RankedAction> {
+    let action_list = parse(source);
+    let options = RankOptions {
+        contexts: contexts.iter().map(|context| context.to_string()).collect(),
+        now: now(),
+    };
+    rank_actions(&action_list, &options)
+}
+
+fn names(ranked: &[RankedAction]) -> Vec<&str> {
+    ranked.iter().map(|action| action.name.as_str()).collect()
+}
+
+#[test]
+fn ranks_open_leaves_by_their_factors() {
+    let ranked = rank(SOURCE, &[]);
+    // the project is left out since it still has open work underneath it, and the tie stays in
+    // file order
+    assert_eq!(
+        names(&ranked),
+        [
+            "Gather numbers",
+            "Refactor parser",
+            "Water plants",
+            "Someday",
+            "Write report"
+        ]
+    );
+
+    let gather = &ranked[0];
+    assert_eq!(gather.path, [0]);
+    assert_eq!(gather.short_id.as_deref(), Some("0195"));
+    assert_eq!(gather.score, 80);
+    assert_eq!(
+        gather.factors,
+        [
+            Factor {
+                kind: FactorKind::Priority,
+                points: 30,
+                reason: "priority 2".to_string(),
+            },
+            Factor {
+                kind: FactorKind::DoDate,
+                points: 50,
+                reason: "overdue by 1 day".to_string(),
+            },
+        ]
+    );
+    assert!(ranked[3].factors.is_empty());
+}
+
+#[test]
+fn being_in_a_context_favours_what_can_be_done_there() {
+    let ranked = rank(SOURCE, &["@home"]);
+    assert_eq!(
+        names(&ranked)[..3],
+        ["Refactor parser", "Water plants", "Gather numbers"]
+    );
+    let water = &ranked[1];
+    assert_eq!(water.score, 65);
+    assert_eq!(water.factors[1].reason, "matches @home");
+    assert_eq!(ranked[2].factors[2].reason, "needs @office");
+}
+
+#[test]
+fn work_sitting_in_progress_gains_on_the_rest() {
+    let ranked = rank(SOURCE, &[]);
+    let refactor = ranked[1].factors[1].clone();
+    assert_eq!(refactor.kind, FactorKind::InProgress);
+    // the id was handed out mid october 2025, so the age is capped
+    assert_eq!(refactor.points, 40);
+    assert!(refactor.reason.starts_with("in progress, created "));
+
+    let without_id = rank("( ) Other\n(-) Started\n", &[]);
+    assert_eq!(names(&without_id), ["Started", "Other"]);
+    assert_eq!(without_id[0].factors[0].reason, "in progress");
+    assert_eq!(without_id[0].score, 20);
+}
+
+#[test]
+fn anything_still_waiting_sinks_to_the_bottom() {
+    let source = "\
+( ) Gather numbers #01951111cfa6718db303d7107f4005b3
+( ) Write up +blocked-by-01951111cfa6718db303d7107f4005b3
+\t> ( ) Outline !1
+( ) Later
+";
+    let ranked = rank(source, &[]);
+    assert_eq!(names(&ranked), ["Gather numbers", "Later", "Outline"]);
+    assert_eq!(ranked[2].score, -60);
+    assert_eq!(ranked[2].factors[1].reason, "waiting on Gather numbers");
+
+    let finished = rank(&source.replacen("( ) Gather", "(x) Gather", 1), &[]);
+    assert_eq!(names(&finished), ["Outline", "Later"]);
+}
+
+#[test]
+fn explains_each_pick() {
+    // without its id nothing says how long the refactor has been going
+    let source = SOURCE.replace(" #0199f000cfa6718db303d7107f4005b3", "");
+    let ranked = rank(&source, &["@office"]);
+    assert_eq!(
+        render_ranking(&ranked[..4]),
+        "\
+1. Gather numbers #0195 (105)
+   priority 2 +30, overdue by 1 day +50, matches @office +25
+2. Refactor parser (60)
+   priority 1 +40, in progress +20
+3. Water plants (15)
+   due today +40, needs @home -25
+4. Someday (0)
+   nothing stands out
+"
+    );
+}
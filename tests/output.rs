@@ -0,0 +1,70 @@
+mod common;
+
+use cliche::entities::*;
+use cliche::output::*;
+use common::parse;
+
+const SOURCE: &str = "( ) Project !1 +work\n\t> ( ) Subtask @2026-10-20 09:00\n(x) Done\n";
+
+#[test]
+fn native_output_reads_back_as_the_same_actions() {
+    let action_list = parse(SOURCE);
+
+    let rendered = render(&action_list, "native").unwrap();
+
+    assert_eq!(rendered, SOURCE);
+    assert_eq!(parse(&rendered), action_list);
+}
+
+#[test]
+fn ndjson_output_has_one_action_per_line() {
+    let action_list = parse(SOURCE);
+
+    let rendered = render(&action_list, "ndjson").unwrap();
+    let lines: Vec<serde_json::Value> = rendered
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1]["path"], serde_json::json!([0, 0]));
+    assert_eq!(lines[1]["depth"], 1);
+    assert_eq!(lines[1]["common"]["name"], "Subtask");
+}
+
+#[test]
+fn table_output_lines_up_columns() {
+    let action_list = open_actions(&parse(SOURCE));
+
+    let rendered = render(&action_list, "table").unwrap();
+
+    assert_eq!(
+        rendered,
+        "STATE  PRIORITY  NAME       CONTEXTS  DO                ID\n\
+         ( )    1         Project    @work\n\
+         ( )                Subtask            2026-10-20 09:00\n"
+    );
+    assert!(render(&action_list, "yaml").is_err());
+}
+
+#[test]
+fn table_output_shows_short_ids_the_way_selectors_take_them() {
+    let action_list = parse(
+        "( ) One #01951111cfa6718db303d7107f4005b3\n( ) Two #01952222cfa6718db303d7107f4005b3\n",
+    );
+
+    let rendered = render(&action_list, "table").unwrap();
+    let short_id = rendered
+        .lines()
+        .nth(1)
+        .unwrap()
+        .split_whitespace()
+        .last()
+        .unwrap();
+
+    assert_eq!(short_id, "#01951");
+    assert_eq!(
+        cliche::selector::select(&action_list, short_id).unwrap(),
+        [vec![0]]
+    );
+}
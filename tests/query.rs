@@ -0,0 +1,173 @@
+mod common;
+
+use cliche::entities::*;
+use cliche::query::*;
+use common::{now, parse};
+use serde_json::Value;
+
+const SOURCE: &str = "\
+( ) Project !1 +work @2026-10-19 09:00
+\t> ( ) Call supplier !2 +work,phone @2026-10-25 09:00
+\t> (x) Send invoice !3 %2026-10-10 10:00
+( ) Weekly review !4 +home
+(-) Paint fence $before winter
+";
+
+fn entries(source: &str) -> Vec<Value> {
+    let action_list = parse(source);
+    flatten(&action_list)
+        .iter()
+        .map(|action| serde_json::to_value(action).unwrap())
+        .collect()
+}
+
+fn names(groups: &[Group]) -> Vec<String> {
+    groups
+        .iter()
+        .flat_map(|group| &group.actions)
+        .map(|action| action["common"]["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn filtered(filter: &str) -> Vec<String> {
+    let options = QueryOptions {
+        filter: Some(filter.to_string()),
+        ..QueryOptions::default()
+    };
+    names(&query_actions(&entries(SOURCE), &options, now()).unwrap())
+}
+
+#[test]
+fn filters_on_properties() {
+    assert_eq!(filtered("priority <= 2"), ["Project", "Call supplier"]);
+    assert_eq!(filtered("state = closed"), ["Send invoice"]);
+    assert_eq!(filtered("state = in-progress"), ["Paint fence"]);
+    assert_eq!(filtered("context = @phone"), ["Call supplier"]);
+    assert_eq!(filtered("context in (home, phone)").len(), 2);
+    assert_eq!(filtered("description ~ WINTER"), ["Paint fence"]);
+    assert_eq!(filtered("parent = Project").len(), 2);
+    assert_eq!(filtered("name = 'Weekly review'"), ["Weekly review"]);
+}
+
+#[test]
+fn missing_values_only_match_not_equal() {
+    assert_eq!(filtered("priority > 0").len(), 4);
+    assert_eq!(filtered("priority != 1").len(), 4);
+    assert_eq!(
+        filtered("context != work"),
+        ["Send invoice", "Weekly review", "Paint fence"]
+    );
+}
+
+#[test]
+fn combines_with_and_or_not() {
+    assert_eq!(
+        filtered("state = open and (priority = 1 or context = home)"),
+        ["Project", "Weekly review"]
+    );
+    assert_eq!(filtered("not state = open"), ["Send invoice"]);
+    assert_eq!(filtered("context not in (work)").len(), 3);
+}
+
+#[test]
+fn compares_relative_and_absolute_dates() {
+    assert_eq!(filtered("do < tomorrow+1d"), ["Project"]);
+    assert_eq!(filtered("do < today+1w"), ["Project"]);
+    assert_eq!(filtered("do >= 2026-10-20").len(), 1);
+    assert_eq!(filtered("completed > now-2w"), ["Send invoice"]);
+}
+
+#[test]
+fn rejects_malformed_queries() {
+    for query in [
+        "bogus = 1",
+        "priority <= high",
+        "priority",
+        "(state = open",
+        "do < soon",
+        "do < today+3y",
+        // past the dates there can be, rather than a panic
+        "do < today+999999999999d",
+        "do < today+99999999d",
+        "completed in (today, now+9999999999w)",
+    ] {
+        let options = QueryOptions {
+            filter: Some(query.to_string()),
+            ..QueryOptions::default()
+        };
+        assert!(
+            query_actions(&entries(SOURCE), &options, now()).is_err(),
+            "{}",
+            query
+        );
+    }
+    assert!(parse_duration("14d").is_ok());
+    assert!(parse_duration("999999999999999w").is_err());
+}
+
+#[test]
+fn sorts_with_missing_values_last_and_limits_after() {
+    let options = QueryOptions {
+        sort: Some("-priority".to_string()),
+        limit: Some(3),
+        ..QueryOptions::default()
+    };
+    assert_eq!(
+        names(&query_actions(&entries(SOURCE), &options, now()).unwrap()),
+        ["Weekly review", "Send invoice", "Call supplier"]
+    );
+
+    let options = QueryOptions {
+        sort: Some("state,name".to_string()),
+        ..QueryOptions::default()
+    };
+    assert_eq!(
+        names(&query_actions(&entries(SOURCE), &options, now()).unwrap()),
+        [
+            "Paint fence",
+            "Call supplier",
+            "Project",
+            "Weekly review",
+            "Send invoice"
+        ]
+    );
+}
+
+#[test]
+fn groups_in_order_of_first_appearance() {
+    let options = QueryOptions {
+        group_by: Some("context".to_string()),
+        ..QueryOptions::default()
+    };
+    let groups = query_actions(&entries(SOURCE), &options, now()).unwrap();
+    let keys: Vec<_> = groups
+        .iter()
+        .map(|group| group.key.clone().unwrap())
+        .collect();
+    assert_eq!(keys, ["@work", "@phone", "(no context)", "@home"]);
+    assert_eq!(groups[0].actions.len(), 2);
+
+    let options = QueryOptions {
+        group_by: Some("parent".to_string()),
+        ..QueryOptions::default()
+    };
+    let groups = query_actions(&entries(SOURCE), &options, now()).unwrap();
+    assert_eq!(groups[0].key.as_deref(), Some("(root)"));
+    assert_eq!(groups[1].key.as_deref(), Some("Project"));
+}
+
+#[test]
+fn numbers_follow_the_order_actions_are_shown_in() {
+    let options = QueryOptions {
+        group_by: Some("context".to_string()),
+        ..QueryOptions::default()
+    };
+    let mut groups = query_actions(&entries(SOURCE), &options, now()).unwrap();
+    let shown = number_actions(&mut groups);
+
+    assert_eq!(shown, [vec![0], vec![0, 0], vec![0, 1], vec![2], vec![1]]);
+    // the call shows up under both @work and @phone with the same number
+    assert_eq!(groups[0].actions[1]["number"], 2);
+    assert_eq!(groups[1].actions[0]["number"], 2);
+    assert_eq!(groups[2].actions[0]["number"], 3);
+}
@@ -0,0 +1,325 @@
+use chrono::{DateTime, Local, NaiveTime, TimeZone, Weekday};
+use cliche::entities::*;
+use cliche::recurrence::*;
+use cliche::transitions::TransitionPolicy;
+use cliche::*;
+use uuid::Uuid;
+
+fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+    Local
+        .with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+        .unwrap()
+}
+
+fn time(hour: u32, minute: u32) -> Option<NaiveTime> {
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+fn new_id() -> Uuid {
+    Uuid::from_u128(0x0195_0000_0000_7000_8000_0000_0000_0001)
+}
+
+#[test]
+fn every_way_of_writing_a_repeat_reads_the_same() {
+    let review = Recurrence::Weekly {
+        days: vec![Weekday::Mon, Weekday::Thu],
+        time: time(9, 0),
+    };
+    for text in [
+        "RW Mon Thurs 09:00",
+        "RW Thurs Mon 09:00AM",
+        "every mon,thu at 09:00",
+        "every Thursday Monday at 9:00",
+        "FREQ=WEEKLY;BYDAY=MO,TH;BYHOUR=9;BYMINUTE=0",
+        "RRULE:FREQ=DAILY;BYDAY=TH,MO;BYHOUR=9",
+    ] {
+        assert_eq!(text.parse::<Recurrence>().unwrap(), review, "{}", text);
+    }
+    assert_eq!(review.to_string(), "RW Mon Thurs 09:00");
+    assert_eq!(
+        review.to_rrule(),
+        "FREQ=WEEKLY;BYDAY=MO,TH;BYHOUR=9;BYMINUTE=0"
+    );
+    assert_eq!(review.to_rrule().parse::<Recurrence>().unwrap(), review);
+
+    let daily = "every day at 01:05PM".parse::<Recurrence>().unwrap();
+    assert_eq!(daily, Recurrence::Daily { time: time(13, 5) });
+    assert_eq!(daily.to_string(), "RDa 13:05");
+    assert_eq!("RW".parse::<Recurrence>().unwrap().to_string(), "RW");
+}
+
+#[test]
+fn refuses_repeats_the_file_cannot_hold() {
+    for text in [
+        "FREQ=MONTHLY;BYDAY=TU",
+        "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO",
+        "FREQ=DAILY;COUNT=3",
+        "every week at 09:00",
+        "every 2w at 09:00",
+        "every 0d",
+        "every 2 fortnights",
+        "every someday",
+    ] {
+        assert!(text.parse::<Recurrence>().is_err(), "{}", text);
+    }
+    assert!(
+        "every 2w at 09:00"
+            .parse::<Recurrence>()
+            .unwrap_err()
+            .contains("from the do-date")
+    );
+}
+
+#[test]
+fn intervals_are_kept_in_the_context_list() {
+    let fortnightly = Recurrence::Interval {
+        count: 2,
+        period: Period::Weeks,
+    };
+    for text in ["every 2w", "every 2 weeks", "FREQ=WEEKLY;INTERVAL=2"] {
+        assert_eq!(text.parse::<Recurrence>().unwrap(), fortnightly, "{}", text);
+    }
+    assert_eq!(fortnightly.to_rrule(), "FREQ=WEEKLY;INTERVAL=2");
+    let monthly = Recurrence::Interval {
+        count: 1,
+        period: Period::Months,
+    };
+    for text in ["every month", "every 1m", "RRULE:FREQ=MONTHLY"] {
+        assert_eq!(text.parse::<Recurrence>().unwrap(), monthly, "{}", text);
+    }
+    // one day or week is the grammar's own repeat
+    assert_eq!(
+        "every 1w".parse::<Recurrence>().unwrap(),
+        "RW".parse::<Recurrence>().unwrap()
+    );
+
+    let source =
+        "( ) Pay rent +home,every-1m @2026-10-31 09:00 #01951111-cfa6-718d-b303-d7107f4005b3\n";
+    let action_list = get_action_list_struct(&serde_json::json!({}), source).unwrap();
+    let rent = &action_list.first().unwrap().common;
+    assert_eq!(rent.recurrence, Some(monthly.clone()));
+    assert_eq!(rent.context_list, Some(vec!["@home".to_string()]));
+    assert_eq!(format!("{}\n", rent), source);
+
+    // a month on from the 31st is the last day of november, and the time comes from the do-date
+    let paid = complete_action(
+        source,
+        "Pay rent",
+        TransitionPolicy::None,
+        RecurFrom::DoDate,
+        at(31, 10, 0),
+        new_id,
+    )
+    .unwrap();
+    assert!(paid.ends_with(
+        "( ) Pay rent +home,every-1m @2026-11-30 09:00 #01950000-0000-7000-8000-000000000001\n"
+    ));
+    assert_eq!(
+        monthly.next(at(31, 9, 0)),
+        Local.with_ymd_and_hms(2026, 11, 30, 9, 0, 0).unwrap()
+    );
+    assert_eq!(fortnightly.next(at(12, 9, 0)), at(26, 9, 0));
+
+    let fortnightly_update = edits::ActionUpdate {
+        recurrence: Some(fortnightly),
+        ..Default::default()
+    };
+    let updated = edits::update_action(
+        "( ) Sprint review +work @2026-10-23 15:00 RDa\n",
+        "Sprint review",
+        &fortnightly_update,
+    )
+    .unwrap();
+    assert_eq!(
+        updated,
+        "( ) Sprint review +work,every-2w @2026-10-23 15:00\n"
+    );
+    let stop = edits::ActionUpdate {
+        remove_recurrence: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        edits::update_action(&updated, "Sprint review", &stop).unwrap(),
+        "( ) Sprint review +work @2026-10-23 15:00\n"
+    );
+}
+
+#[test]
+fn reads_the_repeat_from_the_do_date() {
+    let action_list = get_action_list_struct(
+        &serde_json::json!({}),
+        "( ) Review @2026-10-12 09:00 D30 RW Mon 09:00\n( ) Plants @2026-10-12 RDa\n( ) Once @2026-10-12\n",
+    )
+    .unwrap();
+    let repeats: Vec<Option<String>> = action_list
+        .iter()
+        .map(|action| action.common.recurrence.as_ref().map(|r| r.to_string()))
+        .collect();
+    assert_eq!(
+        repeats,
+        [
+            Some("RW Mon 09:00".to_string()),
+            Some("RDa".to_string()),
+            None
+        ]
+    );
+}
+
+#[test]
+fn next_occurrences_land_after_now() {
+    let monday = Recurrence::Weekly {
+        days: vec![Weekday::Mon],
+        time: None,
+    };
+    // the 12th is a monday, done late on sunday the 18th
+    assert_eq!(monday.next(at(12, 9, 0)), at(19, 9, 0));
+    assert_eq!(monday.next_after(at(5, 9, 0), at(18, 20, 0)), at(19, 9, 0));
+
+    let daily = Recurrence::Daily { time: time(7, 30) };
+    assert_eq!(daily.next(at(12, 9, 0)), at(13, 7, 30));
+
+    let weekly = Recurrence::Weekly {
+        days: Vec::new(),
+        time: None,
+    };
+    assert_eq!(weekly.next(at(14, 18, 0)), at(21, 18, 0));
+}
+
+#[test]
+fn completing_a_repeating_action_schedules_the_next_one() {
+    let source = "\
+( ) Weekly review @2026-10-12 09:00 RW Mon #01951111cfa6718db303d7107f4005b3
+\t> ( ) Inbox zero
+( ) Other
+";
+    let updated = complete_action(
+        source,
+        "Weekly review",
+        TransitionPolicy::None,
+        RecurFrom::DoDate,
+        at(13, 10, 0),
+        new_id,
+    )
+    .unwrap();
+    assert_eq!(
+        updated,
+        "\
+(x) Weekly review @2026-10-12 09:00 RW Mon %2026-10-13 10:00 #01951111cfa6718db303d7107f4005b3
+\t> ( ) Inbox zero
+( ) Weekly review @2026-10-19 09:00 RW Mon #01950000-0000-7000-8000-000000000001
+( ) Other
+"
+    );
+
+    // the earlier record keeps the name, the open one is what completing it again means
+    let next_week = complete_action(
+        &updated,
+        "Weekly review",
+        TransitionPolicy::None,
+        RecurFrom::DoDate,
+        at(20, 10, 0),
+        || Uuid::from_u128(0x0195_0000_0000_7000_8000_0000_0000_0002),
+    )
+    .unwrap();
+    assert!(next_week.contains(
+        "(x) Weekly review @2026-10-19 09:00 RW Mon %2026-10-20 10:00 #01950000-0000-7000-8000-000000000001\n"
+    ));
+    assert!(next_week.contains(
+        "( ) Weekly review @2026-10-26 09:00 RW Mon #01950000-0000-7000-8000-000000000002\n"
+    ));
+    let two_open = "( ) Review @2026-10-12 09:00 RW Mon\n( ) Review @2026-10-19 09:00 RW Mon\n";
+    assert!(
+        complete_action(
+            two_open,
+            "Review",
+            TransitionPolicy::None,
+            RecurFrom::DoDate,
+            at(13, 10, 0),
+            new_id,
+        )
+        .is_err()
+    );
+
+    // counting from completion waits a whole period after it was done
+    let from_completion = complete_action(
+        "( ) Health\n\t> ( ) Stretch @2026-10-12 07:00 RDa\n",
+        "Stretch",
+        TransitionPolicy::None,
+        RecurFrom::Completion,
+        at(15, 20, 0),
+        new_id,
+    )
+    .unwrap();
+    assert!(from_completion.ends_with(
+        "\t> ( ) Stretch @2026-10-16 20:00 RDa #01950000-0000-7000-8000-000000000001\n"
+    ));
+}
+
+#[test]
+fn completing_without_a_repeat_or_twice_adds_nothing() {
+    let once = "( ) Once @2026-10-12 09:00\n";
+    let done = complete_action(
+        once,
+        "Once",
+        TransitionPolicy::None,
+        RecurFrom::DoDate,
+        at(13, 10, 0),
+        new_id,
+    )
+    .unwrap();
+    assert_eq!(done.lines().count(), 1);
+
+    let finished = "(x) Review @2026-10-12 09:00 RW Mon %2026-10-12 10:00\n";
+    let again = complete_action(
+        finished,
+        "Review",
+        TransitionPolicy::None,
+        RecurFrom::DoDate,
+        at(13, 10, 0),
+        new_id,
+    )
+    .unwrap();
+    assert_eq!(again, finished);
+
+    let no_date = CommonActionProperties {
+        name: "Repeats".to_string(),
+        recurrence: Some(Recurrence::Daily { time: None }),
+        ..Default::default()
+    };
+    assert!(edits::create_action("", &no_date, None).is_err());
+}
+
+#[test]
+fn updating_the_repeat_keeps_the_do_date() {
+    let source = "( ) Standup @2026-10-12 09:00 D15 RDa\n( ) Retro @2026-10-16 14:00 D60\n";
+    let weekly = edits::ActionUpdate {
+        recurrence: Some("every mon,wed".parse().unwrap()),
+        ..Default::default()
+    };
+    let updated = edits::update_action(source, "Standup", &weekly).unwrap();
+    assert_eq!(
+        updated,
+        "( ) Standup @2026-10-12 09:00 D15 RW Mon Wen\n( ) Retro @2026-10-16 14:00 D60\n"
+    );
+
+    let stop = edits::ActionUpdate {
+        remove_recurrence: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        edits::update_action(&updated, "Standup", &stop).unwrap(),
+        "( ) Standup @2026-10-12 09:00 D15\n( ) Retro @2026-10-16 14:00 D60\n"
+    );
+
+    let fridays = edits::ActionUpdate {
+        recurrence: Some(Recurrence::Weekly {
+            days: vec![Weekday::Fri],
+            time: None,
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        edits::update_action(source, "Retro", &fridays).unwrap(),
+        "( ) Standup @2026-10-12 09:00 D15 RDa\n( ) Retro @2026-10-16 14:00 D60 RW Fri\n"
+    );
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use cliche::entities::*;
+use cliche::selector::*;
+use common::parse;
+
+const SOURCE: &str = "\
+( ) Project !1 +work #01951111cfa6718db303d7107f4005b3
+\t> ( ) Subtask !2 +work,phone
+\t> (x) Done subtask !3
+( ) Weekly review !4 +home #0195222200007000800000000000000a
+( ) Subtask
+";
+
+fn names(action_list: &ActionList, selector: &str) -> Vec<String> {
+    select(action_list, selector)
+        .unwrap()
+        .iter()
+        .map(|path| get_action(action_list, path).unwrap().common.name.clone())
+        .collect()
+}
+
+#[test]
+fn selects_by_full_or_prefix_id() {
+    let action_list = parse(SOURCE);
+
+    assert_eq!(names(&action_list, "#0195111"), ["Project"]);
+    assert_eq!(names(&action_list, "#01951111-cfa6"), ["Project"]);
+    assert_eq!(
+        names(&action_list, "01951111-cfa6-718d-b303-d7107f4005b3"),
+        ["Project"]
+    );
+    assert_eq!(names(&action_list, "#0195").len(), 2);
+    // without the `#` only a whole id is one, the start of one is a position or a name
+    assert_eq!(
+        names(&action_list, "01951111cfa6718db303d7107f4005b3"),
+        ["Project"]
+    );
+    assert!(names(&action_list, "0195").is_empty());
+    assert!(names(&action_list, "0195111c").is_empty());
+    assert!(select(&action_list, "#xyz").is_err());
+}
+
+#[test]
+fn selects_by_name_path_and_position() {
+    let action_list = parse(SOURCE);
+
+    assert_eq!(names(&action_list, "Subtask"), ["Subtask", "Subtask"]);
+    assert_eq!(names(&action_list, "Project/Subtask"), ["Subtask"]);
+    assert_eq!(
+        select(&action_list, "Project/Subtask").unwrap(),
+        vec![vec![0, 0]]
+    );
+    assert_eq!(names(&action_list, "weekly review"), ["Weekly review"]);
+    assert_eq!(names(&action_list, "~wkly rvw"), ["Weekly review"]);
+    // a bare name never falls back to a loose match
+    assert!(names(&action_list, "weekly").is_empty());
+    assert!(names(&action_list, "Weekly reveiw").is_empty());
+    assert_eq!(names(&action_list, "3"), ["Done subtask"]);
+    assert!(names(&action_list, "9").is_empty());
+}
+
+#[test]
+fn selects_names_that_look_like_other_selectors() {
+    let action_list = parse("( ) Intro\n( ) 2024\n( ) ~draft\n");
+
+    assert_eq!(names(&action_list, "2"), ["2024"]);
+    assert!(names(&action_list, "2024").is_empty());
+    assert_eq!(names(&action_list, "=2024"), ["2024"]);
+    assert_eq!(names(&action_list, "=~draft"), ["~draft"]);
+}
+
+#[test]
+fn selects_by_predicates() {
+    let action_list = parse(SOURCE);
+
+    assert_eq!(
+        names(&action_list, "state:open priority:<3 context:@work"),
+        ["Project", "Subtask"]
+    );
+    assert_eq!(names(&action_list, "state:done"), ["Done subtask"]);
+    assert_eq!(names(&action_list, "context:home"), ["Weekly review"]);
+    assert_eq!(
+        names(&action_list, "priority:>=3 state:closed"),
+        ["Done subtask"]
+    );
+    assert!(select(&action_list, "priority:high").is_err());
+}
+
+#[test]
+fn select_one_explains_misses_and_ambiguity() {
+    let action_list = parse(SOURCE);
+
+    assert_eq!(select_one(&action_list, "Weekly review").unwrap(), vec![1]);
+
+    let missing = select_one(&action_list, "Groceries").unwrap_err();
+    assert_eq!(missing, "no action matches Groceries");
+
+    let ambiguous = select_one(&action_list, "Subtask").unwrap_err();
+    assert!(ambiguous.starts_with("2 actions match Subtask"));
+}
@@ -0,0 +1,94 @@
+use cliche::subtree::*;
+
+const SOURCE: &str = "\
+( ) Project #01951111cfa6718db303d7107f4005b3
+\t> ( ) Phase one !2
+\t\t>> ( ) Step a
+\t\t>> ( ) Step b
+\t> ( ) Phase two
+( ) Other
+";
+
+#[test]
+fn cuts_out_a_subtree_at_the_top_level() {
+    let subtree = extract_subtree(SOURCE, "Phase one").unwrap();
+    assert_eq!(subtree.depth, 1);
+    assert_eq!(
+        subtree.text,
+        "( ) Phase one !2\n\t> ( ) Step a\n\t> ( ) Step b\n"
+    );
+    assert_eq!(
+        &SOURCE[subtree.range.clone()],
+        "\t> ( ) Phase one !2\n\t\t>> ( ) Step a\n\t\t>> ( ) Step b\n"
+    );
+
+    let root = extract_subtree(SOURCE, "Other").unwrap();
+    assert_eq!(root.text, "( ) Other\n");
+}
+
+#[test]
+fn splices_the_edit_back_at_its_depth() {
+    let subtree = extract_subtree(SOURCE, "Phase one").unwrap();
+    let edited =
+        "( ) Phase one !1\n\t> (x) Step a\n\t>> ( ) Smaller step\n( ) Phase one and a half\n";
+    assert_eq!(
+        splice_subtree(SOURCE, &subtree, edited).unwrap(),
+        "\
+( ) Project #01951111cfa6718db303d7107f4005b3
+\t> ( ) Phase one !1
+\t\t>> (x) Step a
+\t\t\t>>> ( ) Smaller step
+\t> ( ) Phase one and a half
+\t> ( ) Phase two
+( ) Other
+"
+    );
+
+    // an untouched subtree goes back exactly as it was
+    assert_eq!(
+        splice_subtree(SOURCE, &subtree, &subtree.text).unwrap(),
+        SOURCE
+    );
+}
+
+#[test]
+fn refuses_edits_that_do_not_fit() {
+    let subtree = extract_subtree(SOURCE, "Phase one").unwrap();
+    assert!(splice_subtree(SOURCE, &subtree, "(?) Broken\n").is_err());
+
+    let too_deep = "( ) a\n\t> ( ) b\n\t\t>> ( ) c\n\t\t\t>>> ( ) d\n\t\t\t\t>>>> ( ) e\n\t\t\t\t\t>>>>> ( ) f\n";
+    assert!(
+        splice_subtree(SOURCE, &subtree, too_deep)
+            .unwrap_err()
+            .contains("f would be nested deeper")
+    );
+
+    let story = "( ) Phase one *Big picture\n";
+    assert!(
+        splice_subtree(SOURCE, &subtree, story)
+            .unwrap_err()
+            .contains("cannot have a story")
+    );
+
+    let copied = "( ) Phase one #01951111cfa6718db303d7107f4005b3\n";
+    assert!(
+        splice_subtree(SOURCE, &subtree, copied)
+            .unwrap_err()
+            .contains("Project and Phase one both have the id")
+    );
+}
+
+#[test]
+fn reopens_with_the_problems_on_top() {
+    let reopened = with_problems("( ) Broken (\n", "1:12: error: unexpected `(`");
+    assert_eq!(
+        reopened.lines().nth(2),
+        Some("// 1:12: error: unexpected `(`")
+    );
+    assert!(reopened.ends_with("\n( ) Broken (\n"));
+
+    // a second round replaces the comments from the first
+    let again = with_problems(&reopened, "still broken");
+    assert_eq!(again.matches("//").count(), 3);
+    assert_eq!(strip_comments(&again), "( ) Broken (\n");
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, Local, TimeZone};
+use cliche::entities::*;
+use cliche::transitions::*;
+
+const PROJECT: &str = "( ) Project\n\t> ( ) first step\n\t> (x) second step %2026-10-01 08:00\n";
+
+fn now() -> DateTime<Local> {
+    Local.with_ymd_and_hms(2026, 10, 18, 17, 30, 0).unwrap()
+}
+
+#[test]
+fn completing_stamps_and_reopening_clears_the_completed_date() {
+    let completed = transition_action(
+        "( ) chore !2\n",
+        "chore",
+        ActionState::Completed,
+        TransitionPolicy::None,
+        now(),
+    )
+    .unwrap();
+    assert_eq!(completed, "(x) chore !2 %2026-10-18 17:30\n");
+
+    let reopened = transition_action(
+        &completed,
+        "chore",
+        ActionState::NotStarted,
+        TransitionPolicy::None,
+        now(),
+    )
+    .unwrap();
+    assert_eq!(reopened, "( ) chore !2\n");
+}
+
+#[test]
+fn cascading_closes_open_descendants_and_keeps_earlier_stamps() {
+    let updated = transition_action(
+        PROJECT,
+        "Project",
+        ActionState::Completed,
+        TransitionPolicy::Cascade,
+        now(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        updated,
+        "(x) Project %2026-10-18 17:30\n\t> (x) first step %2026-10-18 17:30\n\t> (x) second step %2026-10-01 08:00\n"
+    );
+
+    let cancelled = transition_action(
+        PROJECT,
+        "Project",
+        ActionState::Cancelled,
+        TransitionPolicy::Cascade,
+        now(),
+    )
+    .unwrap();
+    assert_eq!(
+        cancelled,
+        "(_) Project\n\t> (_) first step\n\t> (x) second step %2026-10-01 08:00\n"
+    );
+}
+
+#[test]
+fn refusing_keeps_parents_open_while_children_are() {
+    let error = transition_action(
+        PROJECT,
+        "Project",
+        ActionState::Completed,
+        TransitionPolicy::Refuse,
+        now(),
+    )
+    .unwrap_err();
+    assert!(error.contains("first step"));
+
+    let started = transition_action(
+        PROJECT,
+        "Project",
+        ActionState::InProgress,
+        TransitionPolicy::Refuse,
+        now(),
+    )
+    .unwrap();
+    assert!(started.starts_with("(-) Project\n"));
+}
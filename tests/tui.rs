@@ -0,0 +1,177 @@
+mod common;
+
+use cliche::tui::*;
+use common::now;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+const SOURCE: &str = "\
+( ) Project !2 +work
+\t> ( ) Outline
+\t> ( ) Draft
+( ) Water plants @2026-10-18 09:00
+";
+
+fn app(source: &str) -> App<MemoryStore> {
+    App::new(source, MemoryStore::default()).unwrap()
+}
+
+// each character is a key press, except that `<esc>`, `<enter>`, `<tab>` and `<bs>` stand for
+// those keys
+fn press(app: &mut App<MemoryStore>, keys: &str) {
+    let mut rest = keys;
+    while let Some(c) = rest.chars().next() {
+        let (code, length) = [
+            ("<esc>", KeyCode::Esc),
+            ("<enter>", KeyCode::Enter),
+            ("<tab>", KeyCode::Tab),
+            ("<bs>", KeyCode::Backspace),
+        ]
+        .into_iter()
+        .find(|(name, _)| rest.starts_with(name))
+        .map(|(name, code)| (code, name.len()))
+        .unwrap_or((KeyCode::Char(c), c.len_utf8()));
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE), now());
+        rest = &rest[length..];
+    }
+}
+
+fn screen(app: &mut App<MemoryStore>) -> Vec<String> {
+    let mut terminal = Terminal::new(TestBackend::new(60, 8)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let buffer = terminal.backend().buffer().clone();
+    (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+fn shown(app: &App<MemoryStore>) -> Vec<String> {
+    app.rows()
+        .iter()
+        .map(|row| row.action.common.name.clone())
+        .collect()
+}
+
+#[test]
+fn draws_the_tree_with_the_cursor_on_the_first_action() {
+    let mut app = app(SOURCE);
+    app.title = "active.actions".to_string();
+    let lines = screen(&mut app);
+    assert_eq!(lines[0], "active.actions");
+    assert_eq!(lines[1], "▾ ( ) Project !2 @work");
+    assert_eq!(lines[2], "    ( ) Outline");
+    assert_eq!(lines[4], "  ( ) Water plants @2026-10-18 09:00");
+    assert!(lines[7].starts_with("space done"));
+}
+
+#[test]
+fn moves_and_folds_like_vim() {
+    let mut app = app(SOURCE);
+    press(&mut app, "jj");
+    assert_eq!(app.selected(), Some(vec![0, 1]));
+    press(&mut app, "h");
+    assert_eq!(app.selected(), Some(vec![0]));
+    press(&mut app, "h");
+    assert_eq!(shown(&app), ["Project", "Water plants"]);
+    press(&mut app, "G");
+    assert_eq!(app.selected(), Some(vec![1]));
+    press(&mut app, "gl");
+    assert_eq!(shown(&app).len(), 4);
+    press(&mut app, "l");
+    assert_eq!(app.selected(), Some(vec![0, 0]));
+    press(&mut app, "k<tab>");
+    assert_eq!(shown(&app).len(), 2);
+    assert!(app.store.saves.is_empty());
+}
+
+#[test]
+fn toggles_states_and_saves_every_change() {
+    let mut app = app(SOURCE);
+    press(&mut app, "j ");
+    assert!(app.source().contains("\t> (x) Outline %2026-10-18 12:00\n"));
+    assert_eq!(app.store.saves.last().unwrap(), app.source());
+
+    press(&mut app, " ");
+    assert!(app.source().contains("\t> ( ) Outline\n"));
+    press(&mut app, "j-");
+    assert!(app.source().contains("\t> (-) Draft\n"));
+    press(&mut app, "-");
+    assert!(app.source().contains("\t> ( ) Draft\n"));
+    assert_eq!(app.store.saves.len(), 4);
+}
+
+#[test]
+fn edits_the_name_priority_and_contexts_inline() {
+    let mut app = app(SOURCE);
+    press(&mut app, "r<bs><bs><bs><bs><bs><bs><bs>Launch<enter>");
+    press(&mut app, "p<bs>1<enter>");
+    press(&mut app, "c<bs><bs><bs><bs>home, errands<enter>");
+    assert!(app.source().starts_with("( ) Launch !1 +home,errands\n"));
+
+    press(&mut app, "p<bs><enter>");
+    assert!(app.source().starts_with("( ) Launch +home,errands\n"));
+
+    // escape leaves the file alone
+    press(&mut app, "rNope<esc>");
+    assert!(app.source().starts_with("( ) Launch +home,errands\n"));
+
+    press(&mut app, "pmany<enter>");
+    assert_eq!(app.message(), Some("many is not a priority"));
+    assert_eq!(app.store.saves.len(), 4);
+}
+
+#[test]
+fn filters_with_a_selector_keeping_the_parents_of_matches() {
+    let mut app = app(SOURCE);
+    press(&mut app, "/~draf<enter>");
+    assert_eq!(shown(&app), ["Project", "Draft"]);
+    assert!(screen(&mut app)[0].ends_with("/ ~draf"));
+    press(&mut app, "j ");
+    assert!(app.source().contains("(x) Draft"));
+    press(&mut app, "<esc>");
+    assert_eq!(shown(&app).len(), 4);
+    assert!(!app.quit);
+    press(&mut app, "<esc>");
+    assert!(app.quit);
+}
+
+#[test]
+fn undoes_and_redoes_changes() {
+    let mut app = app(SOURCE);
+    press(&mut app, "jj_");
+    let cancelled = app.source().to_string();
+    press(&mut app, "u");
+    assert_eq!(app.source(), SOURCE);
+    assert_eq!(app.store.saves.last().unwrap(), SOURCE);
+    app.handle_key(
+        KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+        now(),
+    );
+    assert_eq!(app.source(), cancelled);
+    press(&mut app, "uu");
+    assert_eq!(app.message(), Some("nothing to undo"));
+}
+
+#[test]
+fn completing_frees_what_was_waiting_and_schedules_repeats() {
+    let source = "\
+( ) Gather numbers @2026-10-12 09:00 RDa #01951111cfa6718db303d7107f4005b3
+(=) Write report +blocked-by-01951111cfa6718db303d7107f4005b3
+";
+    let mut app = app(source);
+    press(&mut app, " ");
+    let lines: Vec<&str> = app.source().lines().collect();
+    assert!(lines[0].starts_with("(x) Gather numbers"));
+    assert!(lines[1].starts_with("( ) Gather numbers @2026-10-19 09:00 RDa #"));
+    assert!(lines[2].starts_with("( ) Write report"));
+    // all of it is one change
+    press(&mut app, "u");
+    assert_eq!(app.source(), source);
+}
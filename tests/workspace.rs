@@ -0,0 +1,157 @@
+use cliche::edits::ActionUpdate;
+use cliche::edits::update_action;
+use cliche::workspace::*;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+fn workspace() -> Workspace {
+    Workspace::from_sources(vec![
+        (
+            PathBuf::from("home.actions"),
+            "( ) Groceries +home\n\t> ( ) Milk\n( ) Shared\n".to_string(),
+        ),
+        (
+            PathBuf::from("work.actions"),
+            "( ) Report !1 #01951111cfa6718db303d7107f4005b3\n( ) Shared\n".to_string(),
+        ),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn reads_every_file_as_one_list() {
+    let workspace = workspace();
+    let names: Vec<String> = workspace
+        .actions()
+        .iter()
+        .map(|action| action.common.name.clone())
+        .collect();
+    assert_eq!(names, ["Groceries", "Shared", "Report", "Shared"]);
+
+    let files: Vec<Option<String>> = workspace
+        .flatten()
+        .into_iter()
+        .map(|action| action.file)
+        .collect();
+    assert_eq!(
+        files,
+        [
+            Some("home.actions".to_string()),
+            Some("home.actions".to_string()),
+            Some("home.actions".to_string()),
+            Some("work.actions".to_string()),
+            Some("work.actions".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn selections_point_back_into_their_own_file() {
+    let workspace = workspace();
+    assert_eq!(workspace.locate(&[2]), Some((1, vec![0])));
+    assert_eq!(workspace.locate(&[0, 0]), Some((0, vec![0, 0])));
+    assert_eq!(workspace.locate(&[4]), None);
+
+    assert_eq!(
+        workspace.select_one("Report").unwrap(),
+        (1, "1".to_string())
+    );
+    assert_eq!(workspace.select_one("5").unwrap(), (1, "2".to_string()));
+    assert_eq!(workspace.select_one("Milk").unwrap(), (0, "2".to_string()));
+
+    let ambiguous = workspace.select_one("Shared").unwrap_err();
+    assert!(ambiguous.contains("home.actions"), "{}", ambiguous);
+    assert!(ambiguous.contains("work.actions"), "{}", ambiguous);
+    assert!(workspace.select_open("Shared").is_err());
+
+    let repeated = Workspace::from_sources(vec![
+        (
+            PathBuf::from("done.actions"),
+            "(x) Review @2026-10-12 RW Mon\n".to_string(),
+        ),
+        (
+            PathBuf::from("open.actions"),
+            "( ) Review @2026-10-19 RW Mon\n".to_string(),
+        ),
+    ])
+    .unwrap();
+    assert!(repeated.select_one("Review").is_err());
+    assert_eq!(
+        repeated.select_open("Review").unwrap(),
+        (1, "1".to_string())
+    );
+
+    let id = Uuid::parse_str("01951111cfa6718db303d7107f4005b3").unwrap();
+    assert_eq!(workspace.file_with_id(id), Some(1));
+}
+
+#[test]
+fn edits_only_touch_the_file_the_action_is_in() {
+    let workspace = workspace();
+    let (index, selector) = workspace.select_one("5").unwrap();
+    let file = &workspace.files[index];
+    let update = ActionUpdate {
+        name: Some("Renamed".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        update_action(&file.source, &selector, &update).unwrap(),
+        "( ) Report !1 #01951111cfa6718db303d7107f4005b3\n( ) Renamed\n"
+    );
+}
+
+#[test]
+fn one_broken_file_does_not_hide_the_others() {
+    let workspace = Workspace::from_sources(vec![
+        (
+            PathBuf::from("broken.actions"),
+            "( ) Fine\n>>>>>>( ) Too deep\n".to_string(),
+        ),
+        (PathBuf::from("ok.actions"), "( ) Other\n".to_string()),
+    ])
+    .unwrap();
+    assert!(!workspace.files[0].diagnostics.is_empty());
+    assert!(workspace.files[1].diagnostics.is_empty());
+    assert!(
+        workspace
+            .actions()
+            .iter()
+            .any(|action| action.common.name == "Other")
+    );
+}
+
+#[test]
+fn entries_can_be_files_directories_or_patterns() {
+    let dir = std::env::temp_dir().join(format!("cliche-workspace-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("projects")).unwrap();
+    for name in [
+        "inbox.actions",
+        "projects/b.actions",
+        "projects/a.actions",
+        "projects/notes.txt",
+    ] {
+        std::fs::write(dir.join(name), "( ) Something\n").unwrap();
+    }
+
+    let entry = |path: &str| dir.join(path).display().to_string();
+    let paths = resolve_entries(
+        &[
+            entry("inbox.actions"),
+            entry("projects"),
+            entry("*.actions"),
+        ],
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        paths,
+        [
+            dir.join("inbox.actions"),
+            dir.join("projects/a.actions"),
+            dir.join("projects/b.actions"),
+        ]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Weekday};
+use cliche::entities::*;
+use cliche::recurrence::Recurrence;
+use common::parse;
+use proptest::prelude::*;
+use tree_sitter_actions::get_test_data;
+use uuid::Uuid;
+
+#[test]
+fn writes_one_action_per_line() {
+    let source = &get_test_data()["actions"]["with_everything"]["content"];
+    let action_list = parse(source);
+
+    let written = to_action_file(&action_list);
+
+    assert_eq!(written.lines().count(), 6);
+    assert!(written.lines().next().unwrap().ends_with(" *Test Story"));
+    assert!(written.lines().nth(2).unwrap().starts_with("\t\t>> ( )"));
+    assert_eq!(parse(&written), action_list);
+}
+
+#[test]
+fn every_fixture_survives_a_round_trip() {
+    for (category, fixtures) in &get_test_data() {
+        for (name, fixture) in fixtures {
+            let action_list = parse(&fixture["content"]);
+            assert_eq!(
+                parse(&to_action_file(&action_list)),
+                action_list,
+                "{}/{}",
+                category,
+                name
+            );
+        }
+    }
+}
+
+// free text may hold anything but the property markers, and loses its outer whitespace on parse
+const TEXT: &str = "[a-zA-Z0-9][a-zA-Z0-9 ,.'?_-]{0,20}[a-zA-Z0-9]|[a-zA-Z0-9]";
+
+fn date() -> impl Strategy<Value = DateTime<Local>> {
+    (2000i32..2100, 1u32..=12, 1u32..=28, 0u32..24, 0u32..60).prop_filter_map(
+        "not a local time",
+        |(year, month, day, hour, minute)| {
+            let naive = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, 0)?;
+            Local.from_local_datetime(&naive).single()
+        },
+    )
+}
+
+fn state() -> impl Strategy<Value = ActionState> {
+    prop_oneof![
+        Just(ActionState::NotStarted),
+        Just(ActionState::Completed),
+        Just(ActionState::InProgress),
+        Just(ActionState::BlockedorAwaiting),
+        Just(ActionState::Cancelled),
+    ]
+}
+
+fn recurrence() -> impl Strategy<Value = Recurrence> {
+    let time = proptest::option::of(
+        (0u32..24, 0u32..60)
+            .prop_map(|(hour, minute)| NaiveTime::from_hms_opt(hour, minute, 0).unwrap()),
+    );
+    let day = (0u8..7).prop_map(|day| Weekday::try_from(day).unwrap());
+    prop_oneof![
+        time.clone().prop_map(|time| Recurrence::Daily { time }),
+        Just(Recurrence::Weekly {
+            days: Vec::new(),
+            time: None
+        }),
+        (proptest::collection::vec(day, 1..4), time).prop_map(|(mut days, time)| {
+            days.sort_by_key(Weekday::num_days_from_monday);
+            days.dedup();
+            Recurrence::Weekly { days, time }
+        }),
+    ]
+}
+
+fn common() -> impl Strategy<Value = CommonActionProperties> {
+    (
+        state(),
+        TEXT,
+        proptest::option::of(TEXT),
+        proptest::option::of(0usize..100),
+        proptest::option::of(proptest::collection::vec("[a-zA-Z0-9_-]{1,8}", 1..4)),
+        proptest::option::of(date()),
+        proptest::option::of(date()),
+        proptest::option::of(any::<u128>()),
+        proptest::option::of(recurrence()),
+        proptest::collection::vec(any::<u128>(), 0..3),
+    )
+        .prop_map(
+            |(
+                state,
+                name,
+                description,
+                priority,
+                contexts,
+                do_date,
+                completed,
+                id,
+                recurrence,
+                blocked_by,
+            )| {
+                CommonActionProperties {
+                    state,
+                    name,
+                    description,
+                    priority,
+                    context_list: contexts.map(|contexts| {
+                        contexts
+                            .into_iter()
+                            .map(|context| format!("@{}", context))
+                            .collect()
+                    }),
+                    id: id.map(Uuid::from_u128),
+                    // the repeat is part of the do-date, so it cannot be written without one
+                    recurrence: recurrence.filter(|_| do_date.is_some()),
+                    do_date_time: do_date,
+                    completed_date_time: completed,
+                    blocked_by: blocked_by.into_iter().map(Uuid::from_u128).collect(),
+                }
+            },
+        )
+}
+
+fn action(depth: usize) -> BoxedStrategy<Action> {
+    let children = if depth < MAX_ACTION_DEPTH {
+        proptest::collection::vec(action(depth + 1), 0..3).boxed()
+    } else {
+        Just(Vec::new()).boxed()
+    };
+    let story = if depth == 0 {
+        proptest::option::of(TEXT).boxed()
+    } else {
+        Just(None).boxed()
+    };
+    (common(), story, children)
+        .prop_map(move |(common, story, children)| Action {
+            story,
+            children,
+            ..Action::new(common, depth)
+        })
+        .boxed()
+}
+
+proptest! {
+    #[test]
+    fn parsing_what_was_written_gives_back_the_same_actions(
+        action_list in proptest::collection::vec(action(0), 0..4)
+    ) {
+        let written = to_action_file(&action_list);
+        prop_assert_eq!(parse(&written), action_list, "{}", written);
+    }
+}
+
+#[test]
+fn depth_comes_from_where_an_action_sits() {
+    // a tree built by hand, with every depth left at its default
+    let named = |name: &str| CommonActionProperties {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let mut child = Action::new(named("child"), 0);
+    child.children.push(Action::new(named("grandchild"), 0));
+    let mut root = Action::new(named("root"), 0);
+    root.children.push(child);
+
+    assert_eq!(
+        to_action_file(&[root]),
+        "( ) root\n\t> ( ) child\n\t\t>> ( ) grandchild\n"
+    );
+}